@@ -0,0 +1,332 @@
+use std::path::Path;
+use std::process::Command as Process;
+
+use ruf_audit_virtual::{
+    AuditOptions, CfgProfile, Channel, Date, FeatureSelection, RufPolicy, RustcRange, Timestamp,
+    Toolchain,
+};
+
+pub const USAGE: &str = "\
+Audit the ruf (rust unstable feature) usages of the project in the current directory.
+
+Usage: cargo ruf <COMMAND> [OPTIONS]
+
+Commands:
+  check            Check ruf issues of the locked dependencies on the rustc
+  fix              Search the newest rustc fixable by changing dependencies, and the fix
+  explain          Explain why the rustc versions searched cannot be fixed
+  lifetime <RUF>   Show the status of the ruf over rustc versions
+  diff <OLD_LOCK>  Check the rufs introduced since the old lockfile, like before `cargo update`
+  forecast         Forecast the rufs breaking on the toolchain after the rustc
+
+Options:
+  --manifest-path <PATH>   The project directory or its Cargo.toml [default: .]
+  --rustc <TOOLCHAIN>      The rustc to check, or the newest to search, like 1.70.0 or
+                           nightly-2023-05-01 [default: the active rustc for check]
+  --oldest <TOOLCHAIN>     The oldest rustc to search
+  --stable                 Simulate stable compilers, where no rufs can be used
+  --target <TRIPLE>        The target audited for [default: the host]
+  --cfg <CFG>              Extra `--cfg` flags, like `tokio_unstable` or `key=\"value\"`
+  --features <FEATURES>    Comma separated features of the workspace members
+  --all-features           Enable all features [default]
+  --no-default-features    Disable the default features
+  --objective <OBJECTIVE>  newest-rustc, fewest-changes, smallest-distance or no-major-downgrade
+  --yank-policy <POLICY>   never, if-locked or always
+  --as-of <TIME>           Only use versions published before it, like 2021-06-01
+  --policy <PATH>          The ruf policy [default: ruf-policy.toml of the project, if any]
+  --index <PATH>           Work offline, with the crates.io-index checkout
+  --ruf-dump <PATH>        Work offline, with the ruf usage dump
+  --lockfile <PATH>        The new lockfile to diff [default: the project's Cargo.lock]
+  --jobs <N>               Rustcs checked at once (explain) [default: 1]
+  --write                  Write the fixed Cargo.lock, Cargo.toml and rust-toolchain.toml (fix)
+  --json                   Print the report as json
+  -v, --verbose            Print the audit events to stderr
+  -h, --help               Print help
+
+Exit codes: 0 if no issues (or fixed), 1 if issues found (or no fix, or unusable rufs
+introduced or already used in forecasts), 2 on errors.
+";
+
+pub enum Command {
+    Check,
+    Fix,
+    Explain,
+    Lifetime(String),
+    Diff(String),
+    Forecast,
+    Help,
+}
+
+pub struct Args {
+    pub command: Command,
+    pub project: String,
+    /// The index and the ruf dump, for offline audits.
+    pub offline: Option<(String, String)>,
+    /// The new lockfile to diff, the project's own by default.
+    pub lockfile: Option<String>,
+    pub options: AuditOptions,
+    /// Threads checking the rustcs when explaining.
+    pub jobs: usize,
+    pub write: bool,
+    pub json: bool,
+    pub verbose: bool,
+}
+
+impl Args {
+    /// Parse the args, without the program name. `ruf` given by cargo is skipped.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter().peekable();
+        if args.peek().is_some_and(|arg| arg == "ruf") {
+            args.next();
+        }
+
+        let mut command = None;
+        let mut project = ".".to_string();
+        let (mut index, mut ruf_dump) = (None, None);
+        let (mut lockfile, mut policy) = (None, None);
+        let (mut rustc, mut oldest) = (None, None);
+        let (mut target, mut cfgs, mut stable) = (None, Vec::new(), false);
+        let mut options = AuditOptions::default();
+        let mut jobs = 1;
+        let (mut write, mut json, mut verbose) = (false, false, false);
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| format!("missing value of {name}"))
+            };
+
+            match arg.as_str() {
+                "-h" | "--help" => command = Some(Command::Help),
+                "--manifest-path" => project = value(&arg)?,
+                "--rustc" => rustc = Some(value(&arg)?.parse::<Toolchain>()?),
+                "--oldest" => oldest = Some(value(&arg)?.parse::<Toolchain>()?),
+                "--stable" => stable = true,
+                "--target" => target = Some(value(&arg)?),
+                "--cfg" => cfgs.push(value(&arg)?),
+                "--features" => options.features = value(&arg)?.parse()?,
+                "--all-features" => options.features = FeatureSelection::All,
+                "--no-default-features" => options.features = FeatureSelection::None,
+                "--objective" => options.objective = value(&arg)?.parse()?,
+                "--yank-policy" => options.yank_policy = value(&arg)?.parse()?,
+                "--as-of" => options.as_of = Some(value(&arg)?.parse::<Timestamp>()?),
+                "--policy" => policy = Some(value(&arg)?),
+                "--index" => index = Some(value(&arg)?),
+                "--ruf-dump" => ruf_dump = Some(value(&arg)?),
+                "--lockfile" => lockfile = Some(value(&arg)?),
+                "--jobs" => {
+                    jobs = value(&arg)?
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|jobs| *jobs > 0)
+                        .ok_or("--jobs shall be a positive number")?;
+                }
+                "--write" => write = true,
+                "--json" => json = true,
+                "-v" | "--verbose" => verbose = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option {arg}")),
+                _ if command.is_some() => return Err(format!("unexpected argument {arg}")),
+                "check" => command = Some(Command::Check),
+                "fix" => command = Some(Command::Fix),
+                "explain" => command = Some(Command::Explain),
+                "forecast" => command = Some(Command::Forecast),
+                "lifetime" => {
+                    command = Some(Command::Lifetime(value(&arg)?));
+                }
+                "diff" => {
+                    command = Some(Command::Diff(value(&arg)?));
+                }
+                _ => return Err(format!("unknown command {arg}")),
+            }
+        }
+        let command = command.unwrap_or(Command::Help);
+
+        // Check (and others on one rustc) on the active rustc by default, and search all versions
+        // for fixes.
+        let mut channel = if stable {
+            Channel::Stable
+        } else {
+            Channel::Nightly
+        };
+        let single = matches!(
+            command,
+            Command::Check | Command::Diff(_) | Command::Forecast
+        );
+        if single && rustc.is_none() {
+            if let Some((active, active_channel)) = active_rustc() {
+                rustc = Some(active);
+                if !stable {
+                    channel = active_channel;
+                }
+            }
+        }
+
+        let default = RustcRange::default();
+        options.rustc = if single {
+            let mut rustc = rustc.unwrap_or(default.newest);
+            if rustc.index().is_none() {
+                eprintln!(
+                    "warning: rustc {rustc} is newer than our ruf lifetime table, checking {} instead",
+                    default.newest
+                );
+                rustc = default.newest;
+            }
+            RustcRange::new(rustc, rustc)
+        } else {
+            RustcRange::new(
+                oldest.unwrap_or(default.oldest),
+                rustc.unwrap_or(default.newest),
+            )
+        };
+        options.rustc.indexes()?;
+
+        let mut cfg = match target {
+            Some(target) => CfgProfile::for_target(&target)?,
+            None => CfgProfile::host(),
+        };
+        for flag in cfgs {
+            cfg = cfg.with_cfg(&flag)?;
+        }
+        options.cfg = cfg.with_channel(channel);
+
+        options.policy = match policy {
+            Some(policy) => RufPolicy::load(Path::new(&policy))?,
+            None => RufPolicy::find(project_dir(&project))?,
+        };
+
+        let offline = match (index, ruf_dump) {
+            (Some(index), Some(ruf_dump)) => Some((index, ruf_dump)),
+            (None, None) => None,
+            _ => return Err("--index and --ruf-dump shall be given together".to_string()),
+        };
+
+        Ok(Self {
+            command,
+            project,
+            offline,
+            lockfile,
+            options,
+            jobs,
+            write,
+            json,
+            verbose,
+        })
+    }
+}
+
+/// The toolchain of `rustc` in the path (rustup picks the one of the project), nightlies are
+/// named by their commit dates. `None` if rustc cannot be run.
+fn active_rustc() -> Option<(Toolchain, Channel)> {
+    let output = Process::new("rustc").arg("-vV").output().ok()?;
+    let output = String::from_utf8(output.stdout).ok()?;
+
+    let field = |name: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .map(|value| value.trim().to_string())
+    };
+    let release = field("release:")?;
+    let (version, pre) = release.split_once('-').unwrap_or((release.as_str(), ""));
+
+    if pre == "nightly" || pre == "dev" {
+        let date = field("commit-date:")?.parse::<Date>().ok()?;
+        Some((Toolchain::Nightly(date), Channel::Nightly))
+    } else {
+        Some((version.parse().ok()?, Channel::Stable))
+    }
+}
+
+/// The directory of the project, given as the directory or its `Cargo.toml`.
+pub fn project_dir(project: &str) -> &Path {
+    let path = Path::new(project);
+    if path.is_file() {
+        path.parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+    } else {
+        path
+    }
+}
+
+#[test]
+fn test_out_of_table_rustc() {
+    let parse = |args: &str| Args::parse(args.split_whitespace().map(String::from));
+    let newest = ruf_lifetime::rustc_ver_num() as u32 - 1;
+
+    // Rustcs newer than the lifetime table are checked as the newest in it.
+    let newest = RustcRange::new(Toolchain::from_index(newest), Toolchain::from_index(newest));
+    let args = parse("ruf check --rustc 1.999.0").unwrap();
+    assert_eq!(args.options.rustc, newest);
+    let args = parse("ruf forecast --rustc nightly-2099-01-01").unwrap();
+    assert_eq!(args.options.rustc, newest);
+
+    // So is the active one, whatever it is.
+    let args = parse("ruf check").unwrap();
+    assert_eq!(args.options.rustc.indexes().unwrap().len(), 1);
+
+    // But searching them is an error.
+    assert!(parse("ruf fix --oldest 1.998.0 --rustc 1.999.0").is_err());
+    assert!(parse("ruf explain --rustc 1.999.0").is_err());
+}
+
+#[test]
+fn test_parse_args() {
+    use ruf_audit_virtual::{Objective, YankPolicy};
+
+    let parse = |args: &str| Args::parse(args.split_whitespace().map(String::from));
+
+    let args = parse("ruf").unwrap();
+    assert!(matches!(args.command, Command::Help));
+    assert!(matches!(parse("fix -h").unwrap().command, Command::Help));
+    assert!(matches!(
+        parse("ruf lifetime doc_cfg").unwrap().command,
+        Command::Lifetime(ruf) if ruf == "doc_cfg"
+    ));
+    assert!(matches!(
+        parse("diff old.lock --lockfile new.lock").unwrap().command,
+        Command::Diff(lock) if lock == "old.lock"
+    ));
+
+    let args = parse(
+        "ruf fix --oldest 1.60.0 --rustc 1.63.0 --stable --features a,b --objective \
+         smallest-distance --yank-policy always --as-of 2021-06-01 --write --json -v",
+    )
+    .unwrap();
+    assert!(matches!(args.command, Command::Fix));
+    assert_eq!(args.options.rustc.indexes(), Ok(vec![63, 62, 61, 60]));
+    assert_eq!(args.options.cfg.channel(), Channel::Stable);
+    assert_eq!(
+        args.options.features,
+        FeatureSelection::List(vec!["a".to_string(), "b".to_string()])
+    );
+    assert_eq!(args.options.objective, Objective::SmallestDistance);
+    assert_eq!(args.options.yank_policy, YankPolicy::Always);
+    assert_eq!(args.options.as_of, Some("2021-06-01".parse().unwrap()));
+    assert!(args.write && args.json && args.verbose);
+    assert_eq!(args.offline, None);
+    assert_eq!(args.jobs, 1);
+
+    let args = parse("explain --jobs 4 --index idx --ruf-dump rufs.csv").unwrap();
+    assert_eq!(args.jobs, 4);
+    assert_eq!(
+        args.offline,
+        Some(("idx".to_string(), "rufs.csv".to_string()))
+    );
+
+    for invalid in [
+        "explain --jobs 0",
+        "explain --jobs many",
+        "explain --jobs",
+        "check --rustc",
+        "check --rustc 2.0.0",
+        "check --objective fastest",
+        "check --index idx",
+        "check --unknown",
+        "check fix",
+        "upgrade",
+        "lifetime",
+    ] {
+        assert!(parse(invalid).is_err(), "{invalid}");
+    }
+}
@@ -0,0 +1,176 @@
+mod args;
+mod report;
+
+use std::env;
+use std::io;
+use std::process::exit;
+
+use ruf_audit_virtual::{
+    apply_audit, audit_concurrent, check_audit, diff_audit, forecast_audit, AuditError,
+    AuditObserver, DepOpsLocal, MetaSource, TextObserver,
+};
+
+use args::{project_dir, Args, Command, USAGE};
+
+/// All went well: no issues, or fixed.
+const EXIT_OK: i32 = 0;
+/// Issues found, or no fix.
+const EXIT_ISSUE: i32 = 1;
+/// Invalid args, or the audit cannot run.
+const EXIT_ERROR: i32 = 2;
+
+/// The exit code of audit results, whether issues are found.
+fn issue_code(found: bool) -> i32 {
+    if found {
+        EXIT_ISSUE
+    } else {
+        EXIT_OK
+    }
+}
+
+/// The exit code of failed audits, only inner errors are errors, others are the audit results.
+fn error_code(e: &AuditError) -> i32 {
+    if e.is_inner() {
+        EXIT_ERROR
+    } else {
+        EXIT_ISSUE
+    }
+}
+
+fn main() {
+    let lifetime = match ruf_lifetime::lifetime() {
+        Ok(lifetime) => lifetime,
+        Err(e) => {
+            eprintln!("error: {e}");
+            exit(EXIT_ERROR);
+        }
+    };
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            exit(EXIT_ERROR);
+        }
+    };
+
+    let code = match &args.command {
+        Command::Help => {
+            print!("{USAGE}");
+            EXIT_OK
+        }
+        Command::Lifetime(ruf) => report::lifetime(lifetime, ruf, args.json),
+        _ => {
+            let res = match &args.offline {
+                Some((index, ruf_dump)) => run(
+                    || DepOpsLocal::new_offline(&args.project, index, ruf_dump),
+                    &args,
+                ),
+                None => run(|| DepOpsLocal::new(&args.project), &args),
+            };
+            res.unwrap_or_else(|e| {
+                eprintln!("error: {}", e.describe());
+                error_code(&e)
+            })
+        }
+    };
+
+    exit(code);
+}
+
+/// Operators are created by `new_ops`, as diffs need one for each lockfile.
+fn run<S: MetaSource>(
+    new_ops: impl Fn() -> Result<DepOpsLocal<S>, AuditError> + Sync,
+    args: &Args,
+) -> Result<i32, AuditError> {
+    if args.verbose {
+        dispatch(new_ops, args, &mut TextObserver(io::stderr()))
+    } else {
+        dispatch(new_ops, args, &mut ())
+    }
+}
+
+/// Only inner errors fail the run, others are the audit results.
+fn dispatch<S: MetaSource>(
+    new_ops: impl Fn() -> Result<DepOpsLocal<S>, AuditError> + Sync,
+    args: &Args,
+    observer: &mut impl AuditObserver,
+) -> Result<i32, AuditError> {
+    let options = &args.options;
+    match &args.command {
+        Command::Check => {
+            let issues = check_audit(new_ops()?, options, observer)?;
+            report::check(&issues, options, args.json);
+            Ok(issue_code(!issues.is_empty()))
+        }
+        Command::Fix => match apply_audit(new_ops()?, options, observer) {
+            Ok(remediation) => {
+                report::fix(&remediation, args.json);
+                if args.write {
+                    let dir = project_dir(&args.project);
+                    remediation.write_to(dir)?;
+                    eprintln!(
+                        "Written Cargo.lock, Cargo.toml and rust-toolchain.toml to {}",
+                        dir.display()
+                    );
+                }
+                Ok(EXIT_OK)
+            }
+            Err(e) if !e.is_inner() => {
+                report::failure(&e, args.json);
+                Ok(error_code(&e))
+            }
+            Err(e) => Err(e),
+        },
+        Command::Explain => match audit_concurrent(&new_ops, options, args.jobs, observer) {
+            Ok(summary) => {
+                report::explain(&summary, args.json);
+                Ok(EXIT_OK)
+            }
+            Err(e) if !e.is_inner() => {
+                report::failure(&e, args.json);
+                Ok(error_code(&e))
+            }
+            Err(e) => Err(e),
+        },
+        Command::Diff(old_lockfile) => {
+            let mut old_ops = new_ops()?;
+            old_ops.set_lockfile(old_lockfile);
+            let mut ops = new_ops()?;
+            if let Some(lockfile) = &args.lockfile {
+                ops.set_lockfile(lockfile);
+            }
+
+            let diff = diff_audit(old_ops, ops, options, observer)?;
+            report::diff(&diff, args.json);
+            Ok(issue_code(!diff.violations().is_empty()))
+        }
+        Command::Forecast => {
+            let forecast = forecast_audit(new_ops()?, options, observer)?;
+            report::forecast(&forecast, args.json);
+            // Rufs already unusable are issues now.
+            let broken = forecast
+                .packages
+                .iter()
+                .any(|pkg| pkg.rufs.iter().any(|ruf| ruf.risk >= 1.0));
+            Ok(issue_code(broken))
+        }
+        Command::Lifetime(_) | Command::Help => unreachable!(),
+    }
+}
+
+#[test]
+fn test_exit_codes() {
+    assert_eq!(issue_code(false), EXIT_OK);
+    assert_eq!(issue_code(true), EXIT_ISSUE);
+
+    // Failing to fix is a result, not an error.
+    assert_eq!(
+        error_code(&AuditError::InnerError("oops".to_string())),
+        EXIT_ERROR
+    );
+    assert_eq!(
+        error_code(&AuditError::FunctionError(None, None)),
+        EXIT_ISSUE
+    );
+    assert_eq!(error_code(&AuditError::NoFix(Vec::new())), EXIT_ISSUE);
+}
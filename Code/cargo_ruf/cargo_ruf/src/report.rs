@@ -0,0 +1,272 @@
+//! Human and json reports of the commands.
+
+use ruf_audit_virtual::{
+    AuditError, AuditOptions, Channel, Decision, ForecastReport, IssueRecord, LockDiff,
+    Remediation, Summary,
+};
+use ruf_lifetime::{LifetimeStore, RufStatus};
+use serde_json::json;
+
+use super::{EXIT_ISSUE, EXIT_OK};
+
+fn print_json(value: &impl serde::Serialize) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).expect("Fatal, report serialize failed")
+    );
+}
+
+fn channel_name(channel: Channel) -> &'static str {
+    match channel {
+        Channel::Nightly => "nightly",
+        Channel::Stable => "stable",
+    }
+}
+
+fn print_issues(issues: &[IssueRecord], indent: &str) {
+    for issue in issues {
+        print!("{indent}{}: {}", issue.package, issue.rufs.join(", "));
+        if !issue.probed.is_empty() {
+            print!(" (probed: {})", issue.probed.join(", "));
+        }
+        println!(", used by {}", issue.roots.join(", "));
+    }
+}
+
+pub fn check(issues: &[IssueRecord], options: &AuditOptions, json: bool) {
+    if json {
+        print_json(&json!({
+            "toolchain": options.rustc.newest.to_string(),
+            "channel": options.cfg.channel(),
+            "target": options.cfg.target(),
+            "issues": issues,
+        }));
+        return;
+    }
+
+    println!(
+        "Checked rustc {} ({}) for {}",
+        options.rustc.newest,
+        channel_name(options.cfg.channel()),
+        options.cfg.target()
+    );
+    if issues.is_empty() {
+        println!("No ruf issues found.");
+    } else {
+        println!("{} packages using rufs unusable:", issues.len());
+        print_issues(issues, "  ");
+        println!("Run `cargo ruf fix` to search a fix.");
+    }
+}
+
+fn print_fix(summary: &Summary) {
+    println!("Fixed on rustc {}", summary.fix_toolchain);
+    if let Some(nightly) = &summary.fix_nightly {
+        println!("Rufs still used, pin the nightly {nightly}");
+    }
+
+    let mut issues = summary.fix_deps.iter().collect::<Vec<_>>();
+    issues.sort_by(|a, b| a.0.cmp(b.0));
+    for (issue, steps) in issues {
+        println!("  {issue}:");
+        for step in steps {
+            println!("    {} {} -> {}", step.name, step.from, step.to);
+        }
+    }
+}
+
+pub fn fix(remediation: &Remediation, json: bool) {
+    if json {
+        print_json(&remediation.summary);
+        return;
+    }
+
+    print_fix(&remediation.summary);
+    if !remediation.pins.is_empty() {
+        println!("Pinned in Cargo.lock:");
+        for (name, ver) in remediation.pins.iter() {
+            println!("  {name} {ver}");
+        }
+    }
+}
+
+pub fn explain(summary: &Summary, json: bool) {
+    if json {
+        print_json(&summary.trail);
+        return;
+    }
+
+    for decision in summary.trail.iter() {
+        let verdict = match decision.decision {
+            Decision::NoIssue => "no issues",
+            Decision::Fixed => "fixed",
+            Decision::Unfixable => "unfixable",
+            Decision::FixFailed => "fix failed",
+        };
+        println!("rustc {}: {verdict}", decision.toolchain);
+        print_issues(&decision.issues, "  ");
+        if let Some(error) = &decision.error {
+            match (&error.failure, &error.message) {
+                (Some(failure), _) => print!("{failure}"),
+                (None, Some(message)) => println!("  {message}"),
+                (None, None) => println!("  {} error", error.kind),
+            }
+        }
+    }
+    print_fix(summary);
+}
+
+pub fn failure(e: &AuditError, json: bool) {
+    if json {
+        print_json(e);
+        return;
+    }
+
+    println!("No fix found: {}", e.describe());
+    if let Some(explain) = e.explain() {
+        print!("{explain}");
+    }
+}
+
+pub fn diff(diff: &LockDiff, json: bool) {
+    if json {
+        print_json(diff);
+        return;
+    }
+
+    println!(
+        "Checked rustc {} ({}) for rufs introduced",
+        diff.toolchain,
+        channel_name(diff.channel)
+    );
+    if diff.introduced.is_empty() {
+        println!("No rufs introduced.");
+        return;
+    }
+    for pkg in diff.introduced.iter() {
+        let previous = pkg
+            .previous
+            .iter()
+            .map(|ver| ver.to_string())
+            .collect::<Vec<_>>();
+        if previous.is_empty() {
+            println!(
+                "  {} (added), used by {}",
+                pkg.package,
+                pkg.roots.join(", ")
+            );
+        } else {
+            println!(
+                "  {} (was {}), used by {}",
+                pkg.package,
+                previous.join(", "),
+                pkg.roots.join(", ")
+            );
+        }
+        for usage in pkg.rufs.iter() {
+            let status = if usage.status.is_empty() {
+                "unknown"
+            } else {
+                usage.status.as_str()
+            };
+            let usable = if usage.usable { "usable" } else { "unusable" };
+            println!("    {}: {status} ({usable})", usage.ruf);
+        }
+    }
+
+    let violations = diff.violations().len();
+    if violations > 0 {
+        println!("{violations} rufs introduced unusable.");
+    }
+}
+
+pub fn forecast(forecast: &ForecastReport, json: bool) {
+    if json {
+        print_json(forecast);
+        return;
+    }
+
+    println!(
+        "Forecast the toolchain after rustc {} ({})",
+        forecast.toolchain,
+        channel_name(forecast.channel)
+    );
+    if forecast.packages.is_empty() {
+        println!("No rufs used.");
+        return;
+    }
+    for pkg in forecast.packages.iter() {
+        println!(
+            "  {}: {:.1}% risk, used by {}",
+            pkg.package,
+            pkg.risk * 100.0,
+            pkg.roots.join(", ")
+        );
+        for ruf in pkg.rufs.iter() {
+            let status = match ruf.status {
+                RufStatus::Unknown => "unknown",
+                _ => ruf.status.as_str(),
+            };
+            print!(
+                "    {} ({status} for {} releases): {:.1}%",
+                ruf.ruf,
+                ruf.age,
+                ruf.risk * 100.0
+            );
+            if ruf.risk >= 1.0 {
+                print!(", already unusable");
+            }
+            for signal in ruf.signals.iter() {
+                print!(", {signal}");
+            }
+            println!();
+        }
+    }
+}
+
+/// Statuses over the rustc releases, runs of the same status merged.
+pub fn lifetime(lifetime: &LifetimeStore, ruf: &str, json: bool) -> i32 {
+    let versions = lifetime.rustc_versions();
+    let statuses = lifetime.get_all_ruf_status(ruf);
+
+    let mut runs: Vec<(&str, &str, RufStatus)> = Vec::new();
+    for (ver, status) in versions.iter().map(|ver| ver.as_str()).zip(statuses) {
+        match runs.last_mut() {
+            Some((_, to, last)) if *last == status => *to = ver,
+            _ => runs.push((ver, ver, status)),
+        }
+    }
+    let known = runs
+        .iter()
+        .any(|(_, _, status)| *status != RufStatus::Unknown);
+
+    if json {
+        let runs = runs
+            .iter()
+            .map(|(from, to, status)| json!({ "from": from, "to": to, "status": status.as_str() }))
+            .collect::<Vec<_>>();
+        print_json(&json!({ "ruf": ruf, "known": known, "lifetime": runs }));
+    } else if !known {
+        println!("Ruf {ruf} is unknown in our lifetime table.");
+    } else {
+        println!("Ruf {ruf}:");
+        for (from, to, status) in runs {
+            let usable = if status.is_usable() {
+                "usable"
+            } else {
+                "unusable"
+            };
+            let status = match status {
+                RufStatus::Unknown => "unknown",
+                _ => status.as_str(),
+            };
+            println!("  {from} - {to}: {status} ({usable})");
+        }
+    }
+
+    if known {
+        EXIT_OK
+    } else {
+        EXIT_ISSUE
+    }
+}
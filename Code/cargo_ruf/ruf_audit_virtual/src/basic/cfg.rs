@@ -0,0 +1,535 @@
+use std::fmt::Display;
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+use fxhash::FxHashSet;
+use serde::Serialize;
+
+/// Cfgs commonly set by build scripts when they probe a nightly compiler,
+/// e.g. with `rustc_version`, `version_check` or `autocfg`.
+const PROBED_CFGS: &[&str] = &[
+    "nightly",
+    "rustc_nightly",
+    "has_nightly",
+    "is_nightly",
+    "nightly_channel",
+    "feature_nightly",
+];
+
+/// A single cfg, `name` or `key = "value"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Cfg {
+    Name(String),
+    KeyPair(String, String),
+}
+
+/// Cfg expressions, as in `#[cfg(...)]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CfgExpr {
+    Not(Box<CfgExpr>),
+    All(Vec<CfgExpr>),
+    Any(Vec<CfgExpr>),
+    Value(Cfg),
+}
+
+impl FromStr for Cfg {
+    type Err = String;
+
+    /// Parse `--cfg` flags, like `nightly` or `feature="foo"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match CfgExpr::from_str(s)? {
+            CfgExpr::Value(cfg) => Ok(cfg),
+            _ => Err(format!("invalid cfg {s}, expect name or key=\"value\"")),
+        }
+    }
+}
+
+impl Display for Cfg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cfg::Name(name) => write!(f, "{}", name),
+            Cfg::KeyPair(key, value) => write!(f, "{} = \"{}\"", key, value),
+        }
+    }
+}
+
+impl FromStr for CfgExpr {
+    type Err = String;
+
+    /// Our ruf conds may come without quotes (`feature = foo`),
+    /// and several conds separated by commas must all hold.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            src: s,
+            chars: s.char_indices().peekable(),
+        };
+
+        let mut exprs = parser.parse_list()?;
+        if let Some((pos, c)) = parser.chars.peek() {
+            return Err(format!("invalid cfg {s}, unexpected {c} at {pos}"));
+        }
+
+        match exprs.len() {
+            0 => Err(format!("invalid cfg {s}, empty")),
+            1 => Ok(exprs.remove(0)),
+            _ => Ok(CfgExpr::All(exprs)),
+        }
+    }
+}
+
+impl CfgExpr {
+    /// All cfgs in the expression.
+    pub fn cfgs(&self) -> Vec<&Cfg> {
+        match self {
+            CfgExpr::Not(expr) => expr.cfgs(),
+            CfgExpr::All(exprs) | CfgExpr::Any(exprs) => {
+                exprs.iter().flat_map(|expr| expr.cfgs()).collect()
+            }
+            CfgExpr::Value(cfg) => vec![cfg],
+        }
+    }
+
+    /// Evaluate with the given enabled cfgs.
+    pub fn eval(&self, enabled: &impl Fn(&Cfg) -> bool) -> bool {
+        match self {
+            CfgExpr::Not(expr) => !expr.eval(enabled),
+            CfgExpr::All(exprs) => exprs.iter().all(|expr| expr.eval(enabled)),
+            CfgExpr::Any(exprs) => exprs.iter().any(|expr| expr.eval(enabled)),
+            CfgExpr::Value(cfg) => enabled(cfg),
+        }
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    /// Comma separated exprs, a trailing comma is allowed.
+    fn parse_list(&mut self) -> Result<Vec<CfgExpr>, String> {
+        let mut exprs = Vec::new();
+        loop {
+            self.skip_spaces();
+            match self.chars.peek() {
+                None | Some((_, ')')) => return Ok(exprs),
+                _ => exprs.push(self.parse_expr()?),
+            }
+
+            self.skip_spaces();
+            if let Some((_, ',')) = self.chars.peek() {
+                self.chars.next();
+            } else {
+                return Ok(exprs);
+            }
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<CfgExpr, String> {
+        let name = self.parse_ident()?;
+        self.skip_spaces();
+
+        match self.chars.peek() {
+            Some((_, '(')) => {
+                self.chars.next();
+                let exprs = self.parse_list()?;
+                self.expect(')')?;
+                match name.as_str() {
+                    "all" => Ok(CfgExpr::All(exprs)),
+                    "any" => Ok(CfgExpr::Any(exprs)),
+                    "not" if exprs.len() == 1 => {
+                        Ok(CfgExpr::Not(Box::new(exprs.into_iter().next().unwrap())))
+                    }
+                    _ => Err(format!("invalid cfg {}, bad predicate {name}", self.src)),
+                }
+            }
+            Some((_, '=')) => {
+                self.chars.next();
+                self.skip_spaces();
+                let value = self.parse_value()?;
+                Ok(CfgExpr::Value(Cfg::KeyPair(name, value)))
+            }
+            _ => Ok(CfgExpr::Value(Cfg::Name(name))),
+        }
+    }
+
+    fn parse_ident(&mut self) -> Result<String, String> {
+        let mut ident = String::new();
+        while let Some((_, c)) = self.chars.peek() {
+            if c.is_alphanumeric() || *c == '_' || *c == '-' {
+                ident.push(*c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+
+        if ident.is_empty() {
+            Err(format!("invalid cfg {}, expect identifier", self.src))
+        } else {
+            Ok(ident)
+        }
+    }
+
+    /// Quoted string, or bare identifier.
+    fn parse_value(&mut self) -> Result<String, String> {
+        if let Some((_, '"')) = self.chars.peek() {
+            self.chars.next();
+            let mut value = String::new();
+            for (_, c) in self.chars.by_ref() {
+                if c == '"' {
+                    return Ok(value);
+                }
+                value.push(c);
+            }
+            Err(format!("invalid cfg {}, unterminated string", self.src))
+        } else {
+            self.parse_ident()
+        }
+    }
+
+    fn expect(&mut self, expect: char) -> Result<(), String> {
+        self.skip_spaces();
+        match self.chars.next() {
+            Some((_, c)) if c == expect => Ok(()),
+            _ => Err(format!("invalid cfg {}, expect {expect}", self.src)),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while let Some((_, c)) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+/// The release channel of the compiler, as build scripts find when probing it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    #[default]
+    Nightly,
+    /// No rufs can be used at all.
+    Stable,
+}
+
+/// The cfgs of a target, with extra `--cfg` flags.
+/// Package features are not here, as they differ between packages.
+///
+/// Cfgs set by build scripts probing the compiler (like `nightly`) are set
+/// only if the compiler is on the nightly channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CfgProfile {
+    target: String,
+    cfgs: FxHashSet<Cfg>,
+    probed: FxHashSet<String>,
+    channel: Channel,
+}
+
+impl Default for CfgProfile {
+    /// The host target.
+    fn default() -> Self {
+        Self::host()
+    }
+}
+
+impl CfgProfile {
+    /// The target we are running on.
+    pub fn host() -> Self {
+        let (arch, os) = (std::env::consts::ARCH, std::env::consts::OS);
+        let triple = match os {
+            "linux" => format!("{arch}-unknown-linux-gnu"),
+            "macos" => format!("{arch}-apple-darwin"),
+            "windows" => format!("{arch}-pc-windows-msvc"),
+            _ => format!("{arch}-unknown-{os}"),
+        };
+
+        Self::for_target(&triple).expect("Fatal, invalid host target")
+    }
+
+    /// The profile build scripts, and the dependencies built for them, are evaluated with: the
+    /// host one when cross compiling, or this one, along with the `--cfg` flags, if not.
+    pub fn for_host(&self) -> Self {
+        let host = Self::host().with_channel(self.channel);
+        if host.target == self.target {
+            self.clone()
+        } else {
+            host
+        }
+    }
+
+    /// Target cfgs from the triple, like `x86_64-unknown-linux-gnu`.
+    /// We only guess them from the triple components, without asking rustc.
+    pub fn for_target(triple: &str) -> Result<Self, String> {
+        let parts = triple.split('-').collect::<Vec<_>>();
+        let (arch, vendor, os, env) = match parts.as_slice() {
+            // Bare metal, like `thumbv7em-none-eabihf`.
+            [arch, "none", _] => (*arch, "unknown", "none", ""),
+            // No vendor, like `aarch64-linux-android`.
+            [arch, "linux", env] => (*arch, "unknown", "linux", *env),
+            [arch, vendor, os] => (*arch, *vendor, *os, ""),
+            [arch, vendor, os, env] => (*arch, *vendor, *os, *env),
+            // Like `wasm32-wasi`.
+            [arch, os] => (*arch, "unknown", *os, ""),
+            _ => {
+                return Err(format!(
+                    "invalid target {triple}, expect arch-vendor-os[-env]"
+                ))
+            }
+        };
+        let (os, env) = match (os, env) {
+            ("darwin", _) => ("macos", env),
+            ("linux", "android" | "androideabi") => ("android", ""),
+            ("wasi" | "wasip1" | "wasip2", _) => ("wasi", env),
+            _ => (os, env),
+        };
+        let arch = match arch {
+            "i386" | "i586" | "i686" => "x86",
+            "armv7" | "armv7a" | "thumbv7neon" | "armv5te" | "armv6" => "arm",
+            "arm64" => "aarch64",
+            _ if arch.starts_with("riscv64") => "riscv64",
+            _ if arch.starts_with("riscv32") => "riscv32",
+            _ if arch.starts_with("thumb") => "arm",
+            _ => arch,
+        };
+
+        let family = match os {
+            "windows" => Some("windows"),
+            "linux" | "android" | "macos" | "ios" | "tvos" | "watchos" | "freebsd" | "netbsd"
+            | "openbsd" | "dragonfly" | "solaris" | "illumos" | "haiku" | "fuchsia" | "redox" => {
+                Some("unix")
+            }
+            _ if arch.starts_with("wasm") => Some("wasm"),
+            _ => None,
+        };
+        let pointer_width = match arch {
+            "x86_64" | "aarch64" | "powerpc64" | "powerpc64le" | "mips64" | "mips64el"
+            | "riscv64" | "s390x" | "sparc64" | "loongarch64" | "wasm64" => "64",
+            "avr" | "msp430" => "16",
+            _ => "32",
+        };
+        let endian = match arch {
+            "s390x" | "powerpc" | "powerpc64" | "mips" | "mips64" | "sparc" | "sparc64" => "big",
+            _ => "little",
+        };
+
+        let mut cfgs = FxHashSet::default();
+        let mut key_pair = |key: &str, value: &str| {
+            cfgs.insert(Cfg::KeyPair(key.to_string(), value.to_string()));
+        };
+        key_pair("target_arch", arch);
+        key_pair("target_vendor", vendor);
+        key_pair("target_os", os);
+        key_pair("target_env", env);
+        key_pair("target_pointer_width", pointer_width);
+        key_pair("target_endian", endian);
+        if let Some(family) = family {
+            key_pair("target_family", family);
+            if family != "wasm" {
+                cfgs.insert(Cfg::Name(family.to_string()));
+            }
+        }
+
+        Ok(Self {
+            target: triple.to_string(),
+            cfgs,
+            probed: PROBED_CFGS.iter().map(|cfg| cfg.to_string()).collect(),
+            channel: Channel::default(),
+        })
+    }
+
+    /// Simulate the compiler on the channel.
+    pub fn with_channel(mut self, channel: Channel) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// Add a cfg name set by build scripts when they find a nightly compiler.
+    pub fn with_probed_cfg(mut self, name: &str) -> Self {
+        self.probed.insert(name.to_string());
+        self
+    }
+
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    pub fn is_nightly(&self) -> bool {
+        self.channel == Channel::Nightly
+    }
+
+    /// Whether the expression depends on cfgs set by build scripts.
+    pub fn is_probed(&self, expr: &CfgExpr) -> bool {
+        expr.cfgs().into_iter().any(|cfg| match cfg {
+            Cfg::Name(name) => self.probed.contains(name),
+            _ => false,
+        })
+    }
+
+    /// Add a `--cfg` flag, like `nightly` or `feature="foo"`.
+    pub fn with_cfg(mut self, cfg: &str) -> Result<Self, String> {
+        self.cfgs.insert(cfg.parse()?);
+        Ok(self)
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Explicit `--cfg` flags always win over build script probes.
+    pub fn is_set(&self, cfg: &Cfg) -> bool {
+        if self.cfgs.contains(cfg) {
+            return true;
+        }
+
+        match cfg {
+            Cfg::Name(name) if self.probed.contains(name) => self.is_nightly(),
+            _ => false,
+        }
+    }
+
+    /// Whether a target specific dependency applies, the platform is `cfg(..)` or a target triple.
+    /// Build scripts run after resolving, so their probed cfgs never apply here,
+    /// and platforms we cannot parse are assumed to apply.
+    pub fn matches_platform(&self, platform: &str) -> bool {
+        match platform
+            .strip_prefix("cfg(")
+            .and_then(|expr| expr.strip_suffix(')'))
+        {
+            Some(expr) => expr
+                .parse::<CfgExpr>()
+                .map_or(true, |expr| expr.eval(&|cfg| self.cfgs.contains(cfg))),
+            None => platform == self.target,
+        }
+    }
+
+    /// Evaluate the cond of a package with its enabled features.
+    pub fn eval(&self, expr: &CfgExpr, features: &[&str]) -> bool {
+        expr.eval(&|cfg| match cfg {
+            Cfg::KeyPair(key, value) if key == "feature" => features.contains(&value.as_str()),
+            _ => self.is_set(cfg),
+        })
+    }
+}
+
+#[test]
+fn test_parse_cfg_expr() {
+    let name = |name: &str| CfgExpr::Value(Cfg::Name(name.to_string()));
+    let key_pair =
+        |key: &str, value: &str| CfgExpr::Value(Cfg::KeyPair(key.to_string(), value.to_string()));
+    let parse = |s: &str| s.parse::<CfgExpr>();
+
+    assert_eq!(parse("unix"), Ok(name("unix")));
+    assert_eq!(
+        parse(r#"target_os = "linux""#),
+        Ok(key_pair("target_os", "linux"))
+    );
+    // Quoted values may hold anything, and our conds may come without quotes.
+    assert_eq!(
+        parse(r#"feature="a b,c""#),
+        Ok(key_pair("feature", "a b,c"))
+    );
+    assert_eq!(parse("feature = foo"), Ok(key_pair("feature", "foo")));
+    assert_eq!(
+        parse(r#"all(unix, not(target_arch = "x86"), any(nightly, feature = "std"),)"#),
+        Ok(CfgExpr::All(vec![
+            name("unix"),
+            CfgExpr::Not(Box::new(key_pair("target_arch", "x86"))),
+            CfgExpr::Any(vec![name("nightly"), key_pair("feature", "std")]),
+        ]))
+    );
+    // Several conds all hold, a trailing comma is allowed.
+    assert_eq!(
+        parse("nightly, feature = foo,"),
+        Ok(CfgExpr::All(vec![
+            name("nightly"),
+            key_pair("feature", "foo")
+        ]))
+    );
+    assert_eq!(parse("any()"), Ok(CfgExpr::Any(Vec::new())));
+
+    for invalid in [
+        "",
+        "not(a, b)",
+        "some(a)",
+        "all(a",
+        r#"feature = "foo"#,
+        "a b",
+        "= foo",
+    ] {
+        assert!(parse(invalid).is_err(), "{invalid} shall be invalid");
+    }
+
+    assert_eq!(
+        "feature=\"foo\"".parse::<Cfg>(),
+        Ok(Cfg::KeyPair("feature".to_string(), "foo".to_string()))
+    );
+    assert!("all(a)".parse::<Cfg>().is_err());
+}
+
+#[test]
+fn test_for_target() {
+    let has = |profile: &CfgProfile, key: &str, value: &str| {
+        profile.is_set(&Cfg::KeyPair(key.to_string(), value.to_string()))
+    };
+    let unix = Cfg::Name("unix".to_string());
+
+    let linux = CfgProfile::for_target("x86_64-unknown-linux-gnu").unwrap();
+    assert!(has(&linux, "target_os", "linux"));
+    assert!(has(&linux, "target_env", "gnu"));
+    assert!(has(&linux, "target_pointer_width", "64"));
+    assert!(linux.is_set(&unix));
+
+    let android = CfgProfile::for_target("armv7-linux-androideabi").unwrap();
+    assert!(has(&android, "target_os", "android"));
+    assert!(has(&android, "target_arch", "arm"));
+    assert!(has(&android, "target_env", ""));
+
+    let wasm = CfgProfile::for_target("wasm32-wasi").unwrap();
+    assert!(has(&wasm, "target_family", "wasm"));
+    assert!(!wasm.is_set(&Cfg::Name("wasm".to_string())));
+
+    // Unknown components are kept as they are, without a family.
+    let unknown = CfgProfile::for_target("mystery64-acme-plan9").unwrap();
+    assert!(has(&unknown, "target_arch", "mystery64"));
+    assert!(has(&unknown, "target_vendor", "acme"));
+    assert!(has(&unknown, "target_os", "plan9"));
+    assert!(has(&unknown, "target_pointer_width", "32"));
+    assert!(!unknown.is_set(&unix));
+
+    assert!(CfgProfile::for_target("x86_64").is_err());
+    assert!(CfgProfile::for_target("a-b-c-d-e").is_err());
+}
+
+#[test]
+fn test_matches_platform() {
+    let linux = CfgProfile::for_target("x86_64-unknown-linux-gnu")
+        .unwrap()
+        .with_cfg("tokio_unstable")
+        .unwrap();
+
+    assert!(linux.matches_platform("x86_64-unknown-linux-gnu"));
+    assert!(!linux.matches_platform("x86_64-pc-windows-msvc"));
+    assert!(linux.matches_platform("cfg(unix)"));
+    assert!(linux.matches_platform(r#"cfg(all(target_os = "linux", not(windows)))"#));
+    assert!(!linux.matches_platform(r#"cfg(target_os = "macos")"#));
+    assert!(linux.matches_platform("cfg(tokio_unstable)"));
+    // Probed cfgs are set by build scripts, run after resolving.
+    assert!(linux.is_set(&Cfg::Name("nightly".to_string())));
+    assert!(!linux.matches_platform("cfg(nightly)"));
+    // Unparsable platforms apply.
+    assert!(linux.matches_platform("cfg(all(unix)"));
+
+    // Build scripts run on the host, with the flags only if not cross compiling.
+    let native = CfgProfile::host().with_cfg("tokio_unstable").unwrap();
+    assert_eq!(native.for_host(), native);
+    let cross = CfgProfile::for_target("wasm32-unknown-unknown")
+        .unwrap()
+        .with_cfg("tokio_unstable")
+        .unwrap();
+    assert_eq!(cross.for_host(), CfgProfile::host());
+}
@@ -0,0 +1,68 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Features enabled on the audited packages, the virt crate or the workspace members.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum FeatureSelection {
+    /// The default features.
+    Default,
+    /// No features, like `--no-default-features`.
+    None,
+    /// All features, like `--all-features`.
+    #[default]
+    All,
+    /// Exactly the listed features, defaults are not included unless `default` is listed.
+    List(Vec<String>),
+}
+
+impl FeatureSelection {
+    pub fn uses_default(&self) -> bool {
+        matches!(self, Self::Default | Self::All)
+    }
+
+    /// The features to enable besides the defaults, `all` is the given declared ones.
+    pub fn features(&self, declared: &[String]) -> Vec<String> {
+        match self {
+            Self::Default | Self::None => Vec::new(),
+            Self::All => declared.to_vec(),
+            Self::List(features) => features.clone(),
+        }
+    }
+}
+
+impl FromStr for FeatureSelection {
+    type Err = String;
+
+    /// `default`, `none`, `all`, or a comma separated list.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "default" => Ok(Self::Default),
+            "none" => Ok(Self::None),
+            "all" => Ok(Self::All),
+            list => {
+                let features = list
+                    .split(',')
+                    .map(|f| f.trim())
+                    .filter(|f| !f.is_empty())
+                    .map(|f| f.to_string())
+                    .collect::<Vec<_>>();
+                if features.is_empty() {
+                    return Err(format!("invalid feature selection {s}"));
+                }
+
+                Ok(Self::List(features))
+            }
+        }
+    }
+}
+
+impl Display for FeatureSelection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::None => write!(f, "none"),
+            Self::All => write!(f, "all"),
+            Self::List(features) => write!(f, "{}", features.join(",")),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use semver::{Version, VersionReq};
+use serde::Serialize;
+
+/// What a fix shall minimize, which also decides the candidate chosen at each fix step.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Objective {
+    /// The newest rustc fixable, taking the newest usable candidates.
+    #[default]
+    NewestRustc,
+    /// The fewest packages changed, keeping to versions compatible with the current lock, so the
+    /// reqs of the dependents still match and they need not change along.
+    FewestChanges,
+    /// The smallest semver distance from the current lock.
+    SmallestDistance,
+    /// The fewest downgrades across incompatible versions (e.g. `2.1.0` to `1.9.0`).
+    NoMajorDowngrade,
+}
+
+impl Objective {
+    pub const ALL: [Objective; 4] = [
+        Self::NewestRustc,
+        Self::FewestChanges,
+        Self::SmallestDistance,
+        Self::NoMajorDowngrade,
+    ];
+
+    /// Choose one from the usable candidates of a package, sorted from newest to oldest.
+    pub fn pick<'a>(&self, current: &Version, candidates: &[&'a Version]) -> &'a Version {
+        match self {
+            Self::NewestRustc => candidates[0],
+            Self::FewestChanges => candidates
+                .iter()
+                .copied()
+                .find(|v| is_compatible(current, v))
+                .unwrap_or_else(|| Self::SmallestDistance.pick(current, candidates)),
+            Self::SmallestDistance => candidates
+                .iter()
+                .copied()
+                .min_by_key(|v| distance(current, v))
+                .unwrap(),
+            Self::NoMajorDowngrade => candidates
+                .iter()
+                .copied()
+                .find(|v| !is_major_downgrade(current, v))
+                .unwrap_or(candidates[0]),
+        }
+    }
+}
+
+/// Semver distance between versions, as differences in major, minor and patch.
+pub fn distance(from: &Version, to: &Version) -> [u64; 3] {
+    [
+        from.major.abs_diff(to.major),
+        from.minor.abs_diff(to.minor),
+        from.patch.abs_diff(to.patch),
+    ]
+}
+
+/// Whether the versions are semver compatible, as cargo sees (`0.x` are majors).
+pub fn is_compatible(from: &Version, to: &Version) -> bool {
+    let (older, newer) = if to < from { (to, from) } else { (from, to) };
+    VersionReq::parse(&format!("^{older}"))
+        .map(|req| req.matches(newer))
+        .unwrap_or(false)
+}
+
+/// Whether it goes to an older version not semver compatible, as cargo sees (`0.x` are majors).
+pub fn is_major_downgrade(from: &Version, to: &Version) -> bool {
+    to < from && !is_compatible(from, to)
+}
+
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "newest-rustc" => Ok(Self::NewestRustc),
+            "fewest-changes" => Ok(Self::FewestChanges),
+            "smallest-distance" => Ok(Self::SmallestDistance),
+            "no-major-downgrade" => Ok(Self::NoMajorDowngrade),
+            _ => Err(format!("invalid objective {s}")),
+        }
+    }
+}
+
+impl Display for Objective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NewestRustc => write!(f, "newest-rustc"),
+            Self::FewestChanges => write!(f, "fewest-changes"),
+            Self::SmallestDistance => write!(f, "smallest-distance"),
+            Self::NoMajorDowngrade => write!(f, "no-major-downgrade"),
+        }
+    }
+}
+
+#[test]
+fn test_version_relations() {
+    let v = |s: &str| Version::parse(s).unwrap();
+
+    assert_eq!(distance(&v("2.1.0"), &v("1.9.3")), [1, 8, 3]);
+    assert_eq!(distance(&v("1.2.3"), &v("1.2.3")), [0, 0, 0]);
+
+    assert!(is_compatible(&v("1.2.0"), &v("1.9.0")));
+    assert!(is_compatible(&v("1.9.0"), &v("1.2.0")));
+    assert!(!is_compatible(&v("1.9.0"), &v("2.0.0")));
+    assert!(is_compatible(&v("0.3.1"), &v("0.3.7")));
+    assert!(!is_compatible(&v("0.3.1"), &v("0.4.0")));
+
+    assert!(is_major_downgrade(&v("2.1.0"), &v("1.9.0")));
+    assert!(is_major_downgrade(&v("0.4.0"), &v("0.3.9")));
+    assert!(!is_major_downgrade(&v("1.9.0"), &v("1.2.0")));
+    assert!(!is_major_downgrade(&v("1.9.0"), &v("2.0.0")));
+}
+
+#[test]
+fn test_objective_pick() {
+    let v = |s: &str| Version::parse(s).unwrap();
+    // Sorted from newest to oldest, as the tree gives them.
+    let versions = [v("3.0.0"), v("2.0.5"), v("1.8.0"), v("1.2.0"), v("0.9.0")];
+    let candidates = versions.iter().collect::<Vec<_>>();
+    let pick = |objective: Objective, current: &str| objective.pick(&v(current), &candidates);
+
+    assert_eq!(pick(Objective::NewestRustc, "1.5.0"), &v("3.0.0"));
+
+    // The newest compatible one, else the closest.
+    assert_eq!(pick(Objective::FewestChanges, "1.5.0"), &v("1.8.0"));
+    assert_eq!(pick(Objective::FewestChanges, "2.3.0"), &v("2.0.5"));
+    assert_eq!(pick(Objective::FewestChanges, "0.5.0"), &v("0.9.0"));
+
+    assert_eq!(pick(Objective::SmallestDistance, "1.4.0"), &v("1.2.0"));
+    assert_eq!(pick(Objective::SmallestDistance, "2.1.0"), &v("2.0.5"));
+
+    // The newest one not downgrading across majors, else the newest.
+    assert_eq!(pick(Objective::NoMajorDowngrade, "1.5.0"), &v("3.0.0"));
+    assert_eq!(pick(Objective::NoMajorDowngrade, "4.0.0"), &v("3.0.0"));
+}
+
+#[test]
+fn test_objective_str() {
+    for objective in Objective::ALL {
+        assert_eq!(objective.to_string().parse::<Objective>(), Ok(objective));
+    }
+    assert!("fastest".parse::<Objective>().is_err());
+}
@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use fxhash::{FxHashMap, FxHashSet};
+use serde::Deserialize;
+
+use super::RufStatus;
+
+/// The policy file looked up in the workspace.
+pub const POLICY_FILE: &str = "ruf-policy.toml";
+
+/// Which rufs are acceptable, on top of the compiler: rufs the compiler cannot use are never
+/// accepted, whatever the policy says. The default accepts all rufs the compiler can use.
+///
+/// Loaded from toml, all keys optional:
+/// ```toml
+/// statuses = ["accepted", "active"]
+/// allow = ["doc_cfg"]
+/// deny = ["specialization"]
+///
+/// [packages.serde]
+/// allow = ["specialization"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RufPolicy {
+    /// Statuses accepted.
+    statuses: Vec<RufStatus>,
+    /// Rufs accepted whatever their statuses, and rufs never accepted.
+    allow: FxHashSet<String>,
+    deny: FxHashSet<String>,
+    /// Exceptions of the packages by name, taking precedence over the lists above.
+    packages: FxHashMap<String, (FxHashSet<String>, FxHashSet<String>)>,
+}
+
+impl Default for RufPolicy {
+    fn default() -> Self {
+        Self {
+            statuses: vec![
+                RufStatus::Active,
+                RufStatus::Incomplete,
+                RufStatus::Accepted,
+            ],
+            allow: FxHashSet::default(),
+            deny: FxHashSet::default(),
+            packages: FxHashMap::default(),
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyFile {
+    statuses: Option<Vec<String>>,
+    allow: Vec<String>,
+    deny: Vec<String>,
+    packages: FxHashMap<String, PackageFile>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PackageFile {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl RufPolicy {
+    /// Load the policy file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("cannot read ruf policy {}: {e}", path.display()))?;
+        content
+            .parse()
+            .map_err(|e| format!("invalid ruf policy {}: {e}", path.display()))
+    }
+
+    /// Load [`POLICY_FILE`] in the directory, or the default policy if there is none.
+    pub fn find(dir: &Path) -> Result<Self, String> {
+        let path = dir.join(POLICY_FILE);
+        if path.exists() {
+            Self::load(&path)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// Whether the package may use the ruf of the status. Denials go before allowances, and
+    /// the exceptions of the package before others.
+    pub fn allows(&self, package: &str, ruf: &str, status: RufStatus) -> bool {
+        if let Some((allow, deny)) = self.packages.get(package) {
+            if deny.contains(ruf) {
+                return false;
+            }
+            if allow.contains(ruf) {
+                return true;
+            }
+        }
+
+        if self.deny.contains(ruf) {
+            return false;
+        }
+        self.allow.contains(ruf) || self.statuses.contains(&status)
+    }
+}
+
+impl FromStr for RufPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let file: PolicyFile = toml::from_str(s).map_err(|e| e.to_string())?;
+
+        let mut policy = Self::default();
+        if let Some(statuses) = file.statuses {
+            policy.statuses = statuses
+                .iter()
+                .map(|status| match status.as_str() {
+                    "active" => Ok(RufStatus::Active),
+                    "incomplete" => Ok(RufStatus::Incomplete),
+                    "accepted" => Ok(RufStatus::Accepted),
+                    _ => Err(format!("invalid ruf status {status}")),
+                })
+                .collect::<Result<_, _>>()?;
+        }
+        policy.allow = file.allow.into_iter().collect();
+        policy.deny = file.deny.into_iter().collect();
+        policy.packages = file
+            .packages
+            .into_iter()
+            .map(|(name, pkg)| {
+                (
+                    name,
+                    (
+                        pkg.allow.into_iter().collect(),
+                        pkg.deny.into_iter().collect(),
+                    ),
+                )
+            })
+            .collect();
+
+        Ok(policy)
+    }
+}
+
+#[test]
+fn test_policy_allows() {
+    let policy = RufPolicy::default();
+    assert!(policy.allows("foo", "doc_cfg", RufStatus::Active));
+    assert!(policy.allows("foo", "doc_cfg", RufStatus::Accepted));
+    assert!(!policy.allows("foo", "doc_cfg", RufStatus::Removed));
+    assert!(!policy.allows("foo", "doc_cfg", RufStatus::Unknown));
+
+    let policy: RufPolicy = r#"
+        statuses = ["accepted"]
+        allow = ["doc_cfg", "specialization"]
+        deny = ["specialization", "never_type"]
+
+        [packages.serde]
+        allow = ["never_type"]
+
+        [packages.tokio]
+        allow = ["let_chains"]
+        deny = ["let_chains", "doc_cfg"]
+    "#
+    .parse()
+    .unwrap();
+
+    // Statuses.
+    assert!(policy.allows("foo", "const_fn", RufStatus::Accepted));
+    assert!(!policy.allows("foo", "const_fn", RufStatus::Active));
+    // Allowed whatever the status, but denials go first.
+    assert!(policy.allows("foo", "doc_cfg", RufStatus::Removed));
+    assert!(!policy.allows("foo", "specialization", RufStatus::Accepted));
+    assert!(!policy.allows("foo", "never_type", RufStatus::Accepted));
+    // Exceptions of the package go before others, denials still first.
+    assert!(policy.allows("serde", "never_type", RufStatus::Active));
+    assert!(!policy.allows("tokio", "let_chains", RufStatus::Accepted));
+    assert!(!policy.allows("tokio", "doc_cfg", RufStatus::Accepted));
+    // Others of the package follow the lists.
+    assert!(!policy.allows("serde", "specialization", RufStatus::Accepted));
+    assert!(policy.allows("tokio", "const_fn", RufStatus::Accepted));
+
+    assert!("statuses = [\"removed\"]".parse::<RufPolicy>().is_err());
+    assert!("allowed = [\"doc_cfg\"]".parse::<RufPolicy>().is_err());
+    assert!("[packages.serde]\nstatuses = []"
+        .parse::<RufPolicy>()
+        .is_err());
+}
+
+#[test]
+fn test_policy_find() {
+    let dir = std::env::temp_dir().join(format!("ruf_policy_test_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    assert_eq!(RufPolicy::find(&dir), Ok(RufPolicy::default()));
+
+    fs::write(dir.join(POLICY_FILE), "deny = [\"doc_cfg\"]").unwrap();
+    let policy = RufPolicy::find(&dir).unwrap();
+    assert!(!policy.allows("foo", "doc_cfg", RufStatus::Active));
+    assert!(policy.allows("foo", "const_fn", RufStatus::Active));
+
+    fs::write(dir.join(POLICY_FILE), "deny = doc_cfg").unwrap();
+    let err = RufPolicy::find(&dir).unwrap_err();
+    assert!(err.starts_with("invalid ruf policy"), "{err}");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,78 +1,71 @@
-#[derive(Debug, Clone)]
+pub use ruf_lifetime::RufStatus;
+use serde::{Deserialize, Serialize};
+
+use super::{CfgExpr, CfgProfile};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CondRuf {
     pub cond: Option<String>,
     pub feature: String,
 }
 
-#[derive(Debug, Clone)]
-pub struct CondRufs(Vec<CondRuf>);
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CondRufs {
+    rufs: Vec<CondRuf>,
+}
 
-#[derive(Debug, Clone)]
-pub enum RufStatus {
-    Unknown,
-    Active,
-    Incomplete,
-    Accepted,
-    Removed,
+impl CondRuf {
+    /// Whether the cond depends on cfgs set by build scripts.
+    pub fn is_probed(&self, cfg: &CfgProfile) -> bool {
+        self.cond
+            .as_ref()
+            .and_then(|cond| cond.parse::<CfgExpr>().ok())
+            .is_some_and(|expr| cfg.is_probed(&expr))
+    }
 }
 
 impl CondRufs {
     pub fn new(rufs: Vec<CondRuf>) -> Self {
-        CondRufs(rufs)
+        CondRufs { rufs }
     }
 
     pub fn empty() -> Self {
-        CondRufs(Vec::new())
+        CondRufs::new(Vec::new())
     }
 
     pub fn push(&mut self, ruf: CondRuf) {
-        self.0.push(ruf);
+        self.rufs.push(ruf);
     }
 
     pub fn extend(&mut self, rufs: impl IntoIterator<Item = CondRuf>) {
-        self.0.extend(rufs.into_iter());
+        self.rufs.extend(rufs);
     }
 
     pub fn borrow(&self) -> Vec<&CondRuf> {
-        self.0.iter().collect()
+        self.rufs.iter().collect()
     }
 
     pub fn inner(self) -> Vec<CondRuf> {
-        self.0.into_iter().collect()
-    }
-}
-
-impl From<&str> for RufStatus {
-    fn from(value: &str) -> Self {
-        match value {
-            "active" => RufStatus::Active,
-            "incomplete" => RufStatus::Incomplete,
-            "accepted" => RufStatus::Accepted,
-            "removed" => RufStatus::Removed,
-            "" => RufStatus::Unknown,
-            _ => unreachable!("Fatal, unknown ruf status: {}", value),
-        }
+        self.rufs.into_iter().collect()
     }
-}
 
-impl From<u32> for RufStatus {
-    fn from(value: u32) -> Self {
-        match value {
-            0 => RufStatus::Unknown,
-            1 => RufStatus::Active,
-            2 => RufStatus::Incomplete,
-            3 => RufStatus::Accepted,
-            4 => RufStatus::Removed,
-            _ => unreachable!("Fatal, unknown ruf status: {}", value),
-        }
-    }
-}
+    /// Rufs only enabled under conds set by build scripts, which are skipped on stable compilers.
+    pub fn probed(&self, cfg: &CfgProfile) -> Vec<&String> {
+        let mut rufs = self
+            .rufs
+            .iter()
+            .map(|condruf| &condruf.feature)
+            .collect::<Vec<_>>();
+        rufs.sort();
+        rufs.dedup();
 
-impl RufStatus {
-    pub fn is_usable(&self) -> bool {
-        match self {
-            Self::Removed | Self::Unknown => false,
-            _ => true,
-        }
+        rufs.into_iter()
+            .filter(|ruf| {
+                self.rufs
+                    .iter()
+                    .filter(|condruf| condruf.feature == **ruf)
+                    .all(|condruf| condruf.is_probed(cfg))
+            })
+            .collect()
     }
 }
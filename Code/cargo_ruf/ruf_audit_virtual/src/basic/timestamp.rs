@@ -0,0 +1,117 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::Date;
+
+/// An instant in UTC, in seconds, like `2021-06-01` (its midnight) or `2021-06-01 12:00:00`.
+/// Crates.io records publish times in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    pub date: Date,
+    /// Seconds since the midnight.
+    pub secs: u32,
+}
+
+impl Timestamp {
+    pub fn new(date: Date, secs: u32) -> Self {
+        Self { date, secs }
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = String;
+
+    /// Dates, and times as PostgreSQL or RFC 3339 writes in UTC, fractional seconds are dropped.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (date, time) = match s.split_once([' ', 'T']) {
+            Some((date, time)) => (date, Some(time)),
+            None => (s, None),
+        };
+        let date = date.parse::<Date>()?;
+
+        let Some(time) = time else {
+            return Ok(Self::new(date, 0));
+        };
+        let time = ["Z", "+00:00", "+00"]
+            .iter()
+            .find_map(|utc| time.strip_suffix(utc))
+            .unwrap_or(time);
+        let time = time.split_once('.').map_or(time, |(time, _)| time);
+
+        let parts = time
+            .split(':')
+            .map(|p| p.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid time {s}: {e}"))?;
+        if !(2..=3).contains(&parts.len())
+            || parts[0] > 23
+            || parts[1] > 59
+            || parts.get(2).is_some_and(|&sec| sec > 60)
+        {
+            return Err(format!(
+                "invalid time {s}, expect YYYY-MM-DD HH:MM:SS in UTC"
+            ));
+        }
+
+        let secs = parts[0] * 3600 + parts[1] * 60 + parts.get(2).copied().unwrap_or(0);
+        Ok(Self::new(date, secs))
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:02}:{:02}:{:02}",
+            self.date,
+            self.secs / 3600,
+            self.secs / 60 % 60,
+            self.secs % 60
+        )
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[test]
+fn test_parse_timestamp() {
+    let date = Date::new(2021, 6, 1);
+    assert_eq!("2021-06-01".parse(), Ok(Timestamp::new(date, 0)));
+    assert_eq!("2021-06-01 12:30".parse(), Ok(Timestamp::new(date, 45000)));
+    for s in [
+        "2021-06-01 12:30:15",
+        "2021-06-01T12:30:15Z",
+        "2021-06-01 12:30:15.123456+00",
+        "2021-06-01T12:30:15.5+00:00",
+    ] {
+        assert_eq!(s.parse(), Ok(Timestamp::new(date, 45015)), "{s}");
+    }
+    for invalid in [
+        "2021-06",
+        "2021-06-01 24:00",
+        "2021-06-01 12",
+        "2021-06-01 12:xx:00",
+    ] {
+        assert!(invalid.parse::<Timestamp>().is_err(), "{invalid}");
+    }
+
+    let time = Timestamp::new(date, 45015);
+    assert_eq!(time.to_string(), "2021-06-01 12:30:15");
+    assert_eq!(time.to_string().parse(), Ok(time));
+    assert!(Timestamp::new(date, 0) < time);
+    assert!(time < Timestamp::new(Date::new(2021, 6, 2), 0));
+}
@@ -0,0 +1,224 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use super::{rustc_ver_num, Date};
+
+/// Rust toolchains, named the same as rustup does, e.g. `1.70.0`, `nightly-2023-05-01`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Toolchain {
+    /// Stable with minor and patch version, patches share the same ruf lifetime.
+    Stable(u32, u32),
+    Nightly(Date),
+}
+
+impl Toolchain {
+    /// The stable toolchain of the lifetime table index.
+    pub fn from_index(index: u32) -> Self {
+        Self::Stable(index, 0)
+    }
+
+    /// Release date of the stable `1.{minor}.0`.
+    pub fn stable_release_date(minor: u32) -> Date {
+        ruf_lifetime::stable_release_date(minor)
+    }
+
+    /// The newest stable minor released at the date.
+    pub fn stable_minor_at(date: &Date) -> Option<u32> {
+        ruf_lifetime::stable_minor_at(date)
+    }
+
+    /// The minor version, nightly is two versions ahead of the stable at the time.
+    pub fn minor(&self) -> u32 {
+        match self {
+            Self::Stable(minor, _) => *minor,
+            Self::Nightly(date) => ruf_lifetime::nightly_minor_at(date),
+        }
+    }
+
+    /// The index in our ruf lifetime table, `None` if not covered by the table.
+    pub fn index(&self) -> Option<u32> {
+        let minor = self.minor();
+        if (minor as usize) < rustc_ver_num() {
+            Some(minor)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_nightly(&self) -> bool {
+        matches!(self, Self::Nightly(_))
+    }
+}
+
+impl FromStr for Toolchain {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(date) = s.strip_prefix("nightly-") {
+            return Ok(Self::Nightly(date.parse()?));
+        }
+
+        let parts = s
+            .split('.')
+            .map(|p| p.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid toolchain {s}: {e}"))?;
+        match parts.as_slice() {
+            [1, minor] => Ok(Self::Stable(*minor, 0)),
+            [1, minor, patch] => Ok(Self::Stable(*minor, *patch)),
+            _ => Err(format!(
+                "invalid toolchain {s}, expect 1.x.y or nightly-YYYY-MM-DD"
+            )),
+        }
+    }
+}
+
+impl Display for Toolchain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stable(minor, patch) => write!(f, "1.{}.{}", minor, patch),
+            Self::Nightly(date) => write!(f, "nightly-{}", date),
+        }
+    }
+}
+
+/// The rustc versions allowed in audits, from the MSRV floor up to the newest allowed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RustcRange {
+    pub oldest: Toolchain,
+    pub newest: Toolchain,
+}
+
+impl Default for RustcRange {
+    /// All rustc versions covered by our lifetime table.
+    fn default() -> Self {
+        Self {
+            oldest: Toolchain::from_index(0),
+            newest: Toolchain::from_index(rustc_ver_num() as u32 - 1),
+        }
+    }
+}
+
+impl RustcRange {
+    pub fn new(oldest: Toolchain, newest: Toolchain) -> Self {
+        Self { oldest, newest }
+    }
+
+    /// Lifetime table indexes in the range, from newest to oldest.
+    /// Versions newer than the table, at either end, are errors, as their rufs are not known.
+    pub fn indexes(&self) -> Result<Vec<u32>, String> {
+        let index = |toolchain: &Toolchain| {
+            toolchain.index().ok_or(format!(
+                "rustc {toolchain} is newer than our ruf lifetime table, the newest is {}",
+                Toolchain::from_index(rustc_ver_num() as u32 - 1)
+            ))
+        };
+        let (oldest, newest) = (index(&self.oldest)?, index(&self.newest)?);
+
+        Ok((oldest..=newest).rev().collect())
+    }
+
+    /// The nightlies in the range building `1.{minor}`, as the first and the last date.
+    pub fn nightly_window(&self, minor: u32) -> Option<(Date, Date)> {
+        let (mut from, mut to) = ruf_lifetime::nightly_window(minor)?;
+        if let Toolchain::Nightly(date) = self.oldest {
+            from = from.max(date);
+        }
+        if let Toolchain::Nightly(date) = self.newest {
+            to = to.min(date);
+        }
+
+        if from <= to {
+            Some((from, to))
+        } else {
+            None
+        }
+    }
+}
+
+#[test]
+fn test_parse_toolchain() {
+    assert_eq!("1.70.2".parse(), Ok(Toolchain::Stable(70, 2)));
+    assert_eq!("1.63".parse(), Ok(Toolchain::Stable(63, 0)));
+    assert_eq!(
+        "nightly-2023-05-01".parse(),
+        Ok(Toolchain::Nightly(Date::new(2023, 5, 1)))
+    );
+    for invalid in [
+        "2.0.0",
+        "1",
+        "1.x.0",
+        "1.70.0.1",
+        "stable",
+        "nightly-2023-13-01",
+    ] {
+        assert!(invalid.parse::<Toolchain>().is_err(), "{invalid}");
+    }
+
+    for toolchain in ["1.70.2", "nightly-2023-05-01"] {
+        assert_eq!(
+            toolchain.parse::<Toolchain>().unwrap().to_string(),
+            toolchain
+        );
+    }
+}
+
+#[test]
+fn test_toolchain_minor() {
+    assert_eq!(Toolchain::stable_release_date(0), Date::new(2015, 5, 15));
+    assert_eq!(Toolchain::stable_release_date(70), Date::new(2023, 6, 1));
+    assert_eq!(Toolchain::stable_minor_at(&Date::new(2015, 5, 1)), None);
+    assert_eq!(Toolchain::stable_minor_at(&Date::new(2023, 6, 1)), Some(70));
+    assert_eq!(
+        Toolchain::stable_minor_at(&Date::new(2023, 5, 31)),
+        Some(69)
+    );
+
+    // Nightlies are two versions ahead of the stable at the time.
+    let nightly = |date: &str| format!("nightly-{date}").parse::<Toolchain>().unwrap();
+    assert_eq!(nightly("2023-05-31").minor(), 71);
+    assert_eq!(nightly("2023-06-01").minor(), 72);
+    assert_eq!(nightly("2015-05-15").minor(), 2);
+    assert_eq!(nightly("2015-01-01").minor(), 0);
+    assert!(nightly("2023-06-01").is_nightly());
+    assert!(!Toolchain::from_index(70).is_nightly());
+
+    let last = rustc_ver_num() as u32 - 1;
+    assert_eq!(Toolchain::from_index(last).index(), Some(last));
+    assert_eq!(Toolchain::Stable(last + 1, 0).index(), None);
+}
+
+#[test]
+fn test_rustc_range() {
+    let last = rustc_ver_num() as u32 - 1;
+    let range = |oldest: &str, newest: &str| {
+        RustcRange::new(oldest.parse().unwrap(), newest.parse().unwrap())
+    };
+
+    assert_eq!(
+        range("1.60.0", "1.63.1").indexes(),
+        Ok(vec![63, 62, 61, 60])
+    );
+    assert_eq!(
+        RustcRange::default().indexes().unwrap().len(),
+        last as usize + 1
+    );
+    // Newer than the table at either end.
+    let beyond = format!("1.{}.0", last + 5);
+    assert!(range(&format!("1.{last}.0"), &beyond).indexes().is_err());
+    assert!(range(&beyond, &beyond).indexes().is_err());
+    assert!(range("1.60.0", "nightly-2099-01-01").indexes().is_err());
+
+    // Nightlies building 1.71 come from 1.69 released to the day before 1.70.
+    let window = (Date::new(2023, 4, 20), Date::new(2023, 5, 31));
+    assert_eq!(range("1.60.0", "1.75.0").nightly_window(71), Some(window));
+    assert_eq!(
+        range("nightly-2023-05-01", "nightly-2023-05-10").nightly_window(71),
+        Some((Date::new(2023, 5, 1), Date::new(2023, 5, 10)))
+    );
+    assert_eq!(
+        range("nightly-2023-06-01", "1.75.0").nightly_window(71),
+        None
+    );
+    assert_eq!(range("1.0.0", "1.75.0").nightly_window(1), None);
+}
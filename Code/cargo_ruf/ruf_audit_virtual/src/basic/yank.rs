@@ -0,0 +1,67 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Whether yanked versions can be fix candidates.
+/// Cargo keeps yanked versions already locked, but refuses them in fresh lockfiles.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum YankPolicy {
+    /// Never use yanked versions, not even the locked ones when fixes go through their packages.
+    Never,
+    /// Keep the yanked version already locked, but never move to other yanked ones, as cargo does.
+    #[default]
+    IfLocked,
+    /// Use yanked versions like others.
+    Always,
+}
+
+impl YankPolicy {
+    /// Whether a yanked version is usable, given it is the locked one or not.
+    pub fn allows(&self, locked: bool) -> bool {
+        match self {
+            Self::Never => false,
+            Self::IfLocked => locked,
+            Self::Always => true,
+        }
+    }
+}
+
+impl FromStr for YankPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "never" => Ok(Self::Never),
+            "if-locked" => Ok(Self::IfLocked),
+            "always" => Ok(Self::Always),
+            _ => Err(format!("invalid yank policy {s}")),
+        }
+    }
+}
+
+impl Display for YankPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Never => write!(f, "never"),
+            Self::IfLocked => write!(f, "if-locked"),
+            Self::Always => write!(f, "always"),
+        }
+    }
+}
+
+#[test]
+fn test_yank_policy() {
+    // Whether yanked versions are usable, locked or not.
+    assert!(!YankPolicy::Never.allows(true));
+    assert!(!YankPolicy::Never.allows(false));
+    assert!(YankPolicy::IfLocked.allows(true));
+    assert!(!YankPolicy::IfLocked.allows(false));
+    assert!(YankPolicy::Always.allows(true));
+    assert!(YankPolicy::Always.allows(false));
+    assert_eq!(YankPolicy::default(), YankPolicy::IfLocked);
+
+    for policy in [YankPolicy::Never, YankPolicy::IfLocked, YankPolicy::Always] {
+        assert_eq!(policy.to_string().parse::<YankPolicy>(), Ok(policy));
+    }
+    assert_eq!(" always ".parse::<YankPolicy>(), Ok(YankPolicy::Always));
+    assert!("sometimes".parse::<YankPolicy>().is_err());
+}
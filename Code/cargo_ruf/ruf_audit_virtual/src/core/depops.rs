@@ -2,15 +2,17 @@ use std::fmt::Display;
 
 use cargo::core::Resolve;
 use cargo_lock::{dependency::Tree, Version};
-use fxhash::FxHashMap;
+use fxhash::{FxHashMap, FxHashSet};
 use semver::VersionReq;
 
 use super::error::AuditError;
-use crate::basic::CondRufs;
+use crate::basic::{CfgProfile, CondRufs, FeatureSelection, RufPolicy, Timestamp};
 
 pub trait DepOps {
     /// Get all candidates of a package.
     fn get_all_candidates(&self, name: &str) -> Result<FxHashMap<Version, CondRufs>, AuditError>;
+    /// Get the yanked versions of a package, see [`crate::basic::YankPolicy`].
+    fn get_yanked(&self, name: &str) -> Result<FxHashSet<Version>, AuditError>;
     /// Get the version requirements of a package.
     fn get_pkg_versionreq(
         &self,
@@ -29,8 +31,29 @@ pub trait DepOps {
         ver: &str,
         condrufs: &'ctx CondRufs,
     ) -> Result<Vec<&'ctx String>, AuditError>;
-    /// Check if the rufs are usable, and return the failed rufs.
-    fn filter_rufs<'ctx>(&self, rustv: u32, rufs: Vec<&'ctx String>) -> Vec<&'ctx String>;
+    /// Set the cfg profile (target and `--cfg` flags) ruf conds are evaluated with.
+    fn set_cfg(&mut self, cfg: CfgProfile);
+    fn get_cfg(&self) -> &CfgProfile;
+    /// Set the features enabled on the audited packages, taking effect from the first resolve.
+    fn set_features(&mut self, features: FeatureSelection);
+    fn get_features(&self) -> &FeatureSelection;
+    /// Set the instant the audit is done as of, versions published after it are not used,
+    /// neither as candidates nor in resolves. Taking effect from the first resolve.
+    fn set_as_of(&mut self, as_of: Option<Timestamp>);
+    fn get_as_of(&self) -> Option<&Timestamp>;
+    /// Set the policy deciding the rufs accepted, on top of the compiler.
+    fn set_policy(&mut self, policy: RufPolicy);
+    fn get_policy(&self) -> &RufPolicy;
+    /// Get the features declared by the audited packages, which can be selected.
+    fn get_root_features(&self, resolve: &Resolve) -> Result<Vec<String>, AuditError>;
+    /// Check if the rufs used by the package are usable, by the compiler and the policy,
+    /// and return the failed rufs.
+    fn filter_rufs<'ctx>(
+        &self,
+        rustv: u32,
+        name: &str,
+        rufs: Vec<&'ctx String>,
+    ) -> Vec<&'ctx String>;
 
     /// First time resolve
     fn first_resolve(&self) -> Result<(Resolve, Tree), AuditError>;
@@ -40,8 +63,16 @@ pub trait DepOps {
         prev_resolve: &Resolve,
         update: (String, Version, Version),
     ) -> Result<(Resolve, Tree), AuditError>;
+    /// Get the local packages, which are pinned and never fixed.
+    fn get_locals(&self) -> Vec<(String, Version)>;
+    /// Get the audited packages: the target crates of the virt package, or the workspace members.
+    /// They share one resolve, and fixes shall hold for all of them.
+    fn get_audited(&self) -> Vec<(String, Version)>;
     /// Get lockfile from the resolve
     fn get_resolve_lockfile(&self, resolve: &Resolve) -> Result<String, AuditError>;
+    /// Get the manifest with the requirements not accepting the given packages edited to them,
+    /// as the lockfile pins the rest, `None` if the manifest cannot be edited.
+    fn get_fixed_manifest(&self, pins: &[(String, Version)]) -> Result<Option<String>, AuditError>;
 }
 
 #[derive(Debug, Hash, PartialEq, Eq)]
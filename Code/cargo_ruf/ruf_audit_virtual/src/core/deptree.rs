@@ -1,4 +1,4 @@
-use std::{cell::RefCell, cmp::min, io::Write, rc::Rc};
+use std::{cell::RefCell, rc::Rc};
 
 use cargo::core::Resolve;
 use cargo_lock::dependency::{
@@ -10,14 +10,40 @@ use petgraph::{algo::toposort, visit::EdgeRef};
 use semver::{Version, VersionReq};
 
 use crate::{
-    basic::{CondRuf, CondRufs},
-    core::{depops::DepOps, error::AuditError},
+    basic::{CfgProfile, CondRuf, CondRufs, Objective, YankPolicy},
+    core::{
+        depops::DepOps,
+        error::AuditError,
+        event::{AuditEvent, AuditObserver, VersionChange},
+        failure::{FixFailure, ReqRejected, RufRejected},
+        memo::Memo,
+        parents::{search_parents, Choices, ParentTree},
+    },
 };
 
 use super::depops::DepVersionReq;
 
 pub type UsedRufs = FxHashMap<String, Vec<String>>;
 
+/// Candidates of each package, along with their rufs and version reqs.
+type LimitedCandidates = FxHashMap<
+    String,
+    (
+        bool, // Candidates removable
+        FxHashMap<Version, (CondRufs, FxHashMap<String, VersionReq>)>,
+    ),
+>;
+/// Fixes applied from the max resolve tree, which tells the tree.
+type FixPath = Vec<(String, Version, Version)>;
+/// A resolved tree along with its used rufs, shared between the memo and the managers.
+type Resolved = Rc<(Resolve, Tree, UsedRufs)>;
+
+/// How many levels of parents we explain for a fix failure.
+const EXPLAIN_DEPTH: usize = 4;
+/// Trees and candidates kept for other rustc versions at most, the oldest ones are dropped.
+const MAX_RESOLVED: usize = 64;
+const MAX_PREPARED: usize = 256;
+
 /// Record and manage the dependency tree of a crate
 pub struct DepTreeManager<D: DepOps> {
     /// Rustc versions.
@@ -26,22 +52,28 @@ pub struct DepTreeManager<D: DepOps> {
     /// Depencency operators
     depops: D,
     /// Dependency resolve related info
-    depresolve: Rc<(Resolve, Tree, UsedRufs)>,
+    depresolve: Resolved,
     /// Store the max resolve tree.
-    maxresolve: Rc<(Resolve, Tree, UsedRufs)>,
+    maxresolve: Resolved,
 
     locals: FxHashSet<String>,
 
-    limited_candidates: RefCell<
-        FxHashMap<
-            String,
-            (
-                bool, // Candidates removable
-                FxHashMap<Version, (CondRufs, FxHashMap<String, VersionReq>)>,
-            ),
-        >,
-    >,
+    limited_candidates: RefCell<LimitedCandidates>,
     limited_fix: RefCell<FxHashMap<String, VersionReq>>,
+
+    /// Decides the candidate chosen among the usable ones.
+    objective: Objective,
+    /// Only newer candidates are searched, and requirements of locals can be edited.
+    upgrade: bool,
+    /// Whether yanked candidates are usable.
+    yank_policy: YankPolicy,
+
+    /// The fixes applied on the current tree.
+    fix_path: FixPath,
+    /// Trees and candidates met, which do not change with rustc versions, and thus are
+    /// shared between them instead of resolving and preparing again.
+    resolved: RefCell<Memo<FixPath, Resolved>>,
+    prepared: RefCell<Memo<(FixPath, String), LimitedCandidates>>,
 }
 
 impl<D: DepOps> DepTreeManager<D> {
@@ -51,6 +83,13 @@ impl<D: DepOps> DepTreeManager<D> {
         let used_rufs = ops.extract_rufs(&resolve)?;
         let resolve = Rc::new((resolve, tree, used_rufs));
 
+        // Locals are pinned during the whole fix.
+        let locals = ops
+            .get_locals()
+            .into_iter()
+            .map(|(name, ver)| format!("{}@{}", name, ver))
+            .collect();
+
         Ok(Self {
             rustv: rustv,
 
@@ -58,10 +97,18 @@ impl<D: DepOps> DepTreeManager<D> {
             depresolve: resolve.clone(),
             maxresolve: resolve,
 
-            locals: FxHashSet::default(),
+            locals,
 
             limited_candidates: RefCell::new(FxHashMap::default()),
             limited_fix: RefCell::new(FxHashMap::default()),
+
+            objective: Objective::default(),
+            upgrade: false,
+            yank_policy: YankPolicy::default(),
+
+            fix_path: Vec::new(),
+            resolved: RefCell::new(Memo::new(MAX_RESOLVED)),
+            prepared: RefCell::new(Memo::new(MAX_PREPARED)),
         })
     }
 
@@ -69,8 +116,27 @@ impl<D: DepOps> DepTreeManager<D> {
         Ok(self.depresolve.2.clone())
     }
 
-    pub fn filter_rufs<'ctx>(&self, rufs: Vec<&'ctx String>) -> Vec<&'ctx String> {
-        self.depops.filter_rufs(self.rustv, rufs)
+    pub fn filter_rufs<'ctx>(&self, name: &str, rufs: Vec<&'ctx String>) -> Vec<&'ctx String> {
+        self.depops.filter_rufs(self.rustv, name, rufs)
+    }
+
+    pub fn get_cfg(&self) -> &CfgProfile {
+        self.depops.get_cfg()
+    }
+
+    /// Rufs of the package only enabled by build scripts probing a nightly compiler.
+    pub fn get_probed_rufs(&self, name: &str, ver: &Version) -> Result<Vec<String>, AuditError> {
+        let cads = self.depops.get_all_candidates(name)?;
+        Ok(cads
+            .get(ver)
+            .map(|condrufs| {
+                condrufs
+                    .probed(self.depops.get_cfg())
+                    .into_iter()
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
     }
 
     pub fn set_local(&mut self, nx: &NodeIndex) {
@@ -89,21 +155,131 @@ impl<D: DepOps> DepTreeManager<D> {
         self.depresolve.1.graph()
     }
 
-    pub fn get_root(&self) -> NodeIndex {
-        let roots = self.depresolve.1.roots();
-        assert!(roots.len() == 1, "Fatal, multiple roots found");
-        roots[0]
+    pub fn get_roots(&self) -> Vec<NodeIndex> {
+        self.depresolve.1.roots()
+    }
+
+    /// Get the audited packages, see [`DepOps::get_audited`].
+    pub fn get_audited(&self) -> Vec<String> {
+        self.depops
+            .get_audited()
+            .into_iter()
+            .map(|(name, ver)| format!("{}@{}", name, ver))
+            .collect()
+    }
+
+    /// Get the audited packages depending on the package, itself included.
+    pub fn get_dependent_audited(&self, nx: NodeIndex) -> Vec<String> {
+        let graph = self.get_graph();
+        let audited = self.get_audited();
+
+        let mut dependents = self
+            .get_all_parents(nx)
+            .into_iter()
+            .map(|p| format!("{}@{}", graph[p].name, graph[p].version))
+            .filter(|name_ver| audited.contains(name_ver))
+            .collect::<Vec<_>>();
+        dependents.sort();
+        dependents
+    }
+
+    /// Get the local nodes in current tree.
+    pub fn get_local_nodes(&self) -> Vec<NodeIndex> {
+        self.depresolve
+            .1
+            .nodes()
+            .values()
+            .filter(|nx| self.is_local(nx))
+            .cloned()
+            .collect()
     }
 
     pub fn get_lockfile(&self) -> Result<String, AuditError> {
         self.depops.get_resolve_lockfile(&self.depresolve.0)
     }
 
+    /// Get the packages changed from the max resolve, that is, the fixed ones.
+    pub fn get_fixed_pkgs(&self) -> Vec<(String, Version)> {
+        let max_nodes = self.maxresolve.1.nodes();
+        let mut fixed = self
+            .depresolve
+            .1
+            .nodes()
+            .keys()
+            .filter(|dep| !max_nodes.contains_key(dep))
+            .map(|dep| (dep.name.to_string(), dep.version.clone()))
+            .collect::<Vec<_>>();
+        fixed.sort();
+
+        fixed
+    }
+
+    /// Get the dependency edges changed from the max resolve, as (parent, dep, old, new).
+    pub fn get_changed_edges(&self) -> Vec<(String, String, Option<Version>, Option<Version>)> {
+        fn collect_edges(tree: &Tree) -> FxHashMap<(String, String), Version> {
+            let graph = tree.graph();
+            graph
+                .edge_references()
+                .map(|edge| {
+                    let parent = &graph[edge.source()];
+                    let dep = &graph[edge.target()];
+                    (
+                        (parent.name.to_string(), dep.name.to_string()),
+                        dep.version.clone(),
+                    )
+                })
+                .collect()
+        }
+
+        let old_edges = collect_edges(&self.maxresolve.1);
+        let new_edges = collect_edges(&self.depresolve.1);
+
+        let mut changed = Vec::new();
+        for ((parent, dep), old_ver) in old_edges.iter() {
+            match new_edges.get(&(parent.clone(), dep.clone())) {
+                Some(new_ver) if new_ver == old_ver => {}
+                new_ver => changed.push((
+                    parent.clone(),
+                    dep.clone(),
+                    Some(old_ver.clone()),
+                    new_ver.cloned(),
+                )),
+            }
+        }
+        for ((parent, dep), new_ver) in new_edges.iter() {
+            if !old_edges.contains_key(&(parent.clone(), dep.clone())) {
+                changed.push((parent.clone(), dep.clone(), None, Some(new_ver.clone())));
+            }
+        }
+        changed.sort();
+
+        changed
+    }
+
+    /// Get the manifest accepting the fixed packages, which the lockfile pins.
+    pub fn get_fixed_manifest(&self) -> Result<Option<String>, AuditError> {
+        let fixed = self.get_fixed_pkgs();
+
+        // A package with multiple fixed versions cannot be pinned in one manifest, leave it to the lockfile.
+        let pins = fixed
+            .iter()
+            .filter(|(name, _)| fixed.iter().filter(|(n, _)| n == name).count() == 1)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        self.depops.get_fixed_manifest(&pins)
+    }
+
     pub fn set_fix_limit(&self, fixes: &Vec<(String, Version, Version)>) {
         // Updates limits on fix, this will also accelerate the step fixing.
         let mut limited_fix_mut = self.limited_fix.borrow_mut();
         for (name, _, fix_ver) in fixes {
-            let req = VersionReq::parse(&format!("<={fix_ver}")).unwrap();
+            // Fixes shall go one way, up when upgrading, or down.
+            let req = if self.upgrade {
+                VersionReq::parse(&format!(">={fix_ver}")).unwrap()
+            } else {
+                VersionReq::parse(&format!("<={fix_ver}")).unwrap()
+            };
             limited_fix_mut.insert(name.clone(), req);
         }
         drop(limited_fix_mut);
@@ -113,11 +289,73 @@ impl<D: DepOps> DepTreeManager<D> {
         self.limited_fix.borrow_mut().clear();
     }
 
+    pub fn set_objective(&mut self, objective: Objective) {
+        self.objective = objective;
+    }
+
+    /// Search upgrades only, where the version requirements of locals on their dependencies
+    /// can be edited, see [`Self::get_req_edits`].
+    pub fn set_upgrade(&mut self, upgrade: bool) {
+        self.upgrade = upgrade;
+        self.prepared.borrow_mut().clear();
+    }
+
+    pub fn set_yank_policy(&mut self, yank_policy: YankPolicy) {
+        self.yank_policy = yank_policy;
+        self.prepared.borrow_mut().clear();
+    }
+
+    /// The requirements of locals the fixes break, which shall be edited in their manifests,
+    /// as (local, dep, req, fixed version).
+    pub fn get_req_edits(
+        &self,
+        fixes: &[(String, Version, Version)],
+    ) -> Result<Vec<(String, String, VersionReq, Version)>, AuditError> {
+        let graph = self.get_graph();
+        let mut edits = Vec::new();
+        for (name, ver, fix_ver) in fixes {
+            let Some((_, &nx)) =
+                self.depresolve.1.nodes().iter().find(|(_, nx)| {
+                    graph[**nx].name.as_str() == name && graph[**nx].version == *ver
+                })
+            else {
+                continue;
+            };
+
+            for p in self.get_parents_sorted(nx) {
+                if !self.is_local(&p) {
+                    continue;
+                }
+                let Some(req) = self.get_dep_req(p, name)? else {
+                    continue;
+                };
+                if !req.matches(fix_ver) {
+                    edits.push((
+                        format!("{}@{}", graph[p].name, graph[p].version),
+                        name.clone(),
+                        req,
+                        fix_ver.clone(),
+                    ));
+                }
+            }
+        }
+        edits.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        edits.dedup_by(|a, b| a.0 == b.0 && a.1 == b.1);
+
+        Ok(edits)
+    }
+
+    /// Whether the requirements of the parent can be ignored, as they can be edited when upgrading.
+    fn req_relaxed(&self, parent_nx: &NodeIndex) -> bool {
+        self.upgrade && self.is_local(parent_nx)
+    }
+
     /// Update rust version configs.
     pub fn switch_rustv(&mut self, rustv: u32) {
         self.rustv = rustv;
         // Restore the max tree and fix limitations.
         self.depresolve = self.maxresolve.clone();
+        self.fix_path.clear();
 
         self.limited_candidates.borrow_mut().clear();
         self.limited_fix.borrow_mut().clear();
@@ -128,28 +366,26 @@ impl<D: DepOps> DepTreeManager<D> {
         &mut self,
         issue_nx: NodeIndex,
         fixes: Vec<(String, Version, Version)>,
-        debugger: &mut impl Write,
+        observer: &mut impl AuditObserver,
     ) -> Result<Vec<(String, Version, Version, Vec<String>)>, AuditError> {
         // Updates the candidates first.
-        self.prepare_limited_candidates(issue_nx, debugger)?;
+        self.prepare_limited_candidates(issue_nx, observer)?;
 
         let max_step = fixes.len();
         let mut cur_step = 0;
         let mut do_fix = Vec::new();
 
         let issue_pkg = self.get_graph()[issue_nx].clone();
-        writeln!(
-            debugger,
-            "[Deptree Debug] issue_dofix: start fixing {}@{} with limits: {:?}",
-            issue_pkg.name,
-            issue_pkg.version,
-            self.limited_fix
+        let issue_name_ver = format!("{}@{}", issue_pkg.name, issue_pkg.version);
+        observer.on_event(AuditEvent::FixStart {
+            package: issue_name_ver.clone(),
+            limits: self
+                .limited_fix
                 .borrow()
                 .iter()
-                .map(|(k, v)| format!("{}: {}", k, v))
-                .collect::<Vec<_>>()
-        )
-        .unwrap();
+                .map(|(k, v)| (k.clone(), v.to_string()))
+                .collect(),
+        });
 
         let mut first_fix = Some(fixes);
         loop {
@@ -161,7 +397,7 @@ impl<D: DepOps> DepTreeManager<D> {
                 let mut step_fixes = if first_fix.is_some() {
                     first_fix.take().unwrap()
                 } else {
-                    self.get_step_fix(*issue_nx, debugger)?
+                    self.get_step_fix(*issue_nx, observer)?
                         .into_iter()
                         .map(|(nx, fix_ver)| {
                             (
@@ -173,28 +409,40 @@ impl<D: DepOps> DepTreeManager<D> {
                         .collect::<Vec<_>>()
                 };
 
-                writeln!(
-                    debugger,
-                    "[Deptree Debug] issue_dofix: step fixing {}@{} -> {}, changeable remaining {:?}",
-                    step_fixes[0].0,
-                    step_fixes[0].1,
-                    step_fixes[0].2,
-                    step_fixes
-                        .iter()
-                        .skip(1)
-                        .map(|(name, ver, fix_ver)| format!("{}@{} -> {}", name, ver, fix_ver))
-                        .collect::<Vec<_>>()
-                )
-                .unwrap();
+                let mut changes = step_fixes.iter().map(|(name, ver, fix_ver)| VersionChange {
+                    name: name.clone(),
+                    from: ver.clone(),
+                    to: fix_ver.clone(),
+                });
+                observer.on_event(AuditEvent::FixStep {
+                    package: issue_name_ver.clone(),
+                    change: changes.next().unwrap(),
+                    remaining: changes.collect(),
+                });
 
                 let fix = step_fixes.remove(0);
-                let (resolve, tree) = self
-                    .depops
-                    .update_resolve(&self.depresolve.0, fix.clone())?;
-                let used_rufs = self.depops.extract_rufs(&resolve)?;
+                let mut fix_path = self.fix_path.clone();
+                fix_path.push(fix.clone());
+
+                // The same fixes on other rustc versions give the same tree.
+                let cached = self.resolved.borrow().get(&fix_path).cloned();
+                let depresolve = match cached {
+                    Some(depresolve) => depresolve,
+                    None => {
+                        let (resolve, tree) = self
+                            .depops
+                            .update_resolve(&self.depresolve.0, fix.clone())?;
+                        let used_rufs = self.depops.extract_rufs(&resolve)?;
+                        let depresolve = Rc::new((resolve, tree, used_rufs));
+                        self.resolved
+                            .borrow_mut()
+                            .insert(fix_path.clone(), depresolve.clone());
+                        depresolve
+                    }
+                };
 
                 let rufs_after_fix =
-                    if let Some(rufs) = used_rufs.get(&format!("{}@{}", fix.0, fix.2)) {
+                    if let Some(rufs) = depresolve.2.get(&format!("{}@{}", fix.0, fix.2)) {
                         rufs.clone()
                     } else {
                         Vec::new()
@@ -202,16 +450,14 @@ impl<D: DepOps> DepTreeManager<D> {
 
                 do_fix.push((fix.0, fix.1, fix.2, rufs_after_fix));
 
-                self.depresolve = Rc::new((resolve, tree, used_rufs));
+                self.depresolve = depresolve;
+                self.fix_path = fix_path;
 
                 cur_step += 1;
             } else {
-                writeln!(
-                    debugger,
-                    "[Deptree Debug] issue_dofix: issue dep {}@{} already gone.",
-                    issue_pkg.name, issue_pkg.version
-                )
-                .unwrap();
+                observer.on_event(AuditEvent::IssueGone {
+                    package: issue_name_ver,
+                });
                 break;
             }
         }
@@ -223,40 +469,226 @@ impl<D: DepOps> DepTreeManager<D> {
     pub fn issue_fixable(
         &self,
         issue_nx: NodeIndex,
-        debugger: &mut impl Write,
+        observer: &mut impl AuditObserver,
     ) -> Result<Vec<(NodeIndex, Version)>, AuditError> {
-        // let graph = self.get_graph();
-        // let dep = &graph[issue_nx];
-        // writeln!(
-        //     debugger,
-        //     "[Deptree Debug] issue_fixable: check {}@{} fixibility",
-        //     dep.name, dep.version
-        // )
-        // .unwrap();
-
         // If local, no version fix of course.
         if self.is_local(&issue_nx) {
-            return Err(AuditError::FunctionError(
+            let failure = self.explain_unfixable(
+                issue_nx,
+                None,
                 Some("local crate has no candidates".to_string()),
-                Some(issue_nx),
+                0,
+            )?;
+            return Err(AuditError::Unfixable(Box::new(failure)));
+        }
+
+        // Prepare candidates, or take those prepared on the same tree for other rustc versions.
+        let graph = self.get_graph();
+        let key = (
+            self.fix_path.clone(),
+            format!("{}@{}", graph[issue_nx].name, graph[issue_nx].version),
+        );
+        let prepared = self.prepared.borrow().get(&key).cloned();
+        if let Some(prepared) = prepared {
+            *self.limited_candidates.borrow_mut() = prepared;
+        } else {
+            self.limited_candidates.borrow_mut().clear();
+            self.prepare_limited_candidates(issue_nx, observer)?;
+            self.prepared
+                .borrow_mut()
+                .insert(key, self.limited_candidates.borrow().clone());
+        }
+
+        match self.get_step_fix(issue_nx, observer) {
+            Err(AuditError::FunctionError(note, _)) => {
+                let failure = self.explain_unfixable(issue_nx, None, note, 0)?;
+                Err(AuditError::Unfixable(Box::new(failure)))
+            }
+            res => res,
+        }
+    }
+
+    /// Explain why the package cannot be fixed with the limited candidates.
+    /// For parents, only their candidates accepting some of the child versions are considered.
+    fn explain_unfixable(
+        &self,
+        nx: NodeIndex,
+        child: Option<(&str, &[Version])>,
+        note: Option<String>,
+        depth: usize,
+    ) -> Result<FixFailure, AuditError> {
+        let graph = self.get_graph();
+        let pkg = &graph[nx];
+        let pkg_name = pkg.name.as_str();
+        let pkg_ver = pkg.version.to_string();
+        let package = format!("{}@{}", pkg_name, pkg_ver);
+
+        let rufs = self
+            .depresolve
+            .2
+            .get(&package)
+            .map(|rufs| {
+                self.filter_rufs(pkg_name, rufs.iter().collect())
+                    .into_iter()
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut failure = FixFailure::new(package, rufs, note);
+
+        if self.is_local(&nx) {
+            failure
+                .note
+                .get_or_insert("local crate, never changed".to_string());
+            return Ok(failure);
+        }
+
+        let limited_candidates_borrow = self.limited_candidates.borrow();
+        let Some((_removable, candidates)) = limited_candidates_borrow.get(pkg_name) else {
+            return Ok(failure);
+        };
+        let limits_on_candidates = self.limited_fix.borrow().get(pkg_name).cloned();
+        let mut candidates = candidates
+            .iter()
+            .filter(|(v, _)| **v != pkg.version)
+            .filter(|(v, _)| Self::limited_fix_filter(v, &limits_on_candidates))
+            .filter(|(_, (_, meta_reqs))| match child {
+                Some((child_name, child_vers)) => meta_reqs
+                    .get(child_name)
+                    .is_some_and(|req| child_vers.iter().any(|v| req.matches(v))),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by(|a, b| b.0.cmp(a.0));
+
+        if candidates.is_empty()
+            && let Some((child_name, child_vers)) = child
+        {
+            failure.note.get_or_insert(format!(
+                "no version accepts {} in {}",
+                child_name,
+                child_vers
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
             ));
         }
 
-        // Prepare candidates.
-        self.limited_candidates.borrow_mut().clear();
+        // Rejected by rufs.
+        let mut ruf_ok = Vec::new();
+        for (ver, (condrufs, _)) in candidates {
+            let rufs =
+                self.depops
+                    .resolve_condrufs(&self.depresolve.0, pkg_name, &pkg_ver, condrufs)?;
+            let issue_rufs = self.depops.filter_rufs(self.rustv, pkg_name, rufs);
+            if issue_rufs.is_empty() {
+                ruf_ok.push(ver.clone());
+            } else {
+                failure.ruf_rejected.push(RufRejected {
+                    version: ver.clone(),
+                    rufs: issue_rufs.into_iter().cloned().collect(),
+                });
+            }
+        }
+
+        // Rejected by parents.
+        let mut blocking = Vec::new();
+        for p in self.get_parents_sorted(nx) {
+            let Some(req) = self.get_dep_req(p, pkg_name)? else {
+                continue;
+            };
+            let rejected = ruf_ok
+                .iter()
+                .filter(|v| !req.matches(v))
+                .cloned()
+                .collect::<Vec<_>>();
+            if rejected.is_empty() {
+                continue;
+            }
+
+            for ver in rejected.iter() {
+                if !failure.req_rejected.iter().any(|r| r.version == *ver) {
+                    failure.req_rejected.push(ReqRejected {
+                        version: ver.clone(),
+                        parent: format!("{}@{}", graph[p].name, graph[p].version),
+                        req: req.to_string(),
+                    });
+                }
+            }
+            blocking.push(p);
+        }
+        failure
+            .req_rejected
+            .sort_by(|a, b| b.version.cmp(&a.version));
+        drop(limited_candidates_borrow);
+
+        if depth < EXPLAIN_DEPTH {
+            for p in blocking {
+                let cause =
+                    self.explain_unfixable(p, Some((pkg_name, &ruf_ok)), None, depth + 1)?;
+                failure.causes.push(cause);
+            }
+        }
+
+        Ok(failure)
+    }
+
+    /// The version req of the parent on the child, `None` if not depends on it.
+    fn get_dep_req(
+        &self,
+        parent_nx: NodeIndex,
+        child_name: &str,
+    ) -> Result<Option<VersionReq>, AuditError> {
+        let p_pkg = &self.get_graph()[parent_nx];
+        let cached = self
+            .limited_candidates
+            .borrow()
+            .get(p_pkg.name.as_str())
+            .and_then(|(_removable, candidates)| candidates.get(&p_pkg.version))
+            .map(|(_, meta_reqs)| meta_reqs.get(child_name).cloned());
+
+        match cached {
+            Some(req) => Ok(req),
+            None => Ok(self
+                .depops
+                .get_pkg_versionreq(p_pkg.name.as_str(), &p_pkg.version.to_string())?
+                .get(child_name)
+                .cloned()),
+        }
+    }
 
-        self.prepare_limited_candidates(issue_nx, debugger)?;
-        self.get_step_fix(issue_nx, debugger)
+    /// The version req of the parent on the child, which it depends on in the tree. Fails if the
+    /// req is missing, as when filtered out with the targets not audited for.
+    fn require_dep_req(
+        &self,
+        parent_nx: NodeIndex,
+        child_name: &str,
+    ) -> Result<VersionReq, AuditError> {
+        self.get_dep_req(parent_nx, child_name)?.ok_or_else(|| {
+            let p_pkg = &self.get_graph()[parent_nx];
+            AuditError::FunctionError(
+                Some(format!(
+                    "cannot find the requirement of {}@{} on {child_name}",
+                    p_pkg.name, p_pkg.version
+                )),
+                Some(parent_nx),
+            )
+        })
+    }
+
+    /// Candidates shall be prepared before checked.
+    fn not_prepared(name: &str) -> AuditError {
+        AuditError::InnerError(format!("candidates of {name} are not prepared"))
     }
 
     /// Get the fixing steps.
     fn get_step_fix(
         &self,
         issue_nx: NodeIndex,
-        debugger: &mut impl Write,
+        observer: &mut impl AuditObserver,
     ) -> Result<Vec<(NodeIndex, Version)>, AuditError> {
-        // let start = Instant::now();
-        let mut fixes = self.get_step_fix_inner(issue_nx, debugger)?;
+        let mut fixes = self.get_step_fix_inner(issue_nx, observer)?;
         let mut topo_fix = Vec::new();
 
         // Do topo sort here.
@@ -268,13 +700,6 @@ impl<D: DepOps> DepTreeManager<D> {
             }
         }
 
-        // writeln!(
-        //     debugger,
-        //     "[Deptree Time] get_step_fix: {:?}",
-        //     start.elapsed()
-        // )
-        // .unwrap();
-
         assert!(fixes.is_empty(), "Fatal, fixes mismatch with deptree.");
         Ok(topo_fix)
     }
@@ -283,7 +708,7 @@ impl<D: DepOps> DepTreeManager<D> {
     fn get_step_fix_inner(
         &self,
         issue_nx: NodeIndex,
-        debugger: &mut impl Write,
+        observer: &mut impl AuditObserver,
     ) -> Result<FxHashMap<NodeIndex, Version>, AuditError> {
         let graph = self.get_graph();
         let dep = &graph[issue_nx];
@@ -293,21 +718,10 @@ impl<D: DepOps> DepTreeManager<D> {
         let mut fix = FxHashMap::default();
         let limited_candidates_borrow = self.limited_candidates.borrow();
 
-        // println!(
-        //     "[DEBUG] name: {dep_name}, limited_candidates_borrow: {:?}",
-        //     limited_candidates_borrow
-        //         .iter()
-        //         .map(|(name, (_, vers))| format!(
-        //             "{} - {:?}\n",
-        //             name,
-        //             vers.iter()
-        //                 .map(|(v, _)| v.to_string())
-        //                 .collect::<Vec<String>>()
-        //         ))
-        //         .collect::<Vec<String>>()
-        // );
         // 1. Check direct fixable first.
-        let (_removable, candidates) = &limited_candidates_borrow.get(&dep_name).unwrap();
+        let (_removable, candidates) = limited_candidates_borrow
+            .get(&dep_name)
+            .ok_or_else(|| Self::not_prepared(&dep_name))?;
 
         let limits_on_candidates = self.limited_fix.borrow().get(&dep_name).cloned();
         let candidates = candidates
@@ -319,96 +733,67 @@ impl<D: DepOps> DepTreeManager<D> {
 
         // And here we check whether these ruf-oks are acceptable by parents.
         let req_ok_candidates = self.get_req_ok_candidates(issue_nx, &ruf_ok_candidates)?;
-        // writeln!(
-        //     debugger,
-        //     "[Deptree Debug] issue_fixable: check issue deps:\n ruf ok: {:?}\n req ok: {:?}",
-        //     ruf_ok_candidates
-        //         .iter()
-        //         .map(|v| v.to_string())
-        //         .collect::<Vec<_>>(),
-        //     req_ok_candidates
-        //         .iter()
-        //         .map(|v| v.to_string())
-        //         .collect::<Vec<_>>()
-        // )
-        // .unwrap();
         if !req_ok_candidates.is_empty() {
             // Ok we have usable versions here.
-            fix.insert(issue_nx, req_ok_candidates[0].clone());
+            let ver = self.objective.pick(&dep.version, &req_ok_candidates);
+            fix.insert(issue_nx, ver.clone());
             return Ok(fix);
         }
 
         // 2. Or we have to check the parents.
         // The main idea is to find usable parents that accept the ruf-ok childs.
+        // A parent shared by several chains shall take a version usable in all of them,
+        // or other parent combinations are searched, and then the next ruf-ok child.
         let ruf_ok_candidates = ruf_ok_candidates
             .into_iter()
             .map(|v| Some(v))
             .chain(vec![None]);
+        let mut conflict = None;
         for usable_child in ruf_ok_candidates {
-            // writeln!(
-            //     debugger,
-            //     "[Deptree Debut] issue_fixable: trying issue dep {} candidates {}",
-            //     dep_name,
-            //     usable_child
-            //         .map(|v| v.to_string())
-            //         .unwrap_or("None".to_string())
-            // )
-            // .unwrap();
-            let mut chain = match self.get_req_ok_parents(issue_nx, usable_child, debugger) {
-                Ok(chain) => chain,
-                Err(e) => {
-                    if e.is_inner() {
-                        return Err(e);
-                    } else {
-                        continue;
-                    }
-                }
-            };
+            observer.on_event(AuditEvent::TryCandidate {
+                package: format!("{}@{}", dep_name, dep_ver),
+                candidate: usable_child.cloned(),
+            });
 
+            let mut choices = Choices::default();
             if let Some(child) = usable_child {
-                chain.push((issue_nx, child.clone()));
+                choices.insert(issue_nx, Some(child.clone()));
             }
-
-            let mut incompatible_update = false;
-            for (p, ver) in chain {
-                assert!(graph[p].version != ver, "Fatal, try update the same node");
-                if let Some(old_ver) = fix.get(&p) {
-                    if check_compatible(old_ver, &ver)? {
-                        if old_ver < &ver {
-                            fix.insert(p, ver);
-                        }
-                    } else {
-                        writeln!(debugger,
-                            "[Deptree Notice] multiple incompatible fix on parent found when choose child {}@{}, incompatible on {} with {} and {}",
-                            dep_name, usable_child.map(|v| v.to_string()).unwrap_or("None".to_string()),
-                            graph[p].name, old_ver, ver,
-                        ).unwrap();
-                        incompatible_update = true;
-                        break;
-                    }
-                } else {
+            let found = search_parents(self, issue_nx, usable_child, choices, &mut |c| {
+                let parent = format!("{}@{}", graph[c.parent].name, graph[c.parent].version);
+                let required = c.required.into_iter().collect::<Vec<_>>();
+                conflict = Some(format!(
+                    "conflict fixes on parent {}: choosing {}@{} needs it to be one of [{}] and [{}]",
+                    parent,
+                    dep_name,
+                    usable_child.map(|v| v.to_string()).unwrap_or("removed".to_string()),
+                    required.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", "),
+                    c.conflicting.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", "),
+                ));
+                observer.on_event(AuditEvent::ParentConflict {
+                    package: format!("{}@{}", dep_name, dep_ver),
+                    candidate: usable_child.cloned(),
+                    parent,
+                    required,
+                    conflicting: c.conflicting,
+                });
+            })?;
+
+            if let Some(choices) = found {
+                for (p, ver) in choices {
+                    // Removed parents are not fixed, but their parents are.
+                    let Some(ver) = ver else {
+                        continue;
+                    };
+                    assert!(ver != graph[p].version, "Fatal, try update the same node");
                     fix.insert(p, ver);
                 }
-            }
-
-            if incompatible_update {
-                // This can be a really complex issue, currently we ignore it.
-                fix.clear();
-                continue;
-            } else {
                 return Ok(fix);
             }
         }
 
-        fn check_compatible(v1: &Version, v2: &Version) -> Result<bool, AuditError> {
-            let min = min(v1, v2);
-            let req = VersionReq::parse(&format!("^{min}"))
-                .map_err(|e| AuditError::InnerError(e.to_string()))?;
-            return Ok(req.matches(v1) && req.matches(v2));
-        }
-
         return Err(AuditError::FunctionError(
-            Some("no usable parent chain found".to_string()),
+            Some(conflict.unwrap_or("no usable parent chain found".to_string())),
             Some(issue_nx),
         ));
     }
@@ -420,12 +805,17 @@ impl<D: DepOps> DepTreeManager<D> {
         pkg_ver: &str,
         candidates: impl Iterator<Item = (&'ctx Version, &'ctx CondRufs)>,
     ) -> Result<Vec<&'ctx Version>, AuditError> {
+        let yanked = self.depops.get_yanked(pkg_name)?;
         let mut usable = Vec::new();
         for (ver, condrufs) in candidates.into_iter() {
+            if yanked.contains(ver) && !self.yank_policy.allows(ver.to_string() == pkg_ver) {
+                continue;
+            }
+
             let rufs =
                 self.depops
                     .resolve_condrufs(&self.depresolve.0, &pkg_name, &pkg_ver, &condrufs)?;
-            let issue_rufs = self.depops.filter_rufs(self.rustv, rufs);
+            let issue_rufs = self.depops.filter_rufs(self.rustv, pkg_name, rufs);
 
             if issue_rufs.is_empty() {
                 usable.push(ver);
@@ -447,186 +837,27 @@ impl<D: DepOps> DepTreeManager<D> {
     ) -> Result<Vec<&'ctx Version>, AuditError> {
         let graph = self.get_graph();
         let pkg_name = graph[pkg_nx].name.as_str();
-        let limited_candidates_borrow = self.limited_candidates.borrow();
 
         // Collect parents' version req on current package.
         let mut version_reqs = Vec::new();
         let parents = self.get_parents_sorted(pkg_nx);
         for p in parents {
-            let p_pkg = &graph[p];
-
-            let req = match limited_candidates_borrow
-                .get(p_pkg.name.as_str())
-                .and_then(|(_removable, candidates)| candidates.get(&p_pkg.version))
-                .map(|(_, meta_reqs)| {
-                    meta_reqs
-                        .get(pkg_name)
-                        .expect("Fatal, cannot find dependency in parent package")
-                        .clone()
-                }) {
-                Some(req) => req,
-                None => self
-                    .depops
-                    .get_pkg_versionreq(p_pkg.name.as_str(), &p_pkg.version.to_string())?
-                    .get(pkg_name)
-                    .expect("Fatal, cannot find dependency in parent package")
-                    .clone(),
-            };
-
-            version_reqs.push((p, req));
+            version_reqs.push((p, self.require_dep_req(p, pkg_name)?));
         }
 
         let usable = candidates
             .into_iter()
-            .filter(|ver| version_reqs.iter().all(|(_, req)| req.matches(ver)))
+            .filter(|ver| {
+                version_reqs
+                    .iter()
+                    .all(|(p, req)| self.req_relaxed(p) || req.matches(ver))
+            })
             .map(|ver| *ver)
             .collect();
 
         Ok(usable)
     }
 
-    /// Get max usable parents version chain in tree that accept the given child version.
-    fn get_req_ok_parents(
-        &self,
-        child_nx: NodeIndex,
-        child: Option<&Version>,
-        debugger: &mut impl Write,
-    ) -> Result<Vec<(NodeIndex, Version)>, AuditError> {
-        let parents = self.get_parents_sorted(child_nx);
-        let graph = self.get_graph();
-        let mut fixes = Vec::new();
-
-        let limited_candidates_borrow = self.limited_candidates.borrow();
-
-        for p in parents {
-            if let Some(child) = child {
-                let p_pkg = &graph[p];
-                let child_pkg = &graph[child_nx];
-
-                let req = limited_candidates_borrow
-                    .get(p_pkg.name.as_str())
-                    .and_then(|(_removable, candidates)| candidates.get(&p_pkg.version))
-                    .map(|(_, meta_reqs)| {
-                        meta_reqs
-                            .get(child_pkg.name.as_str())
-                            .expect("Fatal, cannot find dependency in parent package")
-                            .clone()
-                    })
-                    .unwrap_or_else(|| {
-                        self.depops
-                            .get_pkg_versionreq(p_pkg.name.as_str(), &p_pkg.version.to_string())
-                            .unwrap()
-                            .get(child_pkg.name.as_str())
-                            .expect("Fatal, cannot find dependency in parent package")
-                            .clone()
-                    });
-                if req.matches(child) {
-                    // Ok this is not the limit parents.
-                    continue;
-                }
-            }
-
-            // writeln!(
-            //     debugger,
-            //     "[Deptree Debug] get_req_ok_parents: check parent {}@{} - child {}@{}",
-            //     graph[p].name,
-            //     graph[p].version,
-            //     graph[child_nx].name,
-            //     child.map(|v| v.to_string()).unwrap_or("None".to_string())
-            // )
-            // .unwrap();
-            if self.is_local(&p) {
-                // local reached, we check whether this child is acceptable or not.
-                if child.is_none() {
-                    // Of course locals cannot remove this child.
-                    return Err(AuditError::FunctionError(None, None));
-                }
-
-                let (_, meta_reqs) = limited_candidates_borrow
-                    .get(graph[p].name.as_str())
-                    .unwrap()
-                    .1
-                    .get(&graph[p].version)
-                    .unwrap();
-
-                let req = meta_reqs
-                    .get(graph[child_nx].name.as_str())
-                    .expect("Fatal, cannot find dependency in parent package");
-
-                if req.matches(child.unwrap()) {
-                    continue;
-                } else {
-                    return Err(AuditError::FunctionError(None, None));
-                }
-            }
-
-            let req_ok_parents = self.get_req_ok_parent(p, child_nx, child)?;
-            let usables = self.get_req_ok_candidates(p, &req_ok_parents.iter().collect())?;
-            // writeln!(
-            //     debugger,
-            //     "[Deptree Debug] get_req_ok_parents: checking parent {}:\n req ok: {:?}\n usable: {:?}",
-            //     graph[p].name,
-            //     req_ok_parents.iter().map(|v| v.to_string()).collect::<Vec<_>>(),
-            //     usables.iter().map(|v| v.to_string()).collect::<Vec<_>>()
-            // )
-            // .unwrap();
-            if !usables.is_empty() {
-                // Ok we find needed parents.
-                // writeln!(
-                //     debugger,
-                //     "[Deptree Debug] get_req_ok_parents: found usable parent for {}@{}: {:?}",
-                //     graph[child_nx].name,
-                //     child.map(|v| v.to_string()).unwrap_or("None".to_string()),
-                //     usables[0].to_string(),
-                // )
-                // .unwrap();
-                fixes.push((p, usables[0].clone()));
-            } else {
-                // Or we still need to go up, try from latest req_ok_parents.
-                let mut chain = Vec::new();
-                for req_ok_p in req_ok_parents.iter().map(|v| Some(v)).chain(vec![None]) {
-                    chain = match self.get_req_ok_parents(p, req_ok_p, debugger) {
-                        Ok(chain) => chain,
-                        Err(e) => {
-                            // writeln!(debugger,
-                            //     "[Deptree Debug] get_req_ok_parents: found no usable chain for {}@{}",
-                            //     graph[child_nx].name,
-                            //     child.map(|v| v.to_string()).unwrap_or("None".to_string())
-                            // ).unwrap();
-                            if e.is_inner() {
-                                return Err(e);
-                            } else {
-                                continue;
-                            }
-                        }
-                    };
-                    if let Some(ok_ver) = req_ok_p {
-                        chain.push((p, ok_ver.clone()));
-                    }
-                    // writeln!(
-                    //     debugger,
-                    //     "[Deptree Debug] get_req_ok_parents: found usable chain for {}@{}: {:?}",
-                    //     graph[child_nx].name,
-                    //     child.map(|v| v.to_string()).unwrap_or("None".to_string()),
-                    //     chain
-                    //         .iter()
-                    //         .map(|(x, v)| format!("{}@{}", graph[*x].name, v))
-                    //         .collect::<Vec<_>>()
-                    // )
-                    // .unwrap();
-                    break;
-                }
-                if chain.is_empty() {
-                    // No usable parents found, the fix failed.
-                    return Err(AuditError::FunctionError(None, None));
-                }
-                fixes.extend(chain.into_iter());
-            }
-        }
-
-        Ok(fixes)
-    }
-
     /// Get all usable versions of one parent.
     fn get_req_ok_parent(
         &self,
@@ -643,19 +874,9 @@ impl<D: DepOps> DepTreeManager<D> {
         let child_name = child_pkg.name.as_str();
 
         let limited_candidates_borrow = self.limited_candidates.borrow();
-
-        // println!(
-        //     "[DEBUG] parent_name: {parent_name}, limited_candidates_borrow: {:?}",
-        //     limited_candidates_borrow
-        //         .iter()
-        //         .map(|(name, (_, vers))| format!(
-        //             "{} - {:?}\n",
-        //             name,
-        //             vers.iter().map(|(v, _)| v.to_string()).collect::<Vec<String>>()
-        //         ))
-        //         .collect::<Vec<String>>()
-        // );
-        let (_removable, parent_candidates) = &limited_candidates_borrow.get(parent_name).unwrap();
+        let (_removable, parent_candidates) = limited_candidates_borrow
+            .get(parent_name)
+            .ok_or_else(|| Self::not_prepared(parent_name))?;
 
         let limits_on_candidates = self.limited_fix.borrow().get(parent_name).cloned();
         let parent_candidates_iter = parent_candidates
@@ -668,7 +889,9 @@ impl<D: DepOps> DepTreeManager<D> {
 
         let mut usable = Vec::new();
         for p in ruf_ok_candidates {
-            let (_, meta_reqs) = parent_candidates.get(&p).unwrap();
+            let (_, meta_reqs) = parent_candidates
+                .get(p)
+                .ok_or_else(|| Self::not_prepared(parent_name))?;
             if let Some(child) = child {
                 if let Some(req) = meta_reqs.get(child_name) {
                     if req.matches(child) {
@@ -696,10 +919,9 @@ impl<D: DepOps> DepTreeManager<D> {
             .collect()
     }
 
-    /// Get all parents (up to root), not sorted.
+    /// Get all parents (up to roots), not sorted.
     fn get_all_parents(&self, depnx: NodeIndex) -> FxHashSet<NodeIndex> {
         let graph = self.get_graph();
-        let root = self.get_root();
 
         let mut ancestors = FxHashSet::default();
         let mut stack = vec![depnx];
@@ -710,10 +932,6 @@ impl<D: DepOps> DepTreeManager<D> {
                 }
             }
         }
-        assert!(ancestors.contains(&root), "Fatal, root not in ancestors");
-        // FIXME: We remove the virt node, shall only used in virt fix.
-        ancestors.remove(&root);
-
         ancestors
     }
 
@@ -734,32 +952,14 @@ impl<D: DepOps> DepTreeManager<D> {
     fn prepare_limited_candidates(
         &self,
         pkg_nx: NodeIndex,
-        debugger: &mut impl Write,
+        observer: &mut impl AuditObserver,
     ) -> Result<(), AuditError> {
-        // let start = Instant::now();
         let all_parents = self.get_all_parents(pkg_nx);
         let topos = self.get_topo_sort(all_parents);
 
-        // let graph = self.get_graph();
         for nx in topos {
-            // if self
-            //     .limited_candidates
-            //     .borrow()
-            //     .get(graph[nx].name.as_str())
-            //     .is_some()
-            //     && self.check_node_unique(nx, debugger)
-            // {
-            //     continue;
-            // }
-            self.prepare_limited_candidates_inner(nx, debugger)?;
-        }
-
-        // writeln!(
-        //     debugger,
-        //     "[Deptree Time] prepare_limited_candidates: {:?}",
-        //     start.elapsed()
-        // )
-        // .unwrap();
+            self.prepare_limited_candidates_inner(nx, observer)?;
+        }
 
         Ok(())
     }
@@ -767,20 +967,13 @@ impl<D: DepOps> DepTreeManager<D> {
     fn prepare_limited_candidates_inner(
         &self,
         pkg_nx: NodeIndex,
-        debugger: &mut impl Write,
+        observer: &mut impl AuditObserver,
     ) -> Result<(), AuditError> {
         let graph = self.get_graph();
         let pkg = &graph[pkg_nx];
         let pkg_name = pkg.name.to_string();
         let pkg_ver = pkg.version.to_string();
 
-        // writeln!(
-        //     debugger,
-        //     "[Deptree Debug] prepare_limited_candidates_inner: prepare candidates {}@{}.",
-        //     pkg_name, pkg_ver
-        // )
-        // .unwrap();
-
         // Is it local?
         if self.is_local(&pkg_nx) {
             let meta_reqs = self.depops.get_pkg_versionreq(&pkg_name, &pkg_ver)?;
@@ -788,13 +981,6 @@ impl<D: DepOps> DepTreeManager<D> {
 
             datas.insert(pkg.version.clone(), (CondRufs::empty(), meta_reqs));
 
-            // writeln!(
-            //     debugger,
-            //     "[Deptree Debug] prepare_limited_candidates_inner: it's local parent {}@{}.",
-            //     pkg_name, pkg_ver
-            // )
-            // .unwrap();
-
             self.limited_candidates
                 .borrow_mut()
                 .insert(pkg_name.clone(), (false, datas));
@@ -810,11 +996,16 @@ impl<D: DepOps> DepTreeManager<D> {
             let p_pkg = &graph[p];
 
             let limited_candidates_borrow = self.limited_candidates.borrow();
-            let (p_removable, datas) = limited_candidates_borrow.get(p_pkg.name.as_str()).unwrap();
+            let (p_removable, datas) = limited_candidates_borrow
+                .get(p_pkg.name.as_str())
+                .ok_or_else(|| Self::not_prepared(p_pkg.name.as_str()))?;
 
             if *p_removable {
                 // We donot need to filter it.
                 removable.push(true);
+            } else if self.req_relaxed(&p) {
+                // The requirement will be edited.
+                removable.push(false);
             } else {
                 let mut all_reqs = FxHashSet::default();
                 for (_, (_, meta_reqs)) in datas.iter() {
@@ -840,6 +1031,17 @@ impl<D: DepOps> DepTreeManager<D> {
             }
         }
 
+        if self.upgrade {
+            possible_candidates.retain(|v, _| *v >= pkg.version);
+        }
+
+        // Cargo refuses to move to yanked ones. The locked one is kept here for its reqs,
+        // and checked with the policy when fixing.
+        let yanked = self.depops.get_yanked(&pkg_name)?;
+        possible_candidates.retain(|v, _| {
+            !yanked.contains(v) || *v == pkg.version || self.yank_policy.allows(false)
+        });
+
         if possible_candidates.get(&pkg.version).is_none() {
             // Normally it won't happen, but our version_ruf db may lack infos, and thus cause the parent or the parent's parents
             // not exist. And when this happens, we add current version to the candidates, and set CondRuf to uncond ruf usage.
@@ -857,12 +1059,9 @@ impl<D: DepOps> DepTreeManager<D> {
                 })
                 .collect();
 
-            writeln!(
-                debugger,
-                "[Deptree Notice] package {}@{} meet with info missing in DB version_ruf.",
-                pkg_name, pkg.version
-            )
-            .unwrap();
+            observer.on_event(AuditEvent::MissingRufData {
+                package: format!("{}@{}", pkg_name, pkg_ver),
+            });
 
             possible_candidates.insert(pkg.version.clone(), CondRufs::new(rufs));
         }
@@ -875,12 +1074,6 @@ impl<D: DepOps> DepTreeManager<D> {
             {
                 Ok(reqs) => reqs,
                 Err(_e) => {
-                    // writeln!(
-                    //     debugger,
-                    //     "[Deptree Debug] prepare_limited_candidates_inner: {}@{} get reqs failed with error {:?}.",
-                    //     pkg_name, candidate, e
-                    // )
-                    // .unwrap();
                     continue;
                 }
             };
@@ -888,15 +1081,16 @@ impl<D: DepOps> DepTreeManager<D> {
             datas.insert(candidate, (condrufs, meta_reqs));
         }
 
-        // writeln!(
-        //     debugger,
-        //     "[Deptree Debug] prepare_limited_candidates_inner: possible candidates (X {}) for {pkg_name} {:?}",
-        //     removable,
-        //     datas.keys().map(|v| v.to_string()).collect::<Vec<String>>()
-        // )
-        // .unwrap();
-
         let removable = removable.into_iter().all(|r| r);
+
+        let mut candidates = datas.keys().cloned().collect::<Vec<_>>();
+        candidates.sort();
+        observer.on_event(AuditEvent::Candidates {
+            package: format!("{}@{}", pkg_name, pkg_ver),
+            removable,
+            candidates,
+        });
+
         let mut limited_candidates_borrow_mut = self.limited_candidates.borrow_mut();
         let entry = limited_candidates_borrow_mut
             .entry(pkg_name)
@@ -917,3 +1111,49 @@ impl<D: DepOps> DepTreeManager<D> {
         }
     }
 }
+
+impl<D: DepOps> ParentTree for DepTreeManager<D> {
+    type Node = NodeIndex;
+
+    fn parents(&self, nx: NodeIndex) -> Vec<NodeIndex> {
+        self.get_parents_sorted(nx)
+    }
+
+    fn is_local(&self, nx: NodeIndex) -> bool {
+        DepTreeManager::is_local(self, &nx)
+    }
+
+    fn accepts(
+        &self,
+        parent: NodeIndex,
+        child: NodeIndex,
+        ver: &Version,
+    ) -> Result<bool, AuditError> {
+        if self.req_relaxed(&parent) {
+            return Ok(true);
+        }
+        let req = self.require_dep_req(parent, self.get_graph()[child].name.as_str())?;
+        Ok(req.matches(ver))
+    }
+
+    /// The one the objective picks first, and then from latest to oldest.
+    fn req_ok_parent(
+        &self,
+        parent: NodeIndex,
+        child: NodeIndex,
+        ver: Option<&Version>,
+    ) -> Result<Vec<Version>, AuditError> {
+        let mut usable = self.get_req_ok_parent(parent, child, ver)?;
+        if !usable.is_empty() {
+            let current = &self.get_graph()[parent].version;
+            let picked = self
+                .objective
+                .pick(current, &usable.iter().collect::<Vec<_>>())
+                .clone();
+            usable.retain(|v| *v != picked);
+            usable.insert(0, picked);
+        }
+
+        Ok(usable)
+    }
+}
@@ -1,4 +1,7 @@
-use cargo_lock::dependency::graph::NodeIndex;
+use cargo_lock::dependency::graph::{Graph, NodeIndex};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use super::failure::{FixFailure, RustcFailure};
 
 #[derive(Debug)]
 pub enum AuditError {
@@ -6,6 +9,10 @@ pub enum AuditError {
     InnerError(String),
     /// Fix failure errors, and record which dep cause it.
     FunctionError(Option<String>, Option<NodeIndex>),
+    /// The issue package cannot be fixed, with the explanation.
+    Unfixable(Box<FixFailure>),
+    /// No rustc version can be fixed, with the failure on each of them, from newest to oldest.
+    NoFix(Vec<RustcFailure>),
 }
 
 impl AuditError {
@@ -16,4 +23,76 @@ impl AuditError {
             _ => false,
         }
     }
+
+    /// The error kind, `inner` or `fix`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::InnerError(_) => "inner",
+            Self::FunctionError(_, _) | Self::Unfixable(_) | Self::NoFix(_) => "fix",
+        }
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            Self::InnerError(e) => Some(e),
+            Self::FunctionError(e, _) => e.as_deref(),
+            Self::Unfixable(failure) => failure.note.as_deref(),
+            Self::NoFix(_) => Some("no rustc version can be fixed"),
+        }
+    }
+
+    /// Get the `name@ver` of the dep causing the fix failure, in the given graph.
+    pub fn package(&self, graph: &Graph) -> Option<String> {
+        match self {
+            Self::FunctionError(_, Some(nx)) => graph
+                .node_weight(*nx)
+                .map(|pkg| format!("{}@{}", pkg.name, pkg.version)),
+            Self::Unfixable(failure) => Some(failure.package.clone()),
+            _ => None,
+        }
+    }
+
+    /// A one line description, for logs and events.
+    pub fn describe(&self) -> String {
+        match self.message() {
+            Some(message) => format!("{} error: {}", self.kind(), message),
+            None => format!("{} error", self.kind()),
+        }
+    }
+
+    pub fn failure(&self) -> Option<&FixFailure> {
+        match self {
+            Self::Unfixable(failure) => Some(failure),
+            _ => None,
+        }
+    }
+
+    /// The failure explanation in human readable text, like cargo resolver errors.
+    pub fn explain(&self) -> Option<String> {
+        match self {
+            Self::Unfixable(failure) => Some(failure.to_string()),
+            Self::NoFix(trace) => Some(trace.iter().map(|rustc| rustc.to_string()).collect()),
+            _ => None,
+        }
+    }
+}
+
+/// The node index only makes sense with its graph, so here we only keep the kind and message,
+/// along with the failure explanation if any.
+impl Serialize for AuditError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("AuditError", 5)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.message())?;
+        state.serialize_field("failure", &self.failure())?;
+        state.serialize_field(
+            "trace",
+            &match self {
+                Self::NoFix(trace) => Some(trace),
+                _ => None,
+            },
+        )?;
+        state.serialize_field("explain", &self.explain())?;
+        state.end()
+    }
 }
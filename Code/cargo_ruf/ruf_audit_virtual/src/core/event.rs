@@ -0,0 +1,244 @@
+use std::fmt::{self, Display};
+use std::io::Write;
+
+use semver::Version;
+use serde::Serialize;
+
+/// A package moved from one version to another.
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionChange {
+    pub name: String,
+    pub from: Version,
+    pub to: Version,
+}
+
+/// What happens during an audit, packages are named `name@ver`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// Start checking a rustc version.
+    CheckRustc { rustv: u32, toolchain: String },
+    /// A package using rufs not usable on the rustc.
+    IssueFound { package: String, rufs: Vec<String> },
+    /// The issue package can be fixed with the changes.
+    Fixable {
+        package: String,
+        changes: Vec<VersionChange>,
+    },
+    /// The issue package cannot be fixed.
+    Unfixable { package: String, error: String },
+    /// Start applying fixes on the issue package, under the limits on versions.
+    FixStart {
+        package: String,
+        limits: Vec<(String, String)>,
+    },
+    /// One fix step applied, with the changes remaining.
+    FixStep {
+        package: String,
+        change: VersionChange,
+        remaining: Vec<VersionChange>,
+    },
+    /// The issue package is gone from the dependency tree after fixing.
+    IssueGone { package: String },
+    /// Candidates of a package, `removable` if it can be dropped from the tree.
+    Candidates {
+        package: String,
+        removable: bool,
+        candidates: Vec<Version>,
+    },
+    /// A candidate of the issue package is tried, `None` means to remove it.
+    TryCandidate {
+        package: String,
+        candidate: Option<Version>,
+    },
+    /// Two parent chains require incompatible versions of the parent.
+    ParentConflict {
+        package: String,
+        candidate: Option<Version>,
+        parent: String,
+        required: Vec<Version>,
+        conflicting: Vec<Version>,
+    },
+    /// The package is missing in the ruf data, only the current version is used.
+    MissingRufData { package: String },
+    /// The rustc has no issues.
+    RustcNoIssue { rustv: u32 },
+    /// Issues on the rustc are all fixed.
+    RustcFixed { rustv: u32 },
+    /// Issues on the rustc cannot be fixed, or failed when fixing.
+    RustcFailed { rustv: u32, error: String },
+    /// Disabling the feature changes the number of issue packages.
+    FeatureDisabled {
+        feature: String,
+        issues_before: usize,
+        issues_after: usize,
+    },
+    /// Resolve fails without the feature.
+    FeatureResolveFailed { feature: String, error: String },
+    /// No feature can be disabled to reduce issues.
+    FeatureSearchStuck { issues: usize },
+    /// The rustc can or cannot use the rufs of the root crates.
+    RootChecked {
+        rustv: u32,
+        roots: Vec<String>,
+        rufs: Vec<String>,
+    },
+}
+
+/// Receives the events of an audit, to show progress, collect metrics or record traces.
+pub trait AuditObserver {
+    fn on_event(&mut self, event: AuditEvent);
+}
+
+/// Ignores all events.
+impl AuditObserver for () {
+    fn on_event(&mut self, _event: AuditEvent) {}
+}
+
+/// Records all events in order, which can be serialized and replayed.
+impl AuditObserver for Vec<AuditEvent> {
+    fn on_event(&mut self, event: AuditEvent) {
+        self.push(event);
+    }
+}
+
+impl<O: AuditObserver + ?Sized> AuditObserver for &mut O {
+    fn on_event(&mut self, event: AuditEvent) {
+        (**self).on_event(event);
+    }
+}
+
+/// Writes events as log lines.
+pub struct TextObserver<W: Write>(pub W);
+
+impl<W: Write> AuditObserver for TextObserver<W> {
+    fn on_event(&mut self, event: AuditEvent) {
+        writeln!(self.0, "[VirtAudit] {}", event).unwrap();
+    }
+}
+
+impl Display for VersionChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{} -> {}", self.name, self.from, self.to)
+    }
+}
+
+fn join<T: Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn or_none(ver: &Option<Version>) -> String {
+    ver.as_ref()
+        .map(|v| v.to_string())
+        .unwrap_or("None".to_string())
+}
+
+impl Display for AuditEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CheckRustc { rustv, toolchain } => {
+                write!(f, "checking rustc {} ({})", rustv, toolchain)
+            }
+            Self::IssueFound { package, rufs } => {
+                write!(f, "found issue package {} rufs: [{}]", package, join(rufs))
+            }
+            Self::Fixable { package, changes } => {
+                write!(f, "issue {} is fixable with [{}]", package, join(changes))
+            }
+            Self::Unfixable { package, error } => {
+                write!(f, "issue {} is not fixable: {}", package, error)
+            }
+            Self::FixStart { package, limits } => write!(
+                f,
+                "start fixing {} with limits: [{}]",
+                package,
+                limits
+                    .iter()
+                    .map(|(name, req)| format!("{} {}", name, req))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::FixStep {
+                package,
+                change,
+                remaining,
+            } => write!(
+                f,
+                "fixing {}: {}, remaining [{}]",
+                package,
+                change,
+                join(remaining)
+            ),
+            Self::IssueGone { package } => write!(f, "issue {} already gone", package),
+            Self::Candidates {
+                package,
+                removable,
+                candidates,
+            } => write!(
+                f,
+                "candidates of {} (removable {}): [{}]",
+                package,
+                removable,
+                join(candidates)
+            ),
+            Self::TryCandidate { package, candidate } => {
+                write!(f, "trying {} candidate {}", package, or_none(candidate))
+            }
+            Self::ParentConflict {
+                package,
+                candidate,
+                parent,
+                required,
+                conflicting,
+            } => write!(
+                f,
+                "incompatible fixes on parent {} when choosing {} {}: [{}] and [{}]",
+                parent,
+                package,
+                or_none(candidate),
+                join(required),
+                join(conflicting)
+            ),
+            Self::MissingRufData { package } => {
+                write!(f, "package {} is missing in ruf data", package)
+            }
+            Self::RustcNoIssue { rustv } => write!(f, "rustc {} has no issues", rustv),
+            Self::RustcFixed { rustv } => write!(f, "rustc {} issues fixed", rustv),
+            Self::RustcFailed { rustv, error } => {
+                write!(f, "rustc {} cannot be fixed: {}", rustv, error)
+            }
+            Self::FeatureDisabled {
+                feature,
+                issues_before,
+                issues_after,
+            } => write!(
+                f,
+                "disable feature {}, issues {} -> {}",
+                feature, issues_before, issues_after
+            ),
+            Self::FeatureResolveFailed { feature, error } => {
+                write!(f, "resolve failed without feature {}: {}", feature, error)
+            }
+            Self::FeatureSearchStuck { issues } => {
+                write!(f, "no feature can be disabled to reduce {} issues", issues)
+            }
+            Self::RootChecked { rustv, roots, rufs } => {
+                if rufs.is_empty() {
+                    write!(f, "rustc {} fixed root crates [{}]", rustv, join(roots))
+                } else {
+                    write!(
+                        f,
+                        "rustc {} cannot fix root crates [{}] due to [{}]",
+                        rustv,
+                        join(roots),
+                        join(rufs)
+                    )
+                }
+            }
+        }
+    }
+}
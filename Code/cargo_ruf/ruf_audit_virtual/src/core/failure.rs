@@ -0,0 +1,130 @@
+use std::fmt::{self, Display};
+
+use semver::Version;
+use serde::Serialize;
+
+/// A candidate version rejected as it uses unusable rufs.
+#[derive(Debug, Clone, Serialize)]
+pub struct RufRejected {
+    pub version: Version,
+    pub rufs: Vec<String>,
+}
+
+/// A candidate version rejected by the version req of a parent.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReqRejected {
+    pub version: Version,
+    pub parent: String,
+    pub req: String,
+}
+
+/// Why a package cannot be fixed, rendered as a tree like cargo resolver errors.
+#[derive(Debug, Clone, Serialize)]
+pub struct FixFailure {
+    /// The package, `name@ver`.
+    pub package: String,
+    /// Unusable rufs used by the package now.
+    pub rufs: Vec<String>,
+    /// Candidates rejected by rufs, and by parents, from newest to oldest.
+    pub ruf_rejected: Vec<RufRejected>,
+    pub req_rejected: Vec<ReqRejected>,
+    /// Why the parents rejecting the candidates cannot be changed either.
+    pub causes: Vec<FixFailure>,
+    pub note: Option<String>,
+}
+
+/// Why the fix failed on one rustc version.
+#[derive(Debug, Clone, Serialize)]
+pub struct RustcFailure {
+    pub rustv: u32,
+    pub toolchain: String,
+    pub message: Option<String>,
+    pub failure: Option<FixFailure>,
+}
+
+impl FixFailure {
+    pub fn new(package: String, rufs: Vec<String>, note: Option<String>) -> Self {
+        Self {
+            package,
+            rufs,
+            ruf_rejected: Vec::new(),
+            req_rejected: Vec::new(),
+            causes: Vec::new(),
+            note,
+        }
+    }
+
+    fn render(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        let pad = "  ".repeat(indent);
+        write!(f, "{pad}{} cannot be fixed", self.package)?;
+        if let Some(note) = &self.note {
+            write!(f, ": {note}")?;
+        }
+        writeln!(f)?;
+
+        if !self.rufs.is_empty() {
+            writeln!(f, "{pad}  uses unusable rufs: {}", self.rufs.join(", "))?;
+        }
+
+        // Versions rejected for the same reason are listed together.
+        let mut by_rufs: Vec<(&Vec<String>, Vec<String>)> = Vec::new();
+        for rejected in self.ruf_rejected.iter() {
+            match by_rufs.iter_mut().find(|(rufs, _)| **rufs == rejected.rufs) {
+                Some((_, vers)) => vers.push(rejected.version.to_string()),
+                None => by_rufs.push((&rejected.rufs, vec![rejected.version.to_string()])),
+            }
+        }
+        for (rufs, vers) in by_rufs {
+            writeln!(
+                f,
+                "{pad}  versions {} rejected, using unusable rufs: {}",
+                vers.join(", "),
+                rufs.join(", ")
+            )?;
+        }
+
+        let mut by_req: Vec<((&str, &str), Vec<String>)> = Vec::new();
+        for rejected in self.req_rejected.iter() {
+            let key = (rejected.parent.as_str(), rejected.req.as_str());
+            match by_req.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, vers)) => vers.push(rejected.version.to_string()),
+                None => by_req.push((key, vec![rejected.version.to_string()])),
+            }
+        }
+        for ((parent, req), vers) in by_req {
+            writeln!(
+                f,
+                "{pad}  versions {} rejected, {parent} requires {req}",
+                vers.join(", ")
+            )?;
+        }
+
+        for cause in self.causes.iter() {
+            writeln!(f, "{pad}  because:")?;
+            cause.render(f, indent + 2)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for FixFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.render(f, 0)
+    }
+}
+
+impl Display for RustcFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rustc {}", self.toolchain)?;
+        match &self.message {
+            Some(message) => writeln!(f, ": {message}")?,
+            None => writeln!(f)?,
+        }
+        if let Some(failure) = &self.failure {
+            failure.render(f, 1)?;
+        }
+
+        Ok(())
+    }
+}
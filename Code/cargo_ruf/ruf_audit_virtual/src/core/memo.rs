@@ -0,0 +1,60 @@
+use std::collections::VecDeque;
+use std::hash::Hash;
+
+use fxhash::FxHashMap;
+
+/// Memoized values, at most `capacity` of them, evicting the oldest ones first.
+pub struct Memo<K, V> {
+    capacity: usize,
+    values: FxHashMap<K, V>,
+    /// Keys from the oldest to the newest.
+    order: VecDeque<K>,
+}
+
+impl<K: Clone + Eq + Hash, V> Memo<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            values: FxHashMap::default(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.values.get(key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.values.insert(key.clone(), value).is_some() {
+            return;
+        }
+
+        self.order.push_back(key);
+        while self.order.len() > self.capacity {
+            let oldest = self.order.pop_front().unwrap();
+            self.values.remove(&oldest);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.order.clear();
+    }
+}
+
+#[test]
+fn test_memo() {
+    let mut memo = Memo::new(2);
+    memo.insert("a", 1);
+    memo.insert("b", 2);
+    // Updates keep the order.
+    memo.insert("a", 3);
+    memo.insert("c", 4);
+
+    assert_eq!(memo.get(&"a"), None);
+    assert_eq!(memo.get(&"b"), Some(&2));
+    assert_eq!(memo.get(&"c"), Some(&4));
+
+    memo.clear();
+    assert_eq!(memo.get(&"b"), None);
+}
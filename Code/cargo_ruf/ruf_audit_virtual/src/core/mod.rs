@@ -1,7 +1,13 @@
 mod deptree;
 mod depops;
 mod error;
+mod event;
+mod failure;
+mod memo;
+mod parents;
 
 pub use depops::DepOps;
 pub use deptree::DepTreeManager;
-pub use error::AuditError;
\ No newline at end of file
+pub use error::AuditError;
+pub use event::{AuditEvent, AuditObserver, TextObserver, VersionChange};
+pub use failure::{FixFailure, ReqRejected, RufRejected, RustcFailure};
\ No newline at end of file
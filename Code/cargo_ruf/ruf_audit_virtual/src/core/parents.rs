@@ -0,0 +1,376 @@
+use std::hash::Hash;
+
+use fxhash::FxHashMap;
+use semver::Version;
+
+use super::error::AuditError;
+
+/// Parent combinations tried at most for one child version, before giving it up.
+const MAX_SEARCH_STEPS: usize = 1024;
+
+/// Versions chosen for the packages changed, `None` if removed from the tree.
+pub type Choices<N> = FxHashMap<N, Option<Version>>;
+
+/// The dependency tree as the parent search sees it.
+pub trait ParentTree {
+    type Node: Copy + Eq + Hash;
+
+    /// Direct parents, from the roots down.
+    fn parents(&self, nx: Self::Node) -> Vec<Self::Node>;
+    fn is_local(&self, nx: Self::Node) -> bool;
+    /// Whether the current version of the parent accepts the version of the child.
+    fn accepts(
+        &self,
+        parent: Self::Node,
+        child: Self::Node,
+        ver: &Version,
+    ) -> Result<bool, AuditError>;
+    /// Usable versions of the parent accepting the version of the child, or not depending on
+    /// it if `None`, in the order to try.
+    fn req_ok_parent(
+        &self,
+        parent: Self::Node,
+        child: Self::Node,
+        ver: Option<&Version>,
+    ) -> Result<Vec<Version>, AuditError>;
+}
+
+/// A parent shared by chains requiring different versions of it.
+pub struct ParentConflict<N> {
+    pub parent: N,
+    /// The version chosen by a former chain, `None` if removed.
+    pub required: Option<Version>,
+    /// The versions accepted by the latter.
+    pub conflicting: Vec<Version>,
+}
+
+/// One child version some parent shall accept.
+#[derive(Clone)]
+struct Task<N> {
+    parent: N,
+    child: N,
+    ver: Option<Version>,
+}
+
+/// Search versions of the parents, up to the locals, for the child to take the version, or to be
+/// removed if `None`. Parents are changed one at a time in depth first order, and a parent shared
+/// by several chains shall take one version accepted by all of them, or other versions are tried
+/// for the parents changed before. Parents kept shall keep accepting the versions they were
+/// checked with, if changed by a later chain.
+///
+/// The choices start with the child ones, and come back with the parents added, `None` if no
+/// combination is found.
+pub fn search_parents<T: ParentTree>(
+    tree: &T,
+    child: T::Node,
+    ver: Option<&Version>,
+    choices: Choices<T::Node>,
+    on_conflict: &mut impl FnMut(ParentConflict<T::Node>),
+) -> Result<Option<Choices<T::Node>>, AuditError> {
+    let mut search = Search {
+        tree,
+        steps: 0,
+        on_conflict,
+    };
+    let pending = search.tasks(child, ver);
+    search.search(pending, Vec::new(), choices)
+}
+
+struct Search<'a, T: ParentTree, F> {
+    tree: &'a T,
+    steps: usize,
+    on_conflict: &'a mut F,
+}
+
+impl<T: ParentTree, F: FnMut(ParentConflict<T::Node>)> Search<'_, T, F> {
+    /// Tasks on the parents of the child, popped from the roots down.
+    fn tasks(&self, child: T::Node, ver: Option<&Version>) -> Vec<Task<T::Node>> {
+        let mut tasks = self
+            .tree
+            .parents(child)
+            .into_iter()
+            .map(|parent| Task {
+                parent,
+                child,
+                ver: ver.cloned(),
+            })
+            .collect::<Vec<_>>();
+        tasks.reverse();
+        tasks
+    }
+
+    /// Whether the version of the parent does what the task asks.
+    fn fulfills(&self, task: &Task<T::Node>, parent_ver: &Version) -> Result<bool, AuditError> {
+        Ok(self
+            .tree
+            .req_ok_parent(task.parent, task.child, task.ver.as_ref())?
+            .contains(parent_ver))
+    }
+
+    fn search(
+        &mut self,
+        mut pending: Vec<Task<T::Node>>,
+        kept: Vec<Task<T::Node>>,
+        choices: Choices<T::Node>,
+    ) -> Result<Option<Choices<T::Node>>, AuditError> {
+        let Some(task) = pending.pop() else {
+            return Ok(Some(choices));
+        };
+        self.steps += 1;
+        if self.steps > MAX_SEARCH_STEPS {
+            return Ok(None);
+        }
+
+        // Changed by a former chain already.
+        if let Some(chosen) = choices.get(&task.parent) {
+            let Some(chosen) = chosen else {
+                // Removed, so no more a parent.
+                return self.search(pending, kept, choices);
+            };
+            if self.fulfills(&task, chosen)? {
+                return self.search(pending, kept, choices);
+            }
+
+            (self.on_conflict)(ParentConflict {
+                parent: task.parent,
+                required: Some(chosen.clone()),
+                conflicting: self
+                    .tree
+                    .req_ok_parent(task.parent, task.child, task.ver.as_ref())?,
+            });
+            return Ok(None);
+        }
+
+        // Kept as it is.
+        if let Some(ver) = &task.ver
+            && self.tree.accepts(task.parent, task.child, ver)?
+        {
+            let mut kept = kept;
+            kept.push(task);
+            return self.search(pending, kept, choices);
+        }
+        if self.tree.is_local(task.parent) {
+            return Ok(None);
+        }
+
+        // Changed, to versions still fulfilling the tasks it was kept for, or removed.
+        let mut options = Vec::new();
+        for ver in self
+            .tree
+            .req_ok_parent(task.parent, task.child, task.ver.as_ref())?
+        {
+            let mut usable = true;
+            for kept_task in kept.iter().filter(|kept| kept.parent == task.parent) {
+                if !self.fulfills(kept_task, &ver)? {
+                    usable = false;
+                    break;
+                }
+            }
+            if usable {
+                options.push(Some(ver));
+            }
+        }
+        options.push(None);
+
+        for option in options {
+            let mut pending = pending.clone();
+            pending.extend(self.tasks(task.parent, option.as_ref()));
+            let mut choices = choices.clone();
+            choices.insert(task.parent, option);
+
+            if let Some(choices) = self.search(pending, kept.clone(), choices)? {
+                return Ok(Some(choices));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+use semver::VersionReq;
+
+#[cfg(test)]
+type Reqs = FxHashMap<usize, VersionReq>;
+
+/// Names and versions of the packages changed, sorted.
+#[cfg(test)]
+type Found = Option<Vec<(&'static str, Option<String>)>>;
+
+/// Packages by index, each with the current version and the candidates along with their
+/// reqs on the children.
+#[cfg(test)]
+struct FakeTree {
+    names: Vec<&'static str>,
+    current: Vec<Version>,
+    candidates: Vec<Vec<(Version, Reqs)>>,
+    parents: Vec<Vec<usize>>,
+    locals: Vec<usize>,
+}
+
+#[cfg(test)]
+impl FakeTree {
+    fn new() -> Self {
+        Self {
+            names: Vec::new(),
+            current: Vec::new(),
+            candidates: Vec::new(),
+            parents: Vec::new(),
+            locals: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, name: &'static str, current: &str) -> usize {
+        self.names.push(name);
+        self.current.push(Version::parse(current).unwrap());
+        self.candidates.push(Vec::new());
+        self.parents.push(Vec::new());
+        self.names.len() - 1
+    }
+
+    fn candidate(&mut self, nx: usize, ver: &str, reqs: &[(usize, &str)]) {
+        let reqs = reqs
+            .iter()
+            .map(|(child, req)| (*child, VersionReq::parse(req).unwrap()))
+            .collect();
+        self.candidates[nx].push((Version::parse(ver).unwrap(), reqs));
+    }
+
+    fn depend(&mut self, parent: usize, child: usize) {
+        self.parents[child].push(parent);
+    }
+
+    fn reqs(&self, nx: usize, ver: &Version) -> &Reqs {
+        &self.candidates[nx]
+            .iter()
+            .find(|(v, _)| v == ver)
+            .unwrap()
+            .1
+    }
+}
+
+#[cfg(test)]
+impl ParentTree for FakeTree {
+    type Node = usize;
+
+    fn parents(&self, nx: usize) -> Vec<usize> {
+        self.parents[nx].clone()
+    }
+
+    fn is_local(&self, nx: usize) -> bool {
+        self.locals.contains(&nx)
+    }
+
+    fn accepts(&self, parent: usize, child: usize, ver: &Version) -> Result<bool, AuditError> {
+        Ok(self.reqs(parent, &self.current[parent])[&child].matches(ver))
+    }
+
+    fn req_ok_parent(
+        &self,
+        parent: usize,
+        child: usize,
+        ver: Option<&Version>,
+    ) -> Result<Vec<Version>, AuditError> {
+        let mut usable = self.candidates[parent]
+            .iter()
+            .filter(|(_, reqs)| match ver {
+                Some(ver) => reqs.get(&child).is_some_and(|req| req.matches(ver)),
+                None => !reqs.contains_key(&child),
+            })
+            .map(|(v, _)| v.clone())
+            .collect::<Vec<_>>();
+        usable.sort();
+        usable.reverse();
+        Ok(usable)
+    }
+}
+
+/// A local root depending on `top`, which depends on `left` and `right`, both depending on
+/// `leaf`, all at 1.0.0.
+#[cfg(test)]
+fn diamond() -> (FakeTree, [usize; 5]) {
+    let mut tree = FakeTree::new();
+    let root = tree.add("root", "0.1.0");
+    let top = tree.add("top", "1.0.0");
+    let left = tree.add("left", "1.0.0");
+    let right = tree.add("right", "1.0.0");
+    let leaf = tree.add("leaf", "1.0.0");
+    tree.locals.push(root);
+    tree.candidate(root, "0.1.0", &[(top, "*")]);
+    tree.candidate(top, "1.0.0", &[(left, "=1.0.0"), (right, "=1.0.0")]);
+    tree.candidate(left, "1.0.0", &[(leaf, "=1.0.0")]);
+    tree.candidate(left, "2.0.0", &[(leaf, "^2")]);
+    tree.candidate(left, "3.0.0", &[(leaf, "^2")]);
+    tree.candidate(right, "1.0.0", &[(leaf, "=1.0.0")]);
+    tree.candidate(right, "2.0.0", &[(leaf, "^2")]);
+    tree.depend(root, top);
+    tree.depend(top, left);
+    tree.depend(top, right);
+    tree.depend(left, leaf);
+    tree.depend(right, leaf);
+
+    (tree, [root, top, left, right, leaf])
+}
+
+#[cfg(test)]
+fn search(tree: &FakeTree, child: usize, ver: &str) -> (Found, usize) {
+    let ver = Version::parse(ver).unwrap();
+    let mut choices = Choices::default();
+    choices.insert(child, Some(ver.clone()));
+    let mut conflicts = 0;
+    let found = search_parents(tree, child, Some(&ver), choices, &mut |_| conflicts += 1)
+        .unwrap()
+        .map(|choices| {
+            let mut choices = choices
+                .into_iter()
+                .map(|(nx, ver)| (tree.names[nx], ver.map(|v| v.to_string())))
+                .collect::<Vec<_>>();
+            choices.sort();
+            choices
+        });
+
+    (found, conflicts)
+}
+
+#[test]
+fn test_search_diamond() {
+    let (mut tree, [_, top, left, right, leaf]) = diamond();
+    // Only the older left fits the top taking the new right.
+    tree.candidate(top, "3.0.0", &[(left, "=3.0.0"), (right, "=1.0.0")]);
+    tree.candidate(top, "2.0.0", &[(left, "=2.0.0"), (right, "=2.0.0")]);
+
+    let (found, conflicts) = search(&tree, leaf, "2.0.0");
+    let some = |v: &str| Some(v.to_string());
+    assert_eq!(
+        found,
+        Some(vec![
+            ("leaf", some("2.0.0")),
+            ("left", some("2.0.0")),
+            ("right", some("2.0.0")),
+            ("top", some("2.0.0")),
+        ])
+    );
+    // The newest left is tried first, whose top takes neither the new right nor none.
+    assert_eq!(conflicts, 2);
+}
+
+#[test]
+fn test_search_conflict() {
+    let (mut tree, [root, top, left, right, leaf]) = diamond();
+    tree.candidate(top, "3.0.0", &[(left, "=3.0.0"), (right, "=1.0.0")]);
+    tree.candidate(top, "2.0.0", &[(left, "=1.0.0"), (right, "=2.0.0")]);
+
+    let (found, conflicts) = search(&tree, leaf, "2.0.0");
+    assert_eq!(found, None);
+    assert!(conflicts > 0);
+
+    tree.candidate(top, "4.0.0", &[(left, "=2.0.0"), (right, "=2.0.0")]);
+    let (found, _) = search(&tree, leaf, "2.0.0");
+    assert!(found.is_some());
+    // Locals are never changed.
+    tree.candidates[root][0]
+        .1
+        .insert(top, VersionReq::parse("=1.0.0").unwrap());
+    let (found, _) = search(&tree, leaf, "2.0.0");
+    assert_eq!(found, None);
+}
@@ -4,5 +4,19 @@ mod basic;
 mod core;
 mod virtops;
 
-pub use core::AuditError;
-pub use virtops::{audit, root_audit, treeonly_audit, Summary};
+pub use basic::{
+    Cfg, CfgExpr, CfgProfile, Channel, Date, FeatureSelection, Objective, RiskSignal, RufForecast,
+    RufPolicy, RustcRange, Timestamp, Toolchain, YankPolicy, POLICY_FILE,
+};
+pub use core::{
+    AuditError, AuditEvent, AuditObserver, DepOps, FixFailure, ReqRejected, RufRejected,
+    RustcFailure, TextObserver, VersionChange,
+};
+pub use virtops::{
+    apply_audit, audit, audit_concurrent, check_audit, diff_audit, feature_audit, forecast_audit,
+    optimize_audit, root_audit, treeonly_audit, upgrade_audit, AuditOptions, CachedSource,
+    Decision, DepInfo, DepKind, DepOpsLocal, DepOpsVirt, EdgeChange, ErrorRecord, FeatureReport,
+    FixCost, FixStep, ForecastReport, IndexSource, IntroducedRufs, IssueRecord, LockDiff,
+    MetaSource, NightlyWindow, PackageForecast, PgSource, RankedFix, Remediation, ReqEdit,
+    RufUsage, RustcDecision, Summary, UpgradePlan,
+};
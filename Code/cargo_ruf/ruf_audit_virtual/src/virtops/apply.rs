@@ -0,0 +1,91 @@
+use std::fs;
+use std::path::Path;
+
+use semver::Version;
+
+use super::audit::check_fix;
+use super::options::AuditOptions;
+use super::report::Summary;
+use crate::core::{AuditError, AuditObserver, DepOps, DepTreeManager};
+
+/// A usable remediation of a successful fix.
+#[derive(Debug)]
+pub struct Remediation {
+    /// The audit summary, with the rustc version chosen.
+    pub summary: Summary,
+    /// The fixed packages, pinned to their precise versions.
+    pub pins: Vec<(String, Version)>,
+    /// The fixed `Cargo.lock`.
+    pub lockfile: String,
+    /// The `Cargo.toml` with the requirements the pins break edited, `None` if it cannot be edited.
+    pub manifest: Option<String>,
+}
+
+impl Remediation {
+    /// The `rust-toolchain.toml` for the chosen rustc, the pinned nightly if rufs still used.
+    /// Remediations from [`apply_audit`] always have the nightly then.
+    pub fn toolchain_file(&self) -> String {
+        format!(
+            "[toolchain]\nchannel = \"{}\"\nprofile = \"minimal\"\n",
+            self.summary
+                .fix_nightly
+                .as_ref()
+                .unwrap_or(&self.summary.fix_toolchain)
+        )
+    }
+
+    /// Write `Cargo.lock`, `Cargo.toml` and `rust-toolchain.toml` into the dir.
+    /// The dir can be the project itself, and the files will be overwritten.
+    pub fn write_to(&self, dir: impl AsRef<Path>) -> Result<(), AuditError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir).map_err(|e| AuditError::InnerError(e.to_string()))?;
+
+        fs::write(dir.join("Cargo.lock"), &self.lockfile)
+            .map_err(|e| AuditError::InnerError(e.to_string()))?;
+        if let Some(manifest) = &self.manifest {
+            fs::write(dir.join("Cargo.toml"), manifest)
+                .map_err(|e| AuditError::InnerError(e.to_string()))?;
+        }
+        fs::write(dir.join("rust-toolchain.toml"), self.toolchain_file())
+            .map_err(|e| AuditError::InnerError(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+/// Audit and fix, and then give the remediation of the fix. Fixes still using rufs fail if no
+/// nightly can build them. The observer receives the events of the audit.
+pub fn apply_audit(
+    mut ops: impl DepOps,
+    options: &AuditOptions,
+    observer: &mut impl AuditObserver,
+) -> Result<Remediation, AuditError> {
+    options.setup(&mut ops);
+    let rustcs = options.rustc_indexes()?;
+    // Init a tree first, locals are set by the operators.
+    let mut deptree = DepTreeManager::new(ops, rustcs[0])?;
+
+    let summary = check_fix(&mut deptree, options, observer)?;
+    // The stable release cannot build rufs, nor can a nightly not using them all.
+    let uses_rufs = deptree
+        .extract_rufs()?
+        .values()
+        .any(|rufs| !rufs.is_empty());
+    if uses_rufs && summary.fix_nightly.is_none() {
+        return Err(AuditError::FunctionError(
+            Some(format!(
+                "rufs still used on rustc {}, but no nightly of it can use them all",
+                summary.fix_toolchain
+            )),
+            None,
+        ));
+    }
+    let pins = deptree.get_fixed_pkgs();
+
+    Ok(Remediation {
+        summary,
+        pins,
+        lockfile: deptree.get_lockfile()?,
+        manifest: deptree.get_fixed_manifest()?,
+    })
+}
@@ -1,116 +1,247 @@
-use std::io::Write;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
 
 use cargo_lock::dependency::graph::NodeIndex;
-use fxhash::FxHashSet;
+use fxhash::{FxHashMap, FxHashSet};
 use petgraph::visit;
 use semver::Version;
 
-use super::ops::DepOpsVirt;
-use crate::core::{AuditError, DepTreeManager};
+use super::options::AuditOptions;
+use super::report::{self, Decision, FixStep, RustcDecision, Summary};
+use crate::basic::Toolchain;
+use crate::core::{AuditError, AuditEvent, AuditObserver, DepOps, DepTreeManager, VersionChange};
 
 /// The main audit function.
-/// The debugger receives an output stream to write debug information.
+/// The observer receives the events of the audit.
 pub fn audit(
-    name: &str,
-    ver: &str,
-    workspace: &str,
-    debugger: &mut impl Write,
-) -> Result<u32, AuditError> {
-    // Init a tree first
-    let ops = DepOpsVirt::new(name, ver, workspace)?;
-    let mut deptree = DepTreeManager::new(ops, 63)?;
-    let logical_root = deptree
-        .get_graph()
-        .neighbors(deptree.get_root())
-        .next()
-        .unwrap();
-    deptree.set_local(&logical_root);
-
-    check_fix(deptree, debugger)
+    mut ops: impl DepOps,
+    options: &AuditOptions,
+    observer: &mut impl AuditObserver,
+) -> Result<Summary, AuditError> {
+    options.setup(&mut ops);
+    let rustcs = options.rustc_indexes()?;
+    // Init a tree first, locals are set by the operators.
+    let mut deptree = DepTreeManager::new(ops, rustcs[0])?;
+
+    check_fix(&mut deptree, options, observer)
 }
 
-fn check_fix(
-    mut deptree: DepTreeManager<DepOpsVirt>,
-    debugger: &mut impl Write,
-) -> Result<u32, AuditError> {
-    for rustc in (0..64).rev() {
-        deptree.switch_rustv(rustc);
-        writeln!(
-            debugger,
-            "[VirtAudit Debug] check_fix: Checking rustc version {}.",
-            rustc,
-        )
-        .unwrap();
-        let issue_deps = check_issue(&deptree, debugger)?;
-        if issue_deps.is_empty() {
-            writeln!(
-                debugger,
-                "[VirtAudit Debug] check_fix: Rustc version {} has no issues.",
-                rustc
-            )
-            .unwrap();
-            return Ok(rustc);
+/// Outcome of one rustc checked by a thread of [`audit_concurrent`]: the events, the decision,
+/// and the summary if fixed.
+type RustcOutcome = Result<(Vec<AuditEvent>, RustcDecision, Option<Summary>), AuditError>;
+
+/// Same as [`audit`], but `jobs` threads check the rustcs at once, each with its own operators
+/// from `new_ops` and checking every `jobs`th rustc from newest to oldest, so the trees and
+/// candidates met are still shared between the rustcs of a thread. Threads stop at the rustcs
+/// older than one fixed. Sources can be shared between the operators through `Arc`, but the
+/// operators shall not share the files they write, e.g. [`DepOpsVirt`](super::DepOpsVirt) ones
+/// need their own workspaces.
+///
+/// The observer receives the events of the rustcs in order, once the threads are done.
+pub fn audit_concurrent<D: DepOps>(
+    new_ops: impl Fn() -> Result<D, AuditError> + Sync,
+    options: &AuditOptions,
+    jobs: usize,
+    observer: &mut impl AuditObserver,
+) -> Result<Summary, AuditError> {
+    let rustcs = options.rustc_indexes()?;
+    let jobs = jobs.clamp(1, rustcs.len());
+    // The newest rustc fixed so far, by its index in rustcs.
+    let fixed_at = AtomicUsize::new(usize::MAX);
+
+    let mut outcomes = thread::scope(|scope| {
+        let handles = (0..jobs)
+            .map(|job| {
+                let (new_ops, rustcs, fixed_at) = (&new_ops, &rustcs, &fixed_at);
+                scope.spawn(move || {
+                    let mut outcomes: Vec<(usize, RustcOutcome)> = Vec::new();
+                    let mut deptree = match new_ops().and_then(|mut ops| {
+                        options.setup(&mut ops);
+                        DepTreeManager::new(ops, rustcs[job])
+                    }) {
+                        Ok(deptree) => deptree,
+                        Err(e) => {
+                            outcomes.push((job, Err(e)));
+                            return outcomes;
+                        }
+                    };
+                    deptree.set_objective(options.objective);
+                    deptree.set_yank_policy(options.yank_policy);
+
+                    for i in (job..rustcs.len()).step_by(jobs) {
+                        if fixed_at.load(Ordering::Relaxed) < i {
+                            break;
+                        }
+
+                        let mut events = Vec::new();
+                        let outcome = fix_rustc(&mut deptree, rustcs[i], &mut events).and_then(
+                            |(decision, fixed)| {
+                                let Some(fix_deps) = fixed else {
+                                    return Ok((events, decision, None));
+                                };
+                                fixed_at.fetch_min(i, Ordering::Relaxed);
+                                let summary = report::summarize(
+                                    &deptree,
+                                    options,
+                                    rustcs[i] as i32,
+                                    fix_deps,
+                                    Vec::new(),
+                                )?;
+                                Ok((events, decision, Some(summary)))
+                            },
+                        );
+                        let done = !matches!(outcome, Ok((_, _, None)));
+                        outcomes.push((i, outcome));
+                        if done {
+                            break;
+                        }
+                    }
+
+                    outcomes
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect::<Vec<_>>()
+    });
+    outcomes.sort_by_key(|(i, _)| *i);
+
+    // All rustcs newer than the first fixed or failed one are checked, by some thread.
+    let mut trail = Vec::new();
+    for (_, outcome) in outcomes {
+        let (events, decision, summary) = outcome?;
+        for event in events {
+            observer.on_event(event);
+        }
+        trail.push(decision);
+        if let Some(mut summary) = summary {
+            summary.trail = trail;
+            return Ok(summary);
         }
+    }
 
-        let first_issue = issue_deps.first().cloned().unwrap();
-        match check_fixable(&mut deptree, issue_deps, debugger) {
-            Ok(mut fixes) => {
-                if let Err(e) = try_fix(&mut deptree, first_issue, fixes.remove(0), debugger) {
-                    writeln!(debugger,
-                    "[VirtAudit Debug] check_fix: Fix failure for rustc version {} with issue: {:?}", rustc, e).unwrap();
-                } else {
-                    writeln!(
-                        debugger,
-                        "[VirtAudit Debug] check_fix: Rustc version {} issues fixed.",
-                        rustc,
-                    )
-                    .unwrap();
-                    return Ok(rustc);
-                }
+    Err(AuditError::NoFix(
+        trail
+            .iter()
+            .filter_map(|decision| decision.failure())
+            .collect(),
+    ))
+}
+
+/// Search the newest fixable rustc in options, the deptree is left fixed when succeed.
+pub(super) fn check_fix(
+    deptree: &mut DepTreeManager<impl DepOps>,
+    options: &AuditOptions,
+    observer: &mut impl AuditObserver,
+) -> Result<Summary, AuditError> {
+    let rustcs = options.rustc_indexes()?;
+    deptree.set_objective(options.objective);
+    deptree.set_yank_policy(options.yank_policy);
+
+    let mut trail = Vec::new();
+    for rustc in rustcs {
+        let (decision, fixed) = fix_rustc(deptree, rustc, observer)?;
+        trail.push(decision);
+        if let Some(fix_deps) = fixed {
+            return report::summarize(deptree, options, rustc as i32, fix_deps, trail);
+        }
+    }
+
+    Err(AuditError::NoFix(
+        trail
+            .iter()
+            .filter_map(|decision| decision.failure())
+            .collect(),
+    ))
+}
+
+/// Fix steps of each fixed package.
+type FixDeps = FxHashMap<String, Vec<FixStep>>;
+
+/// Check and fix the issues on the rustc, the deptree is left fixed when succeed.
+/// Returns the decision, along with the fixes if fixed.
+pub(super) fn fix_rustc(
+    deptree: &mut DepTreeManager<impl DepOps>,
+    rustc: u32,
+    observer: &mut impl AuditObserver,
+) -> Result<(RustcDecision, Option<FixDeps>), AuditError> {
+    deptree.switch_rustv(rustc);
+    observer.on_event(AuditEvent::CheckRustc {
+        rustv: rustc,
+        toolchain: Toolchain::from_index(rustc).to_string(),
+    });
+    let issue_deps = check_issue(deptree, observer)?;
+    let mut decision = RustcDecision::new(deptree, rustc, &issue_deps)?;
+    if issue_deps.is_empty() {
+        observer.on_event(AuditEvent::RustcNoIssue { rustv: rustc });
+        return Ok((decision, Some(FxHashMap::default())));
+    }
+
+    let first_issue = issue_deps.first().cloned().unwrap();
+    match check_fixable(deptree, issue_deps, observer) {
+        Ok(mut fixes) => match try_fix(deptree, first_issue, fixes.remove(0), observer) {
+            Ok(fix_deps) => {
+                observer.on_event(AuditEvent::RustcFixed { rustv: rustc });
+                return Ok((decision, Some(fix_deps)));
             }
             Err(e) => {
-                if !e.is_inner() {
-                    writeln!(debugger,
-                    "[VirtAudit Debug] check_fix: Rustc version {} got issues cannot be fixed with error: {:?}.",
-                    rustc, e).unwrap();
-                } else {
-                    return Err(e);
-                }
+                observer.on_event(AuditEvent::RustcFailed {
+                    rustv: rustc,
+                    error: e.describe(),
+                });
+                decision.fail(Decision::FixFailed, &e, deptree.get_graph());
+            }
+        },
+        Err(e) => {
+            if !e.is_inner() {
+                observer.on_event(AuditEvent::RustcFailed {
+                    rustv: rustc,
+                    error: e.describe(),
+                });
+                decision.fail(Decision::Unfixable, &e, deptree.get_graph());
+            } else {
+                return Err(e);
             }
         }
     }
 
-    Err(AuditError::FunctionError(None, None))
+    Ok((decision, None))
 }
 
-fn check_issue(
-    deptree: &DepTreeManager<DepOpsVirt>,
-    debugger: &mut impl Write,
+pub(super) fn check_issue(
+    deptree: &DepTreeManager<impl DepOps>,
+    observer: &mut impl AuditObserver,
 ) -> Result<Vec<NodeIndex>, AuditError> {
     // Extract current used rufs.
     let used_rufs = deptree.extract_rufs()?;
 
     // We do bfs and thus fix problems up to down.
     let graph = deptree.get_graph();
-    // In virt audit, real root is the child of `root`.
-    let root = graph.neighbors(deptree.get_root()).next().unwrap();
+    // Start from all roots: the virt package, or the workspace members.
+    let roots = deptree.get_roots();
 
     // Collect all ruf issues first.
     let mut issue_deps = Vec::new();
-    let mut bfs = visit::Bfs::new(&graph, root);
+    let mut bfs = visit::Bfs::new(&graph, roots[0]);
+    for &root in roots.iter().skip(1) {
+        bfs.discovered.insert(root.index());
+        bfs.stack.push_back(root);
+    }
     while let Some(nx) = bfs.next(&graph) {
         let node = &graph[nx];
         let name_ver = format!("{}@{}", node.name, node.version);
         if let Some(rufs) = used_rufs.get(&name_ver) {
-            let issue_rufs = deptree.filter_rufs(rufs.iter().collect());
+            let issue_rufs = deptree.filter_rufs(node.name.as_str(), rufs.iter().collect());
             if !issue_rufs.is_empty() {
                 // Ok here we got issues
-                writeln!(
-                    debugger,
-                    "[VirtAudit Debug] check_issue: Found issue package {}@{} rufs: {:?}",
-                    node.name, node.version, issue_rufs
-                )
-                .unwrap();
+                observer.on_event(AuditEvent::IssueFound {
+                    package: name_ver.clone(),
+                    rufs: issue_rufs.into_iter().cloned().collect(),
+                });
                 issue_deps.push(nx);
             }
         }
@@ -119,23 +250,17 @@ fn check_issue(
     Ok(issue_deps)
 }
 
-fn check_fixable(
-    deptree: &mut DepTreeManager<DepOpsVirt>,
+pub(super) fn check_fixable(
+    deptree: &mut DepTreeManager<impl DepOps>,
     issue_deps: Vec<NodeIndex>,
-    debugger: &mut impl Write,
+    observer: &mut impl AuditObserver,
 ) -> Result<Vec<Vec<(String, Version, Version)>>, AuditError> {
     let graph = deptree.get_graph();
     let mut fixes = Vec::new();
 
     // Check possible fix for each issue.
     for nx in issue_deps {
-        writeln!(
-            debugger,
-            "[VirtAudit Debug] check_fixable: Check {}@{} fixibility",
-            graph[nx].name, graph[nx].version,
-        )
-        .unwrap();
-        match deptree.issue_fixable(nx, debugger) {
+        match deptree.issue_fixable(nx, observer) {
             Ok(fix) => {
                 let fix = fix
                     .into_iter()
@@ -148,16 +273,17 @@ fn check_fixable(
                     })
                     .collect::<Vec<_>>();
 
-                writeln!(
-                    debugger,
-                    "[VirtAudit Debug] check_fixable: Issue dep {}@{} is fixable with {:?}, adds to fix limits.",
-                    graph[nx].name,
-                    graph[nx].version,
-                    fix.iter()
-                        .map(|(name, ver, fix_ver)| format!("{}@{} -> {}", name, ver, fix_ver))
-                        .collect::<Vec<_>>()
-                )
-                .unwrap();
+                observer.on_event(AuditEvent::Fixable {
+                    package: format!("{}@{}", graph[nx].name, graph[nx].version),
+                    changes: fix
+                        .iter()
+                        .map(|(name, ver, fix_ver)| VersionChange {
+                            name: name.clone(),
+                            from: ver.clone(),
+                            to: fix_ver.clone(),
+                        })
+                        .collect(),
+                });
 
                 // Add limits for the fix.
                 deptree.set_fix_limit(&fix);
@@ -165,12 +291,10 @@ fn check_fixable(
                 fixes.push(fix);
             }
             Err(e) => {
-                writeln!(
-                    debugger,
-                    "[VirtAudit Debug] check_fixable: Issue dep {}@{} is not fixable with error {:?}.",
-                    graph[nx].name, graph[nx].version, e
-                )
-                .unwrap();
+                observer.on_event(AuditEvent::Unfixable {
+                    package: format!("{}@{}", graph[nx].name, graph[nx].version),
+                    error: e.describe(),
+                });
                 return Err(e);
             }
         }
@@ -182,15 +306,16 @@ fn check_fixable(
     Ok(fixes)
 }
 
-fn try_fix(
-    deptree: &mut DepTreeManager<DepOpsVirt>,
+pub(super) fn try_fix(
+    deptree: &mut DepTreeManager<impl DepOps>,
     first_issue: NodeIndex,
     first_fix: Vec<(String, Version, Version)>,
-    debugger: &mut impl Write,
-) -> Result<(), AuditError> {
+    observer: &mut impl AuditObserver,
+) -> Result<FixDeps, AuditError> {
     // For loop detect.
     let mut already_fixed = FxHashSet::default();
     let mut is_first = Some((first_issue, first_fix));
+    let mut fix_deps = FxHashMap::default();
 
     // The fix modify the deptree, and thus the remaining issues and their fixability may changes.
     // So here we have to recheck the issues and fix them.
@@ -200,14 +325,14 @@ fn try_fix(
         let (issue_nx, fix) = if is_first.is_some() {
             is_first.take().unwrap()
         } else {
-            let issue_nx = check_issue(deptree, debugger)?.first().cloned();
+            let issue_nx = check_issue(deptree, observer)?.first().cloned();
             if issue_nx.is_none() {
-                return Ok(());
+                return Ok(fix_deps);
             }
 
             let issue_nx = issue_nx.unwrap();
 
-            let fix = deptree.issue_fixable(issue_nx, debugger)?;
+            let fix = deptree.issue_fixable(issue_nx, observer)?;
             assert!(!fix.is_empty(), "Fatal, no fix found when fixing issue.");
 
             let fix = fix
@@ -224,19 +349,20 @@ fn try_fix(
         };
 
         let issue_name_ver = format!("{}@{}", graph[issue_nx].name, graph[issue_nx].version);
-        writeln!(
-            debugger,
-            "[VirtAudit Debug] try_fix: Try fix {} with {:?}",
-            graph[issue_nx].name,
-            fix.iter()
-                .map(|(name, ver, fix_ver)| format!("{}@{} -> {}", name, ver, fix_ver))
-                .collect::<Vec<_>>()
-        )
-        .unwrap();
 
         // Set the limit first.
         deptree.set_fix_limit(&fix);
-        deptree.issue_dofix(issue_nx, fix, debugger)?;
+        let steps = deptree.issue_dofix(issue_nx, fix, observer)?;
+
+        fix_deps
+            .entry(issue_name_ver.clone())
+            .or_insert_with(Vec::new)
+            .extend(steps.into_iter().map(|(name, from, to, rufs)| FixStep {
+                name,
+                from,
+                to,
+                rufs,
+            }));
 
         let check_loop = already_fixed.insert(issue_name_ver);
 
@@ -250,31 +376,37 @@ fn try_fix(
 }
 
 #[test]
+#[ignore = "needs the ruf database in PostgreSQL and a local audit workspace"]
 fn test_audit() {
+    use super::ops::DepOpsVirt;
+    use crate::core::TextObserver;
     use std::sync::{Arc, Mutex};
 
     const WORKSPACE_PATH: &str = "/home/ubuntu/Workspaces/Cargo-Ecosystem-Monitor/Code/cargo_ruf/ruf_audit_virtual/virt_work";
     let stdout = Arc::new(Mutex::new(std::io::stdout()));
     let mut buffer = stdout.lock().unwrap();
+    let options = AuditOptions::default();
+    let mut observer = TextObserver(&mut *buffer);
 
-    // let res = audit("taxonomy", "0.3.1", WORKSPACE_PATH, &mut *buffer);
-    // let res = audit("pyo3", "0.9.2", WORKSPACE_PATH, &mut *buffer);
+    // let res = audit(DepOpsVirt::new("taxonomy", "0.3.1", WORKSPACE_PATH).unwrap(), &options, &mut observer);
+    // let res = audit(DepOpsVirt::new("pyo3", "0.9.2", WORKSPACE_PATH).unwrap(), &options, &mut observer);
 
     // let res = audit(
-    //     "rustc-ap-rustc_errors",
-    //     "12.0.0",
-    //     WORKSPACE_PATH,
-    //     &mut *buffer,
+    //     DepOpsVirt::new("rustc-ap-rustc_errors", "12.0.0", WORKSPACE_PATH).unwrap(),
+    //     &options,
+    //     &mut observer,
     // );
 
-    // let res = audit("tar", "0.4.0", WORKSPACE_PATH, &mut *buffer);
-    // let res = audit("chrono-tz", "0.1.0", WORKSPACE_PATH, &mut *buffer);
-    let res = audit("leaf", "0.0.1", WORKSPACE_PATH, &mut *buffer);
-
-    // let res = audit("kunai", "0.3.0", WORKSPACE_PATH, &mut *buffer);
-    // let res = audit("hsr-codegen", "0.2.0", WORKSPACE_PATH, &mut *buffer);
-    // let res = audit("bouncer", "1.0.0", WORKSPACE_PATH, &mut *buffer);
-    // let res = audit("tari_comms_dht", "0.8.1", WORKSPACE_PATH, &mut *buffer);
-
-    println!("RESULTS: {:?}", res);
+    // let res = audit(DepOpsVirt::new("tar", "0.4.0", WORKSPACE_PATH).unwrap(), &options, &mut observer);
+    // let res = audit(DepOpsVirt::new("chrono-tz", "0.1.0", WORKSPACE_PATH).unwrap(), &options, &mut observer);
+    let _ = audit(
+        DepOpsVirt::new("leaf", "0.0.1", WORKSPACE_PATH).unwrap(),
+        &options,
+        &mut observer,
+    );
+
+    // let res = audit(DepOpsVirt::new("kunai", "0.3.0", WORKSPACE_PATH).unwrap(), &options, &mut observer);
+    // let res = audit(DepOpsVirt::new("hsr-codegen", "0.2.0", WORKSPACE_PATH).unwrap(), &options, &mut observer);
+    // let res = audit(DepOpsVirt::new("bouncer", "1.0.0", WORKSPACE_PATH).unwrap(), &options, &mut observer);
+    // let res = audit(DepOpsVirt::new("tari_comms_dht", "0.8.1", WORKSPACE_PATH).unwrap(), &options, &mut observer);
 }
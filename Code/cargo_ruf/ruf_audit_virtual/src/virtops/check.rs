@@ -0,0 +1,29 @@
+use super::audit::check_issue;
+use super::options::AuditOptions;
+use super::report::{IssueRecord, RustcDecision};
+use crate::basic::Toolchain;
+use crate::core::{AuditError, AuditEvent, AuditObserver, DepOps, DepTreeManager};
+
+/// Check the ruf issues of the current tree on the newest rustc in options, without fixing them.
+/// The observer receives the events of the audit.
+pub fn check_audit(
+    mut ops: impl DepOps,
+    options: &AuditOptions,
+    observer: &mut impl AuditObserver,
+) -> Result<Vec<IssueRecord>, AuditError> {
+    options.setup(&mut ops);
+    let rustc = options.rustc_indexes()?[0];
+    // Init a tree first, locals are set by the operators.
+    let deptree = DepTreeManager::new(ops, rustc)?;
+
+    observer.on_event(AuditEvent::CheckRustc {
+        rustv: rustc,
+        toolchain: Toolchain::from_index(rustc).to_string(),
+    });
+    let issue_deps = check_issue(&deptree, observer)?;
+    if issue_deps.is_empty() {
+        observer.on_event(AuditEvent::RustcNoIssue { rustv: rustc });
+    }
+
+    Ok(RustcDecision::new(&deptree, rustc, &issue_deps)?.issues)
+}
@@ -0,0 +1,214 @@
+use fxhash::{FxHashMap, FxHashSet};
+use semver::Version;
+use serde::Serialize;
+
+use super::options::AuditOptions;
+use crate::basic::{self, Channel, Toolchain};
+use crate::core::{AuditError, AuditEvent, AuditObserver, DepOps, DepTreeManager};
+
+/// A ruf newly used, along with its status on the rustc checked.
+#[derive(Debug, Clone, Serialize)]
+pub struct RufUsage {
+    pub ruf: String,
+    /// Status in the lifetime table, empty for unknown rufs.
+    pub status: String,
+    /// Whether it is usable, by the compiler (never on stable ones) and the policy.
+    pub usable: bool,
+}
+
+/// Rufs used by a package of the new lockfile, but by none of its versions in the old one.
+#[derive(Debug, Clone, Serialize)]
+pub struct IntroducedRufs {
+    pub package: String,
+    /// Versions of the package in the old lockfile, empty if newly added.
+    pub previous: Vec<Version>,
+    pub rufs: Vec<RufUsage>,
+    /// The audited packages depending on it.
+    pub roots: Vec<String>,
+}
+
+/// Rufs introduced by updating the lockfile, like `cargo update` does.
+#[derive(Debug, Clone, Serialize)]
+pub struct LockDiff {
+    /// The rustc checked, and the compiler channel simulated.
+    pub toolchain: String,
+    pub channel: Channel,
+    pub introduced: Vec<IntroducedRufs>,
+}
+
+impl LockDiff {
+    /// The introduced rufs not usable, which the update shall not bring.
+    pub fn violations(&self) -> Vec<(&str, &RufUsage)> {
+        self.introduced
+            .iter()
+            .flat_map(|pkg| {
+                pkg.rufs
+                    .iter()
+                    .filter(|usage| !usage.usable)
+                    .map(|usage| (pkg.package.as_str(), usage))
+            })
+            .collect()
+    }
+}
+
+/// Rufs and versions of each package in the old resolve, by name.
+struct OldResolve<'a> {
+    rufs: FxHashMap<String, FxHashSet<String>>,
+    versions: FxHashMap<&'a str, Vec<Version>>,
+}
+
+impl<'a> OldResolve<'a> {
+    /// From the rufs used by each `name@version`, and all packages in the resolve.
+    fn new(
+        used_rufs: FxHashMap<String, Vec<String>>,
+        packages: impl Iterator<Item = (&'a str, &'a Version)>,
+    ) -> Self {
+        let mut rufs: FxHashMap<String, FxHashSet<String>> = FxHashMap::default();
+        for (name_ver, pkg_rufs) in used_rufs {
+            let (name, _) = name_ver.split_once('@').unwrap();
+            rufs.entry(name.to_string()).or_default().extend(pkg_rufs);
+        }
+
+        let mut versions: FxHashMap<&str, Vec<Version>> = FxHashMap::default();
+        for (name, version) in packages {
+            versions.entry(name).or_default().push(version.clone());
+        }
+        versions.values_mut().for_each(|versions| versions.sort());
+
+        Self { rufs, versions }
+    }
+
+    /// Rufs of the package used by none of its old versions.
+    fn introduced<'r>(&self, name: &str, rufs: &'r [String]) -> Vec<&'r String> {
+        let before = self.rufs.get(name);
+        rufs.iter()
+            .filter(|ruf| before.map_or(true, |before| !before.contains(*ruf)))
+            .collect()
+    }
+
+    /// Old versions of the package, empty if newly added.
+    fn previous(&self, name: &str) -> Vec<Version> {
+        self.versions.get(name).cloned().unwrap_or_default()
+    }
+}
+
+/// Compare the rufs used by two resolves of a project, usually from the lockfiles before and after
+/// an update (see [`DepOpsLocal::set_lockfile`](super::DepOpsLocal::set_lockfile)), on the newest
+/// rustc in options. A ruf is introduced if the package uses it, while no version of the package
+/// in the old resolve does, so bumps keeping their rufs are not reported again.
+pub fn diff_audit(
+    mut old_ops: impl DepOps,
+    mut new_ops: impl DepOps,
+    options: &AuditOptions,
+    observer: &mut impl AuditObserver,
+) -> Result<LockDiff, AuditError> {
+    options.setup(&mut old_ops);
+    options.setup(&mut new_ops);
+    let rustv = options.rustc_indexes()?[0];
+
+    // Rufs and versions of each package in the old resolve.
+    let old_tree = DepTreeManager::new(old_ops, rustv)?;
+    let old_graph = old_tree.get_graph();
+    let old = OldResolve::new(
+        old_tree.extract_rufs()?,
+        old_graph
+            .node_indices()
+            .map(|nx| (old_graph[nx].name.as_str(), &old_graph[nx].version)),
+    );
+
+    let new_tree = DepTreeManager::new(new_ops, rustv)?;
+    observer.on_event(AuditEvent::CheckRustc {
+        rustv,
+        toolchain: Toolchain::from_index(rustv).to_string(),
+    });
+
+    let used_rufs = new_tree.extract_rufs()?;
+    let graph = new_tree.get_graph();
+    let mut introduced = Vec::new();
+    for nx in graph.node_indices() {
+        let node = &graph[nx];
+        let package = format!("{}@{}", node.name, node.version);
+        let Some(rufs) = used_rufs.get(&package) else {
+            continue;
+        };
+
+        let rufs = old.introduced(node.name.as_str(), rufs);
+        if rufs.is_empty() {
+            continue;
+        }
+
+        let unusable = new_tree.filter_rufs(node.name.as_str(), rufs.clone());
+        if !unusable.is_empty() {
+            observer.on_event(AuditEvent::IssueFound {
+                package: package.clone(),
+                rufs: unusable.iter().map(|ruf| ruf.to_string()).collect(),
+            });
+        }
+
+        introduced.push(IntroducedRufs {
+            package,
+            previous: old.previous(node.name.as_str()),
+            rufs: rufs
+                .into_iter()
+                .map(|ruf| RufUsage {
+                    ruf: ruf.clone(),
+                    status: basic::get_ruf_status(ruf, rustv).as_str().to_string(),
+                    usable: !unusable.contains(&ruf),
+                })
+                .collect(),
+            roots: new_tree.get_dependent_audited(nx),
+        });
+    }
+    introduced.sort_by(|a, b| a.package.cmp(&b.package));
+
+    let diff = LockDiff {
+        toolchain: Toolchain::from_index(rustv).to_string(),
+        channel: new_tree.get_cfg().channel(),
+        introduced,
+    };
+    if diff.violations().is_empty() {
+        observer.on_event(AuditEvent::RustcNoIssue { rustv });
+    }
+
+    Ok(diff)
+}
+
+#[test]
+fn test_old_resolve() {
+    let v = |s: &str| Version::parse(s).unwrap();
+    let rufs = |rufs: &[&str]| rufs.iter().map(|ruf| ruf.to_string()).collect::<Vec<_>>();
+
+    let versions = [v("1.0.0"), v("0.9.0"), v("2.1.0"), v("0.3.0")];
+    let packages = [
+        ("foo", &versions[0]),
+        ("foo", &versions[1]),
+        ("bar", &versions[2]),
+        ("baz", &versions[3]),
+    ];
+    let used_rufs = [
+        ("foo@1.0.0".to_string(), rufs(&["doc_cfg"])),
+        ("foo@0.9.0".to_string(), rufs(&["never_type"])),
+        ("bar@2.1.0".to_string(), rufs(&["specialization"])),
+    ];
+    let old = OldResolve::new(used_rufs.into_iter().collect(), packages.into_iter());
+
+    // Bumps keep the rufs of any old version, and introduce the others.
+    let bumped = rufs(&["doc_cfg", "never_type", "let_chains"]);
+    assert_eq!(old.introduced("foo", &bumped), vec!["let_chains"]);
+    assert_eq!(
+        old.introduced("bar", &rufs(&["specialization"])),
+        Vec::<&String>::new()
+    );
+    // Packages using no ruf before introduce all.
+    assert_eq!(old.introduced("baz", &rufs(&["doc_cfg"])), vec!["doc_cfg"]);
+
+    // Newly added packages introduce all, with no previous versions.
+    assert_eq!(
+        old.introduced("qux", &bumped),
+        bumped.iter().collect::<Vec<_>>()
+    );
+    assert_eq!(old.previous("qux"), Vec::<Version>::new());
+
+    assert_eq!(old.previous("foo"), vec![v("0.9.0"), v("1.0.0")]);
+    assert_eq!(old.previous("baz"), vec![v("0.3.0")]);
+}
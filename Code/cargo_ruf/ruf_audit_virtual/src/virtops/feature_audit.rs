@@ -0,0 +1,209 @@
+use std::fmt::Display;
+
+use cargo::core::Resolve;
+use fxhash::FxHashSet;
+use semver::Version;
+use serde::Serialize;
+
+use super::options::AuditOptions;
+use super::report::IssueRecord;
+use super::resolve;
+use crate::basic::{Channel, FeatureSelection, Toolchain};
+use crate::core::{AuditError, AuditEvent, AuditObserver, DepOps};
+
+/// Features of the audited packages to disable, so that no ruf issues are left.
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureReport {
+    /// The rustc checked, and the compiler channel simulated.
+    pub toolchain: String,
+    pub channel: Channel,
+    /// Features kept enabled.
+    pub features: Vec<String>,
+    /// Features to disable, in the order they are found.
+    pub disabled: Vec<String>,
+    /// Issues left after disabling them, empty if it builds.
+    pub issues: Vec<IssueRecord>,
+}
+
+impl FeatureReport {
+    pub fn builds(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// The selection to audit with, see [`AuditOptions::features`].
+    pub fn selection(&self) -> FeatureSelection {
+        FeatureSelection::List(self.features.clone())
+    }
+}
+
+impl Display for FeatureReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let compiler = match self.channel {
+            Channel::Stable => "stable".to_string(),
+            Channel::Nightly => format!("rustc {}", self.toolchain),
+        };
+        let disabled = self
+            .disabled
+            .iter()
+            .map(|feature| format!("`{feature}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match (self.builds(), self.disabled.is_empty()) {
+            (true, true) => write!(f, "builds on {compiler}"),
+            (true, false) => write!(f, "builds on {compiler} if you disable feature {disabled}"),
+            (false, true) => write!(
+                f,
+                "{} packages use broken rufs on {compiler} whatever features disabled",
+                self.issues.len()
+            ),
+            (false, false) => write!(
+                f,
+                "{} packages still use broken rufs on {compiler} after disabling feature {disabled}",
+                self.issues.len()
+            ),
+        }
+    }
+}
+
+/// Search the features to disable so that no ruf issues are left on the newest rustc in options,
+/// without changing any dependencies. Features are disabled one by one, each time the one
+/// leaving the fewest issue packages, until none is left or disabling more does not help.
+pub fn feature_audit(
+    mut ops: impl DepOps,
+    options: &AuditOptions,
+    observer: &mut impl AuditObserver,
+) -> Result<FeatureReport, AuditError> {
+    options.setup(&mut ops);
+    let rustv = options.rustc_indexes()?[0];
+
+    let (resolve, _) = ops.first_resolve()?;
+    let declared = ops.get_root_features(&resolve)?;
+    let mut features = match &options.features {
+        FeatureSelection::Default if declared.iter().any(|f| f == "default") => {
+            vec!["default".to_string()]
+        }
+        selection => selection.features(&declared),
+    };
+    let mut issues = check_issue(&ops, &resolve, rustv)?;
+
+    let mut disabled = Vec::new();
+    while !issues.is_empty() {
+        let mut best: Option<(&String, Vec<IssueRecord>)> = None;
+        for feature in features.iter() {
+            let trial = features
+                .iter()
+                .filter(|f| *f != feature)
+                .cloned()
+                .collect::<Vec<_>>();
+            ops.set_features(FeatureSelection::List(trial));
+            let trial_issues = match ops.first_resolve() {
+                Ok((resolve, _)) => check_issue(&ops, &resolve, rustv)?,
+                Err(e) => {
+                    observer.on_event(AuditEvent::FeatureResolveFailed {
+                        feature: feature.clone(),
+                        error: e.describe(),
+                    });
+                    continue;
+                }
+            };
+
+            let fewest = best.as_ref().map_or(issues.len(), |(_, i)| i.len());
+            if trial_issues.len() < fewest {
+                best = Some((feature, trial_issues));
+            }
+        }
+
+        let Some((feature, trial_issues)) = best else {
+            observer.on_event(AuditEvent::FeatureSearchStuck {
+                issues: issues.len(),
+            });
+            break;
+        };
+        observer.on_event(AuditEvent::FeatureDisabled {
+            feature: feature.clone(),
+            issues_before: issues.len(),
+            issues_after: trial_issues.len(),
+        });
+
+        let feature = feature.clone();
+        features.retain(|f| *f != feature);
+        disabled.push(feature);
+        issues = trial_issues;
+    }
+
+    Ok(FeatureReport {
+        toolchain: Toolchain::from_index(rustv).to_string(),
+        channel: ops.get_cfg().channel(),
+        features,
+        disabled,
+        issues,
+    })
+}
+
+/// The packages using rufs not usable on the rustc, in the resolve.
+fn check_issue(
+    ops: &impl DepOps,
+    resolve: &Resolve,
+    rustv: u32,
+) -> Result<Vec<IssueRecord>, AuditError> {
+    let used_rufs = ops.extract_rufs(resolve)?;
+
+    // The packages each audited package depends on, for the target.
+    let target_pkgs = resolve::target_pkgs(resolve, ops.get_cfg());
+    let audited = ops
+        .get_audited()
+        .into_iter()
+        .filter_map(|(name, ver)| {
+            let root = resolve.query(&format!("{}@{}", name, ver)).ok()?;
+            let mut deps = FxHashSet::default();
+            let mut stack = vec![root];
+            while let Some(pkg_id) = stack.pop() {
+                if deps.insert(format!("{}@{}", pkg_id.name(), pkg_id.version())) {
+                    stack.extend(target_pkgs.get(&pkg_id).into_iter().flatten().copied());
+                }
+            }
+            Some((format!("{}@{}", name, ver), deps))
+        })
+        .collect::<Vec<_>>();
+
+    let mut issues = Vec::new();
+    for (package, rufs) in used_rufs.iter() {
+        let (name, ver) = package.rsplit_once('@').unwrap();
+        let rufs = ops
+            .filter_rufs(rustv, name, rufs.iter().collect())
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        if rufs.is_empty() {
+            continue;
+        }
+
+        let ver = Version::parse(ver).map_err(|e| AuditError::InnerError(e.to_string()))?;
+        let probed = ops
+            .get_all_candidates(name)?
+            .get(&ver)
+            .map(|condrufs| condrufs.probed(ops.get_cfg()))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|ruf| rufs.contains(*ruf))
+            .cloned()
+            .collect();
+
+        let roots = audited
+            .iter()
+            .filter(|(_, deps)| deps.contains(package))
+            .map(|(root, _)| root.clone())
+            .collect();
+
+        issues.push(IssueRecord {
+            package: package.clone(),
+            rufs,
+            probed,
+            roots,
+        });
+    }
+    issues.sort_by(|a, b| a.package.cmp(&b.package));
+
+    Ok(issues)
+}
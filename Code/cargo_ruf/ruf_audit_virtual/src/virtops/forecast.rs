@@ -0,0 +1,93 @@
+use serde::Serialize;
+
+use super::options::AuditOptions;
+use crate::basic::{self, Channel, RufForecast, Toolchain};
+use crate::core::{AuditError, AuditEvent, AuditObserver, DepOps, DepTreeManager};
+
+/// Forecasts of the rufs used by a package.
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageForecast {
+    pub package: String,
+    /// Chance of some ruf breaking in the next release, taking the rufs as independent.
+    pub risk: f64,
+    /// Riskiest first.
+    pub rufs: Vec<RufForecast>,
+    /// The audited packages depending on it.
+    pub roots: Vec<String>,
+}
+
+/// Rufs of the tree breaking in the release after the rustc checked, see
+/// [`ruf_lifetime::forecast_rufs`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ForecastReport {
+    /// The rustc checked, and the compiler channel simulated.
+    pub toolchain: String,
+    pub channel: Channel,
+    /// Riskiest first, packages using no rufs are left out.
+    pub packages: Vec<PackageForecast>,
+}
+
+/// Forecast the ruf usages of the current tree breaking on the toolchain after the newest rustc
+/// in options, from the lifetime history up to it. Rufs already unusable, by the compiler or the
+/// policy, are at risk 1.
+pub fn forecast_audit(
+    mut ops: impl DepOps,
+    options: &AuditOptions,
+    observer: &mut impl AuditObserver,
+) -> Result<ForecastReport, AuditError> {
+    options.setup(&mut ops);
+    let rustv = options.rustc_indexes()?[0];
+    // Init a tree first, locals are set by the operators.
+    let deptree = DepTreeManager::new(ops, rustv)?;
+
+    observer.on_event(AuditEvent::CheckRustc {
+        rustv,
+        toolchain: Toolchain::from_index(rustv).to_string(),
+    });
+
+    let used_rufs = deptree.extract_rufs()?;
+    let graph = deptree.get_graph();
+    let mut packages = Vec::new();
+    for nx in graph.node_indices() {
+        let node = &graph[nx];
+        let package = format!("{}@{}", node.name, node.version);
+        let Some(rufs) = used_rufs.get(&package) else {
+            continue;
+        };
+
+        let unusable = deptree.filter_rufs(node.name.as_str(), rufs.iter().collect());
+        if !unusable.is_empty() {
+            observer.on_event(AuditEvent::IssueFound {
+                package: package.clone(),
+                rufs: unusable.iter().map(|ruf| ruf.to_string()).collect(),
+            });
+        }
+
+        let names = rufs.iter().map(|ruf| ruf.as_str()).collect::<Vec<_>>();
+        let mut forecasts = basic::forecast_rufs(&names, rustv);
+        for forecast in forecasts.iter_mut() {
+            if unusable.contains(&&forecast.ruf) {
+                forecast.risk = 1.0;
+            }
+        }
+        forecasts.sort_by(|a, b| b.risk.total_cmp(&a.risk).then_with(|| a.ruf.cmp(&b.ruf)));
+
+        packages.push(PackageForecast {
+            package,
+            risk: 1.0 - forecasts.iter().map(|f| 1.0 - f.risk).product::<f64>(),
+            rufs: forecasts,
+            roots: deptree.get_dependent_audited(nx),
+        });
+    }
+    packages.sort_by(|a, b| {
+        b.risk
+            .total_cmp(&a.risk)
+            .then_with(|| a.package.cmp(&b.package))
+    });
+
+    Ok(ForecastReport {
+        toolchain: Toolchain::from_index(rustv).to_string(),
+        channel: deptree.get_cfg().channel(),
+        packages,
+    })
+}
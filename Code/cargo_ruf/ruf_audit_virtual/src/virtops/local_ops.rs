@@ -0,0 +1,343 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::PathBuf;
+
+use cargo::core::dependency::DepKind;
+use cargo::core::{Resolve, Workspace};
+use cargo::ops;
+
+use cargo_lock::dependency::Tree;
+use fxhash::{FxHashMap, FxHashSet};
+use semver::{Version, VersionReq};
+
+use crate::basic::{CfgProfile, CondRufs, FeatureSelection, RufPolicy, Timestamp};
+use crate::core::AuditError;
+use crate::core::DepOps;
+
+use super::meta_ops::MetaOps;
+use super::source::{IndexSource, MetaSource, PgSource, DEFAULT_DB};
+use super::{manifest, resolve};
+
+/// Version and declared version reqs of each local crate.
+type Locals = FxHashMap<String, (Version, FxHashMap<String, VersionReq>)>;
+
+/// Audit an existing cargo project, crate metas of registry packages still come from the [`MetaSource`].
+///
+/// All non-registry packages (workspace members, path and git dependencies) are locals,
+/// which are pinned and never fixed. Their own ruf usages are not known, as they are not in our databases.
+pub struct DepOpsLocal<S: MetaSource = PgSource> {
+    /// Crate metas of the registry packages.
+    meta: MetaOps<S>,
+
+    /// For the local project.
+    project_path: PathBuf,
+    toml_path: PathBuf,
+    home_path: PathBuf,
+    /// Resolve from this lockfile instead of the project's own, if set.
+    lockfile: Option<PathBuf>,
+
+    /// The local crates, collected at first resolve.
+    locals: RefCell<Locals>,
+    /// The workspace members, collected at first resolve.
+    members: RefCell<Vec<(String, Version)>>,
+
+    /// Features of the workspace members.
+    features: FeatureSelection,
+}
+
+impl DepOpsLocal<PgSource> {
+    pub fn new(project: &str) -> Result<Self, AuditError> {
+        // Prepare the db client.
+        let source = PgSource::new(DEFAULT_DB)?;
+        Self::with_source(source, project)
+    }
+}
+
+impl DepOpsLocal<IndexSource> {
+    /// Works without database, see [`IndexSource`].
+    pub fn new_offline(project: &str, index: &str, ruf_dump: &str) -> Result<Self, AuditError> {
+        let source = IndexSource::new(index, ruf_dump)?;
+        Self::with_source(source, project)
+    }
+}
+
+impl<S: MetaSource> DepOpsLocal<S> {
+    /// The project can be given as its directory or its `Cargo.toml`.
+    pub fn with_source(source: S, project: &str) -> Result<Self, AuditError> {
+        let path = PathBuf::from(project)
+            .canonicalize()
+            .map_err(|e| AuditError::InnerError(format!("invalid project {project}: {e}")))?;
+
+        let (project_path, toml_path) = if path.is_dir() {
+            (path.clone(), path.join("Cargo.toml"))
+        } else {
+            (path.parent().unwrap().to_path_buf(), path)
+        };
+        if !toml_path.exists() {
+            return Err(AuditError::InnerError(format!(
+                "no Cargo.toml found in {}",
+                project_path.display()
+            )));
+        }
+
+        let home_path = cargo::util::homedir(&project_path)
+            .ok_or(AuditError::InnerError("cargo home not found".to_string()))?;
+
+        Ok(Self {
+            meta: MetaOps::new(source),
+
+            project_path,
+            toml_path,
+            home_path,
+            lockfile: None,
+
+            locals: RefCell::new(FxHashMap::default()),
+            members: RefCell::new(Vec::new()),
+
+            features: FeatureSelection::default(),
+        })
+    }
+
+    /// Resolve from another lockfile of the project, like one before `cargo update`.
+    pub fn set_lockfile(&mut self, lockfile: &str) {
+        self.lockfile = Some(PathBuf::from(lockfile));
+    }
+
+    /// For the inital resolve, we start from the project's own lockfile if there is one,
+    /// or the lockfile set.
+    fn do_first_resolve(&self) -> Result<(Resolve, Tree), String> {
+        let config = resolve::new_gctx(&self.project_path, &self.home_path);
+        let ws = Workspace::new(&self.toml_path, &config).map_err(|e| e.to_string())?;
+
+        let previous = match &self.lockfile {
+            Some(lockfile) => Some(resolve::load_lockfile(&ws, lockfile)?),
+            None => ops::load_pkg_lockfile(&ws).map_err(|e| e.to_string())?,
+        };
+        let features = resolve::cli_features(&self.features)?;
+        let resolve =
+            resolve::resolve_ws(&ws, &features, previous.as_ref(), self.meta.resolve_as_of())?;
+
+        // Collect the locals along with their resolved dependencies.
+        let mut locals = self.locals.borrow_mut();
+        locals.clear();
+        let host = self.meta.cfg.for_host();
+        for pkg_id in resolve.iter() {
+            if pkg_id.source_id().is_registry() {
+                continue;
+            }
+
+            let mut reqs = FxHashMap::default();
+            for (dep_id, deps) in resolve.deps(pkg_id) {
+                // NOTICE: A dependency may be declared multiple times (build, targets, ...),
+                // we only keep the first one for our target here, or the host for build ones.
+                if let Some(dep) = deps.iter().find(|dep| {
+                    let cfg = if dep.kind() == DepKind::Build {
+                        &host
+                    } else {
+                        &self.meta.cfg
+                    };
+                    dep.platform()
+                        .map_or(true, |platform| cfg.matches_platform(&platform.to_string()))
+                }) {
+                    // Locked or not, the req is the one declared.
+                    reqs.entry(dep_id.name().to_string()).or_insert_with(|| {
+                        VersionReq::parse(&dep.version_req().to_string())
+                            .unwrap_or(VersionReq::STAR)
+                    });
+                }
+            }
+
+            let check_dup =
+                locals.insert(pkg_id.name().to_string(), (pkg_id.version().clone(), reqs));
+            if check_dup.is_some() {
+                return Err(format!("multiple local packages named {}", pkg_id.name()));
+            }
+        }
+        drop(locals);
+
+        *self.members.borrow_mut() = ws
+            .members()
+            .map(|pkg| (pkg.name().to_string(), pkg.version().clone()))
+            .collect();
+
+        let tree = resolve::resolve_to_tree(&ws, &resolve, &self.meta.cfg)?;
+
+        Ok((resolve, tree))
+    }
+
+    /// Updates one pkg in a time.
+    fn do_update_resolve_once(
+        &self,
+        prev_resolve: &Resolve,
+        update: &(String, Version, Version),
+    ) -> Result<(Resolve, Tree), String> {
+        let config = resolve::new_gctx(&self.project_path, &self.home_path);
+        let ws = Workspace::new(&self.toml_path, &config).map_err(|e| e.to_string())?;
+
+        let features = resolve::cli_features(&self.features)?;
+        let resolve = resolve::update_resolve_once(
+            &ws,
+            &features,
+            prev_resolve,
+            update,
+            self.meta.resolve_as_of(),
+        )?;
+        let tree = resolve::resolve_to_tree(&ws, &resolve, &self.meta.cfg)?;
+
+        Ok((resolve, tree))
+    }
+}
+
+impl<S: MetaSource> DepOps for DepOpsLocal<S> {
+    fn get_all_candidates(&self, name: &str) -> Result<FxHashMap<Version, CondRufs>, AuditError> {
+        // Check locals first
+        if self.locals.borrow().contains_key(name) {
+            return Ok(FxHashMap::default());
+        }
+
+        self.meta.get_all_candidates(name)
+    }
+
+    fn get_yanked(&self, name: &str) -> Result<FxHashSet<Version>, AuditError> {
+        // Locals are never yanked.
+        if self.locals.borrow().contains_key(name) {
+            return Ok(FxHashSet::default());
+        }
+
+        self.meta.get_yanked(name)
+    }
+
+    fn get_pkg_versionreq(
+        &self,
+        name: &str,
+        ver: &str,
+    ) -> Result<FxHashMap<String, VersionReq>, AuditError> {
+        // Check locals first
+        if let Some((_, localreq)) = self.locals.borrow().get(name) {
+            return Ok(localreq.clone());
+        }
+
+        self.meta.get_pkg_versionreq(name, ver)
+    }
+
+    fn extract_rufs(
+        &self,
+        resolve: &Resolve,
+    ) -> Result<FxHashMap<String, Vec<String>>, AuditError> {
+        // Locals are not in our databases.
+        self.meta
+            .extract_rufs(resolve, |pkg_id| !pkg_id.source_id().is_registry())
+    }
+
+    fn resolve_condrufs<'ctx>(
+        &self,
+        resolve: &Resolve,
+        name: &str,
+        ver: &str,
+        condrufs: &'ctx CondRufs,
+    ) -> Result<Vec<&'ctx String>, AuditError> {
+        self.meta.resolve_condrufs(resolve, name, ver, condrufs)
+    }
+
+    fn set_cfg(&mut self, cfg: CfgProfile) {
+        self.meta.set_cfg(cfg);
+    }
+
+    fn get_cfg(&self) -> &CfgProfile {
+        &self.meta.cfg
+    }
+
+    fn set_features(&mut self, features: FeatureSelection) {
+        self.features = features;
+    }
+
+    fn get_features(&self) -> &FeatureSelection {
+        &self.features
+    }
+
+    fn set_as_of(&mut self, as_of: Option<Timestamp>) {
+        self.meta.set_as_of(as_of);
+    }
+
+    fn get_as_of(&self) -> Option<&Timestamp> {
+        self.meta.as_of.as_ref()
+    }
+
+    fn set_policy(&mut self, policy: RufPolicy) {
+        self.meta.policy = policy;
+    }
+
+    fn get_policy(&self) -> &RufPolicy {
+        &self.meta.policy
+    }
+
+    fn get_root_features(&self, resolve: &Resolve) -> Result<Vec<String>, AuditError> {
+        let roots = resolve::root_pkgs(resolve);
+        let mut features = Vec::new();
+        for pkg_id in roots.iter() {
+            for feature in resolve.summary(*pkg_id).features().keys() {
+                // Features of several members are told apart by the member names.
+                if roots.len() == 1 {
+                    features.push(feature.to_string());
+                } else {
+                    features.push(format!("{}/{}", pkg_id.name(), feature));
+                }
+            }
+        }
+        features.sort();
+
+        Ok(features)
+    }
+
+    fn filter_rufs<'ctx>(
+        &self,
+        rustv: u32,
+        name: &str,
+        rufs: Vec<&'ctx String>,
+    ) -> Vec<&'ctx String> {
+        self.meta.filter_rufs(rustv, name, rufs)
+    }
+
+    fn first_resolve(&self) -> Result<(Resolve, Tree), AuditError> {
+        self.do_first_resolve().map_err(AuditError::InnerError)
+    }
+
+    fn update_resolve(
+        &self,
+        prev_resolve: &Resolve,
+        update: (String, Version, Version),
+    ) -> Result<(Resolve, Tree), AuditError> {
+        self.do_update_resolve_once(prev_resolve, &update)
+            .map_err(AuditError::InnerError)
+    }
+
+    fn get_locals(&self) -> Vec<(String, Version)> {
+        self.locals
+            .borrow()
+            .iter()
+            .map(|(name, (ver, _))| (name.clone(), ver.clone()))
+            .collect()
+    }
+
+    fn get_audited(&self) -> Vec<(String, Version)> {
+        self.members.borrow().clone()
+    }
+
+    fn get_fixed_manifest(&self, pins: &[(String, Version)]) -> Result<Option<String>, AuditError> {
+        let file = fs::read_to_string(&self.toml_path)
+            .map_err(|e| AuditError::InnerError(e.to_string()))?;
+        let manifest = manifest::pin_reqs(&file, pins).map_err(AuditError::InnerError)?;
+
+        Ok(Some(manifest))
+    }
+
+    fn get_resolve_lockfile(&self, resolve: &Resolve) -> Result<String, AuditError> {
+        let config = resolve::new_gctx(&self.project_path, &self.home_path);
+        let ws = Workspace::new(&self.toml_path, &config)
+            .map_err(|e| AuditError::InnerError(e.to_string()))?;
+        let lockfile = ops::resolve_to_string(&ws, resolve)
+            .map_err(|e| AuditError::InnerError(e.to_string()))?;
+
+        Ok(lockfile)
+    }
+}
@@ -0,0 +1,136 @@
+//! Manifest edits shared by the dependency operators.
+
+use semver::{Version, VersionReq};
+use toml_edit::{DocumentMut, Item, TableLike};
+
+/// Dependency tables of a package, and of each `[target.'cfg(..)']`.
+const DEP_TABLES: [&str; 3] = ["dependencies", "build-dependencies", "dev-dependencies"];
+
+/// Edit the version requirements in the manifest not accepting the pinned versions, to the pinned
+/// ones. The lockfile pins the rest, so only existing requirements are edited, in all dependency
+/// tables and `[workspace.dependencies]`. Inherited ones (`workspace = true`) are left to the
+/// workspace, and deps without requirements (path or git ones) are left as they are.
+pub(super) fn pin_reqs(manifest: &str, pins: &[(String, Version)]) -> Result<String, String> {
+    let mut doc = manifest.parse::<DocumentMut>().map_err(|e| e.to_string())?;
+
+    for key in DEP_TABLES {
+        if let Some(deps) = doc.get_mut(key).and_then(Item::as_table_like_mut) {
+            pin_deps(deps, pins)?;
+        }
+    }
+    if let Some(targets) = doc.get_mut("target").and_then(Item::as_table_like_mut) {
+        for (_, target) in targets.iter_mut() {
+            for key in DEP_TABLES {
+                if let Some(deps) = target.get_mut(key).and_then(Item::as_table_like_mut) {
+                    pin_deps(deps, pins)?;
+                }
+            }
+        }
+    }
+    if let Some(deps) = doc
+        .get_mut("workspace")
+        .and_then(|workspace| workspace.get_mut("dependencies"))
+        .and_then(Item::as_table_like_mut)
+    {
+        pin_deps(deps, pins)?;
+    }
+
+    Ok(doc.to_string())
+}
+
+fn pin_deps(deps: &mut dyn TableLike, pins: &[(String, Version)]) -> Result<(), String> {
+    for (key, item) in deps.iter_mut() {
+        // Deps may be renamed.
+        let name = item
+            .get("package")
+            .and_then(|p| p.as_str())
+            .unwrap_or(key.get());
+        let Some((_, ver)) = pins.iter().find(|(pin, _)| pin == name) else {
+            continue;
+        };
+
+        let req = match item.as_table_like() {
+            Some(table) if table.contains_key("workspace") => continue,
+            Some(table) => table.get("version").and_then(|req| req.as_str()),
+            None => item.as_str(),
+        };
+        let Some(req) = req else {
+            continue;
+        };
+        let req = VersionReq::parse(req)
+            .map_err(|e| format!("invalid requirement {req} of {name}: {e}"))?;
+        if req.matches(ver) {
+            continue;
+        }
+
+        let pinned = toml_edit::value(format!("={ver}"));
+        match item.as_table_like_mut() {
+            Some(table) => {
+                table.insert("version", pinned);
+            }
+            None => *item = pinned,
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_pin_reqs() {
+    let manifest = r#"[package]
+name = "local"
+version = "0.1.0"
+
+[dependencies]
+kept = "1.0"
+bumped = "1.0"
+renamed = { package = "real", version = "0.2" }
+inherited = { workspace = true }
+local_dep = { path = "../local_dep" }
+
+[build-dependencies]
+built = { version = "2.1", features = ["std"] }
+
+[target.'cfg(unix)'.dependencies]
+unix_only = "0.3.1"
+
+[workspace.dependencies]
+inherited = "3"
+"#;
+    let pins = [
+        ("kept", "1.2.0"),
+        ("bumped", "2.0.0"),
+        ("real", "0.1.9"),
+        ("inherited", "4.0.0"),
+        ("local_dep", "1.0.0"),
+        ("built", "2.0.5"),
+        ("unix_only", "0.3.0"),
+        ("transitive", "1.0.0"),
+    ]
+    .map(|(name, ver)| (name.to_string(), Version::parse(ver).unwrap()));
+
+    let pinned = pin_reqs(manifest, &pins).unwrap();
+    assert_eq!(
+        pinned,
+        r#"[package]
+name = "local"
+version = "0.1.0"
+
+[dependencies]
+kept = "1.0"
+bumped = "=2.0.0"
+renamed = { package = "real", version = "=0.1.9" }
+inherited = { workspace = true }
+local_dep = { path = "../local_dep" }
+
+[build-dependencies]
+built = { version = "=2.0.5", features = ["std"] }
+
+[target.'cfg(unix)'.dependencies]
+unix_only = "=0.3.0"
+
+[workspace.dependencies]
+inherited = "=4.0.0"
+"#
+    );
+}
@@ -0,0 +1,247 @@
+//! Crate meta operations shared by the dependency operators.
+
+use std::cell::RefCell;
+
+use cargo::core::{PackageId, Resolve};
+use cargo::util::interning::InternedString;
+
+use fxhash::{FxHashMap, FxHashSet};
+use semver::{Version, VersionReq};
+
+use crate::basic::{self, CfgProfile, CondRufs, RufPolicy, Timestamp};
+use crate::core::AuditError;
+
+use super::resolve::{self, AsOf, PublishTimes};
+use super::source::MetaSource;
+
+/// The crate metas of registry packages from the [`MetaSource`], cached, along with the settings
+/// they are read with. Locals are left to the operators, as they know them.
+pub(super) struct MetaOps<S: MetaSource> {
+    /// Where the crate metas come from.
+    pub source: S,
+
+    /// Ruf conds are evaluated with it.
+    pub cfg: CfgProfile,
+    /// Only versions published before it are used, if set.
+    pub as_of: Option<Timestamp>,
+    /// Rufs accepted, on top of the compiler.
+    pub policy: RufPolicy,
+
+    /// Caches.
+    cads_cache: RefCell<FxHashMap<String, FxHashMap<Version, CondRufs>>>,
+    reqs_cache: RefCell<FxHashMap<String, FxHashMap<String, VersionReq>>>,
+    times_cache: RefCell<FxHashMap<String, FxHashMap<Version, Timestamp>>>,
+    yanked_cache: RefCell<FxHashMap<String, FxHashSet<Version>>>,
+}
+
+impl<S: MetaSource> PublishTimes for MetaOps<S> {
+    fn publish_times(&self, name: &str) -> Result<FxHashMap<Version, Timestamp>, String> {
+        self.get_publish_times(name)
+    }
+}
+
+impl<S: MetaSource> MetaOps<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            source,
+
+            cfg: CfgProfile::default(),
+            as_of: None,
+            policy: RufPolicy::default(),
+
+            cads_cache: RefCell::new(FxHashMap::default()),
+            reqs_cache: RefCell::new(FxHashMap::default()),
+            times_cache: RefCell::new(FxHashMap::default()),
+            yanked_cache: RefCell::new(FxHashMap::default()),
+        }
+    }
+
+    pub fn set_cfg(&mut self, cfg: CfgProfile) {
+        // Version reqs differ between targets.
+        if cfg.target() != self.cfg.target() {
+            self.reqs_cache.borrow_mut().clear();
+        }
+        self.cfg = cfg;
+    }
+
+    pub fn set_as_of(&mut self, as_of: Option<Timestamp>) {
+        // Candidates are filtered with it.
+        if as_of != self.as_of {
+            self.cads_cache.borrow_mut().clear();
+        }
+        self.as_of = as_of;
+    }
+
+    /// All versions of a registry crate, with their cond rufs.
+    pub fn get_all_candidates(
+        &self,
+        name: &str,
+    ) -> Result<FxHashMap<Version, CondRufs>, AuditError> {
+        if let Some(cads) = self.cads_cache.borrow().get(name) {
+            return Ok(cads.clone());
+        }
+
+        let mut cads = self
+            .source
+            .get_version_rufs(name)
+            .map_err(AuditError::InnerError)?;
+
+        // Versions without publish times are kept, the same as resolving as of it.
+        if let Some(as_of) = &self.as_of {
+            let times = self
+                .get_publish_times(name)
+                .map_err(AuditError::InnerError)?;
+            cads.retain(|v, _| times.get(v).map_or(true, |time| time <= as_of));
+        }
+
+        self.cads_cache
+            .borrow_mut()
+            .insert(name.to_string(), cads.clone());
+
+        Ok(cads)
+    }
+
+    /// Yanked versions of a registry crate.
+    pub fn get_yanked(&self, name: &str) -> Result<FxHashSet<Version>, AuditError> {
+        if let Some(yanked) = self.yanked_cache.borrow().get(name) {
+            return Ok(yanked.clone());
+        }
+
+        let yanked = self
+            .source
+            .get_yanked(name)
+            .map_err(AuditError::InnerError)?;
+        self.yanked_cache
+            .borrow_mut()
+            .insert(name.to_string(), yanked.clone());
+
+        Ok(yanked)
+    }
+
+    /// Version reqs of a registry crate version, on the target.
+    pub fn get_pkg_versionreq(
+        &self,
+        name: &str,
+        ver: &str,
+    ) -> Result<FxHashMap<String, VersionReq>, AuditError> {
+        let name_ver = format!("{}@{}", name, ver);
+        if let Some(reqs) = self.reqs_cache.borrow().get(&name_ver) {
+            return Ok(reqs.clone());
+        }
+
+        let reqs = self
+            .source
+            .get_version_reqs(name, ver, &self.cfg)
+            .map_err(AuditError::InnerError)?;
+
+        self.reqs_cache.borrow_mut().insert(name_ver, reqs.clone());
+
+        Ok(reqs)
+    }
+
+    /// Publish times of a crate, for audits as of an instant.
+    pub fn get_publish_times(&self, name: &str) -> Result<FxHashMap<Version, Timestamp>, String> {
+        if let Some(times) = self.times_cache.borrow().get(name) {
+            return Ok(times.clone());
+        }
+
+        let times = self.source.get_publish_times(name)?;
+        self.times_cache
+            .borrow_mut()
+            .insert(name.to_string(), times.clone());
+
+        Ok(times)
+    }
+
+    /// Resolves are done as of the instant audited as of, if any.
+    pub fn resolve_as_of(&self) -> Option<AsOf<'_>> {
+        self.as_of.as_ref().map(|time| AsOf { time, times: self })
+    }
+
+    /// Rufs used by the packages built for the target, except the locals.
+    pub fn extract_rufs(
+        &self,
+        resolve: &Resolve,
+        is_local: impl Fn(PackageId) -> bool,
+    ) -> Result<FxHashMap<String, Vec<String>>, AuditError> {
+        let mut rufs = FxHashMap::default();
+
+        // Packages for other targets are never built.
+        for pkg_id in resolve::target_pkgs(resolve, &self.cfg).into_keys() {
+            // Locals are not in our databases.
+            if is_local(pkg_id) {
+                continue;
+            }
+
+            let pkg_rufs = self.extract_rufs_from_one_pkg(
+                pkg_id.name().as_str(),
+                pkg_id.version(),
+                resolve.features(pkg_id),
+            )?;
+
+            // If no ruf used, we just skip it.
+            if !pkg_rufs.is_empty() {
+                let name_ver = format!("{}@{}", pkg_id.name(), pkg_id.version());
+                let check_dup = rufs.insert(name_ver, pkg_rufs);
+                assert!(check_dup.is_none());
+            }
+        }
+
+        Ok(rufs)
+    }
+
+    /// Packages missing from our ruf data use no known rufs, the tree manager reports them
+    /// when preparing their candidates.
+    fn extract_rufs_from_one_pkg(
+        &self,
+        name: &str,
+        ver: &Version,
+        pkg_feature: &[InternedString],
+    ) -> Result<Vec<String>, AuditError> {
+        let cads = self.get_all_candidates(name)?;
+        let Some(condrufs) = cads.get(ver) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(resolve::enabled_rufs(condrufs, pkg_feature, &self.cfg)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    pub fn resolve_condrufs<'ctx>(
+        &self,
+        resolve: &Resolve,
+        name: &str,
+        ver: &str,
+        condrufs: &'ctx CondRufs,
+    ) -> Result<Vec<&'ctx String>, AuditError> {
+        let pkg_id = resolve
+            .query(&format!("{}@{}", name, ver))
+            .map_err(|e| AuditError::InnerError(e.to_string()))?;
+
+        Ok(resolve::enabled_rufs(
+            condrufs,
+            resolve.features(pkg_id),
+            &self.cfg,
+        ))
+    }
+
+    /// Rufs of the package unusable on the rustc.
+    pub fn filter_rufs<'ctx>(
+        &self,
+        rustv: u32,
+        name: &str,
+        rufs: Vec<&'ctx String>,
+    ) -> Vec<&'ctx String> {
+        // Stable compilers reject all rufs, and the policy may reject more.
+        rufs.into_iter()
+            .filter(|ruf| {
+                let status = basic::get_ruf_status(ruf, rustv);
+                !self.cfg.is_nightly()
+                    || !status.is_usable()
+                    || !self.policy.allows(name, ruf, status)
+            })
+            .collect()
+    }
+}
@@ -1,8 +1,36 @@
+mod apply;
 mod audit;
+mod check;
+mod diff;
+mod feature_audit;
+mod forecast;
+mod local_ops;
+mod manifest;
+mod meta_ops;
 mod ops;
+mod optimize;
+mod options;
+mod report;
+mod resolve;
 mod root_audit;
+mod source;
 mod treeonly_audit;
+mod upgrade;
 
-pub use audit::audit;
+pub use apply::{apply_audit, Remediation};
+pub use audit::{audit, audit_concurrent};
+pub use check::check_audit;
+pub use diff::{diff_audit, IntroducedRufs, LockDiff, RufUsage};
+pub use feature_audit::{feature_audit, FeatureReport};
+pub use forecast::{forecast_audit, ForecastReport, PackageForecast};
+pub use local_ops::DepOpsLocal;
+pub use ops::DepOpsVirt;
+pub use optimize::{optimize_audit, FixCost, RankedFix};
+pub use options::AuditOptions;
+pub use report::{
+    Decision, EdgeChange, ErrorRecord, FixStep, IssueRecord, NightlyWindow, RustcDecision, Summary,
+};
 pub use root_audit::root_audit;
-pub use treeonly_audit::{treeonly_audit, Summary};
+pub use source::{CachedSource, DepInfo, DepKind, IndexSource, MetaSource, PgSource};
+pub use treeonly_audit::treeonly_audit;
+pub use upgrade::{upgrade_audit, ReqEdit, UpgradePlan};
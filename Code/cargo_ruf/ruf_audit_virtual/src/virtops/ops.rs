@@ -1,61 +1,36 @@
-use std::cell::RefCell;
 use std::collections::HashSet;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 use std::path::PathBuf;
 use std::str::FromStr;
-use std::sync::Mutex;
 
 use cargo::core::registry::PackageRegistry;
 use cargo::core::resolver::{CliFeatures, HasDevUnits};
 use cargo::core::{PackageId, PackageIdSpec, PackageIdSpecQuery, Resolve, Shell, Workspace};
 use cargo::util::cache_lock::CacheLockMode;
-use cargo::util::interning::InternedString;
 use cargo::{ops, GlobalContext};
 
 use cargo_lock::dependency::Tree;
 use cargo_lock::Lockfile;
 use fxhash::{FxHashMap, FxHashSet};
-use postgres::{Client, NoTls};
-use regex::Regex;
 use semver::{Version, VersionReq};
 
-use crate::basic::{self, CondRuf, CondRufs};
+use crate::basic::{CfgProfile, CondRufs, FeatureSelection, RufPolicy, Timestamp};
 use crate::core::AuditError;
 use crate::core::DepOps;
 
-lazy_static::lazy_static! {
-    static ref RE_CONDS: Regex = Regex::new(r"^\s*feature\s*=\s*([\w-]+)\s*$").unwrap();
-}
-
-/*
-    -- Currently we HAVE NOT created this table --
-    CREATE TABLE version_ruf AS
-    SELECT versions_with_name.id, versions_with_name.name, versions_with_name.num, versions_with_name.crate_id, version_feature.conds, version_feature.feature
-    FROM versions_with_name
-    JOIN version_feature
-    ON versions_with_name.id = version_feature.id
-
-    -- We have to strip empty cond('') to NULL --
-    UPDATE version_ruf SET conds = NULL WHERE conds = ''
-
-    -- And also the dependencies table --
-    CREATE VIEW dependencies_with_name AS
-    SELECT dependencies.*, crates.name AS crate_name
-    FROM dependencies
-    JOIN crates
-    ON dependencies.crate_id = crates.id
-*/
+use super::meta_ops::MetaOps;
+use super::source::{CachedSource, IndexSource, MetaSource, PgSource, DEFAULT_DB};
+use super::{manifest, resolve};
 
 /// Colect needed info from our databases, we call it virtual impl.
 /// Used for virtual pipeline analysis.
-pub struct DepOpsVirt {
-    /// For our database connection.
-    conn: Mutex<Client>,
+pub struct DepOpsVirt<S: MetaSource = PgSource> {
+    /// Crate metas of the registry packages.
+    meta: MetaOps<S>,
 
-    /// For the target crates.
-    name: String,
-    ver: String,
+    /// For the target crates, the dependencies of the virt crate.
+    crates: Vec<(String, Version)>,
 
     /// For the virt workspace.
     workspace_path: PathBuf,
@@ -65,26 +40,89 @@ pub struct DepOpsVirt {
     /// The local crates.
     locals: FxHashMap<String, FxHashMap<String, VersionReq>>,
 
-    /// Caches.
-    cads_cache: RefCell<FxHashMap<String, FxHashMap<Version, CondRufs>>>,
-    reqs_cache: RefCell<FxHashMap<String, FxHashMap<String, VersionReq>>>,
+    /// Features of the target crate.
+    features: FeatureSelection,
 }
 
-impl DepOpsVirt {
+impl DepOpsVirt<PgSource> {
     pub fn new(name: &str, ver: &str, workspace: &str) -> Result<Self, AuditError> {
         // Prepare the db client.
-        let client = Client::connect(
-            "host=localhost dbname=crates user=postgres password=postgres",
-            NoTls,
-        )
-        .unwrap();
+        let source = PgSource::new(DEFAULT_DB)?;
+        Self::with_source(source, name, ver, workspace)
+    }
+
+    /// Audit a batch of crates together, sharing one resolve, see [`DepOpsVirt::with_source_batch`].
+    pub fn new_batch(crates: &[(&str, &str)], workspace: &str) -> Result<Self, AuditError> {
+        let source = PgSource::new(DEFAULT_DB)?;
+        Self::with_source_batch(source, crates, workspace)
+    }
+}
+
+impl DepOpsVirt<CachedSource<PgSource>> {
+    /// Metas are cached in the directory, shared with other audits, see [`CachedSource`].
+    pub fn new_cached(
+        name: &str,
+        ver: &str,
+        workspace: &str,
+        cache: &str,
+    ) -> Result<Self, AuditError> {
+        let source = CachedSource::new(PgSource::new(DEFAULT_DB)?, cache)?;
+        Self::with_source(source, name, ver, workspace)
+    }
+}
+
+impl DepOpsVirt<IndexSource> {
+    /// Works without database, see [`IndexSource`].
+    pub fn new_offline(
+        name: &str,
+        ver: &str,
+        workspace: &str,
+        index: &str,
+        ruf_dump: &str,
+    ) -> Result<Self, AuditError> {
+        let source = IndexSource::new(index, ruf_dump)?;
+        Self::with_source(source, name, ver, workspace)
+    }
+}
+
+impl<S: MetaSource> DepOpsVirt<S> {
+    pub fn with_source(
+        source: S,
+        name: &str,
+        ver: &str,
+        workspace: &str,
+    ) -> Result<Self, AuditError> {
+        Self::with_source_batch(source, &[(name, ver)], workspace)
+    }
+
+    /// The crates are all depended by the virt crate, so they shall have different names.
+    /// Fixes are then checked against all of them.
+    pub fn with_source_batch(
+        source: S,
+        crates: &[(&str, &str)],
+        workspace: &str,
+    ) -> Result<Self, AuditError> {
+        if crates.is_empty() {
+            return Err(AuditError::InnerError("no crates to audit".to_string()));
+        }
 
         // Prepare local crates.
         let mut locals = FxHashMap::default();
         let mut virt_inner = FxHashMap::default();
-        let virt_req = VersionReq::parse(&format!("={}", ver))
-            .map_err(|e| AuditError::InnerError(e.to_string()))?;
-        virt_inner.insert(name.to_string(), virt_req);
+        let mut target_crates = Vec::new();
+        for (name, ver) in crates {
+            let ver = Version::parse(ver).map_err(|e| {
+                AuditError::InnerError(format!("invalid version {name}@{ver}: {e}"))
+            })?;
+            let virt_req = VersionReq::parse(&format!("={}", ver))
+                .map_err(|e| AuditError::InnerError(e.to_string()))?;
+            if virt_inner.insert(name.to_string(), virt_req).is_some() {
+                return Err(AuditError::InnerError(format!(
+                    "crate {name} audited more than once"
+                )));
+            }
+            target_crates.push((name.to_string(), ver));
+        }
         locals.insert("virt".to_string(), virt_inner);
 
         let workspace_path = PathBuf::from(workspace);
@@ -92,10 +130,9 @@ impl DepOpsVirt {
         let toml_path = workspace_path.join("Cargo.toml");
 
         let uninit = Self {
-            conn: Mutex::new(client),
+            meta: MetaOps::new(source),
 
-            name: name.to_string(),
-            ver: ver.to_string(),
+            crates: target_crates,
 
             workspace_path: workspace_path,
             registry_path: registry_path,
@@ -103,204 +140,83 @@ impl DepOpsVirt {
 
             locals: locals,
 
-            cads_cache: RefCell::new(FxHashMap::default()),
-            reqs_cache: RefCell::new(FxHashMap::default()),
+            features: FeatureSelection::default(),
         };
 
         Ok(uninit)
     }
 
-    #[allow(unused)]
-    fn get_crate_id_with_name(&self, crate_name: &str) -> Result<i32, String> {
-        let crate_id = self
-            .conn
-            .lock()
-            .unwrap()
-            .query(
-                "SELECT id FROM crates WHERE name = $1 LIMIT 1",
-                &[&crate_name],
-            )
-            .map_err(|e| e.to_string())?;
-
-        if crate_id.len() == 0 {
-            return Err(format!("No crate with name {} found", crate_name));
-        }
-
-        Ok(crate_id[0].get::<usize, i32>(0))
-    }
-
-    fn get_version_id_with_name_ver(&self, crate_name: &str, version: &str) -> Result<i32, String> {
-        let version_id = self
-            .conn
-            .lock()
-            .unwrap()
-            .query(
-                "SELECT id FROM versions_with_name WHERE name = $1 AND num = $2 LIMIT 1",
-                &[&crate_name, &version],
-            )
-            .map_err(|e| e.to_string())?;
+    /// For the inital resolve, called at [new] only once.
+    fn do_first_resolve(&self) -> Result<(Resolve, Tree), String> {
+        // Create virtual environment.
+        assert!(self.workspace_path.exists());
 
-        if version_id.len() == 0 {
-            return Err(format!(
-                "No version with namever {}-{} found",
-                crate_name, version
-            ));
+        // The target crates are pinned, so they shall be published before the instant.
+        if let Some(as_of) = &self.meta.as_of {
+            for (name, ver) in self.crates.iter() {
+                let times = self.meta.get_publish_times(name)?;
+                if times.get(ver).is_some_and(|time| time > as_of) {
+                    return Err(format!("{name}@{ver} is published after {as_of}"));
+                }
+            }
         }
 
-        Ok(version_id[0].get::<usize, i32>(0))
-    }
-
-    fn get_cads_with_crate_name(&self, name: &str) -> Result<FxHashMap<Version, CondRufs>, String> {
-        let rows = self
-            .conn
-            .lock()
-            .unwrap()
-            .query(
-                "SELECT num, conds, feature FROM version_ruf WHERE name = $1",
-                &[&name],
-            )
-            .expect("Fatal, db query failed");
-
-        let mut dep_rufs = FxHashMap::default();
-        for row in rows {
-            let ver = row.get::<_, String>(0);
-            let ver = Version::parse(&ver)
-                .map_err(|e| format!("Version parse failure, invalid version: {} {}", ver, e))?;
-
-            let entry = dep_rufs.entry(ver).or_insert_with(CondRufs::empty);
+        // 1. Pre-resolve: with the selected features, and get all features if needed.
+        let mut resolve = self.resolve_virt_toml(&self.features.features(&[]))?;
 
-            let cond = row.get::<_, Option<String>>(1);
-            let ruf = row.get::<_, String>(2);
-
-            if ruf != "no_feature_used" {
-                entry.push(CondRuf {
-                    cond: cond,
-                    feature: ruf,
-                });
+        // 2. Update resolve with all features if found any.
+        if self.features == FeatureSelection::All {
+            let features = self.get_declared_features(&resolve)?;
+            if !features.is_empty() {
+                resolve = self.resolve_virt_toml(&features)?;
             }
         }
 
-        Ok(dep_rufs)
-    }
-
-    fn get_reqs_with_version_id(
-        &self,
-        version_id: i32,
-    ) -> Result<FxHashMap<String, VersionReq>, String> {
-        let rows = self
-            .conn
-            .lock()
-            .unwrap()
-            .query(
-                "SELECT crate_name, req, kind FROM dependencies_with_name WHERE version_id = $1",
-                &[&version_id],
-            )
-            .expect("Fatal, db query failed");
-
-        let mut dep_reqs = FxHashMap::default();
-        for row in rows {
-            let name = row.get::<_, String>(0);
-            let req = row.get::<_, String>(1);
-            let req = VersionReq::parse(&req)
-                .map_err(|e| format!("VersionReq parse failure, invalid req: {} {}", req, e))?;
-            let kind = row.get::<_, i32>(2);
-
-            if kind != 2 {
-                // NOTICE: Shall we ignore the optional, target, etc on the dependencies ?
-                let check_dup = dep_reqs.insert(name.clone(), req.clone());
-                if let Some(dup) = check_dup {
-                    if dup != req {
-                        return Err(format!(
-                            "conflict version reqs on {name}: {dup}, by {version_id}, maybe differernt cfgs"
-                        ));
-                    }
-                }
-            } // We DONOT care the dev dependencies.
-        }
+        // And here the resolve is finally usable.
+        let config = resolve::new_gctx(&self.workspace_path, &self.registry_path);
+        let ws = Workspace::new(&self.toml_path, &config).map_err(|e| e.to_string())?;
+        let tree = resolve::resolve_to_tree(&ws, &resolve, &self.meta.cfg)?;
 
-        Ok(dep_reqs)
+        Ok((resolve, tree))
     }
 
-    /// For the inital resolve, called at [new] only once.
-    fn do_first_resolve(&self) -> Result<(Resolve, Tree), String> {
-        let mut features = Vec::new();
-
-        // Create virtual environment.
-        assert!(self.workspace_path.exists());
-
-        // Get virtual toml file
-        let file = self.format_virt_toml_file(&self.name, &self.ver, &features);
+    /// Write the virt toml file with the features, and resolve it.
+    fn resolve_virt_toml(&self, features: &[String]) -> Result<Resolve, String> {
+        let file = self.format_virt_toml_file(features);
         File::create(&self.toml_path)
             .map_err(|e| e.to_string())?
             .write_all(file.as_bytes())
             .expect("Fatal, write virt.toml file failed");
 
-        // 1. Pre-resolve: get all features first
-        let config = GlobalContext::new(
-            Shell::new(),
-            self.workspace_path.clone(),
-            self.registry_path.clone(),
-        );
-        config.shell().set_verbosity(cargo::core::Verbosity::Quiet);
-
+        let config = resolve::new_gctx(&self.workspace_path, &self.registry_path);
         let ws = Workspace::new(&self.toml_path, &config).map_err(|e| e.to_string())?;
-        let mut registry = PackageRegistry::new(ws.gctx()).map_err(|e| e.to_string())?;
-        let mut resolve = ops::resolve_with_previous(
-            &mut registry,
+        resolve::resolve_ws(
             &ws,
             &CliFeatures::new_all(true),
-            HasDevUnits::No,
             None,
-            None,
-            &[],
-            true,
+            self.meta.resolve_as_of(),
         )
-        .map_err(|e| e.to_string())?;
-
-        let pkg = resolve
-            .query(&format!("{}@{}", &self.name, &self.ver))
-            .map_err(|e| e.to_string())?;
-        for feature in resolve.summary(pkg).features().keys() {
-            features.push(feature.as_str());
-        }
+    }
 
-        // 2. Update resolve with features if found any.
-        if !features.is_empty() {
-            let file = self.format_virt_toml_file(&self.name, &self.ver, &features);
-            File::create(&self.toml_path)
-                .map_err(|e| e.to_string())?
-                .write_all(file.as_bytes())
-                .expect("Fatal, write virt.toml file failed");
-
-            let config = GlobalContext::new(
-                Shell::new(),
-                self.workspace_path.clone(),
-                self.registry_path.clone(),
-            );
-            config.shell().set_verbosity(cargo::core::Verbosity::Quiet);
-
-            let ws = Workspace::new(&self.toml_path, &config).map_err(|e| e.to_string())?;
-            let mut registry = PackageRegistry::new(ws.gctx()).map_err(|e| e.to_string())?;
-
-            resolve = ops::resolve_with_previous(
-                &mut registry,
-                &ws,
-                &CliFeatures::new_all(true),
-                HasDevUnits::No,
-                None,
-                None,
-                &[],
-                true,
-            )
-            .map_err(|e| e.to_string())?;
+    /// Features declared by the target crates, sorted.
+    /// Features of a batch are told apart by the crate names, like `name/feature`.
+    fn get_declared_features(&self, resolve: &Resolve) -> Result<Vec<String>, String> {
+        let mut features = Vec::new();
+        for (name, ver) in self.crates.iter() {
+            let pkg = resolve
+                .query(&format!("{}@{}", name, ver))
+                .map_err(|e| e.to_string())?;
+            for feature in resolve.summary(pkg).features().keys() {
+                if self.crates.len() == 1 {
+                    features.push(feature.to_string());
+                } else {
+                    features.push(format!("{}/{}", name, feature));
+                }
+            }
         }
+        features.sort();
 
-        // And here the resolve is finally usable.
-        let lockfile = ops::resolve_to_string(&ws, &mut resolve).map_err(|e| e.to_string())?;
-        let lockfile = Lockfile::from_str(&lockfile).map_err(|e| e.to_string())?;
-        let tree = lockfile.dependency_tree().map_err(|e| e.to_string())?;
-
-        Ok((resolve, tree))
+        Ok(features)
     }
 
     /// Updates one pkg in a time.
@@ -309,102 +225,18 @@ impl DepOpsVirt {
         prev_resolve: &Resolve,
         update: &(String, Version, Version),
     ) -> Result<(Resolve, Tree), String> {
-        let config = GlobalContext::new(
-            Shell::new(),
-            self.workspace_path.clone(),
-            self.registry_path.clone(),
-        );
-        config.shell().set_verbosity(cargo::core::Verbosity::Quiet);
-
+        let config = resolve::new_gctx(&self.workspace_path, &self.registry_path);
         let ws = Workspace::new(&self.toml_path, &config).map_err(|e| e.to_string())?;
 
-        let _lock = ws
-            .gctx()
-            .acquire_package_cache_lock(CacheLockMode::DownloadExclusive)
-            .map_err(|e| e.to_string())?;
-
-        let mut registry = PackageRegistry::new(ws.gctx()).map_err(|e| e.to_string())?;
-        let mut to_avoid = HashSet::new();
-
-        let mut sources = Vec::new();
-        let (name, prev_ver, new_ver) = update;
-        {
-            let name_ver = format!("{}@{}", name, prev_ver);
-            let pkg_id = prev_resolve.query(&name_ver).unwrap();
-
-            to_avoid.insert(pkg_id);
-            sources.push({
-                assert!(pkg_id.source_id().is_registry());
-                pkg_id
-                    .source_id()
-                    .with_precise_registry_version(
-                        pkg_id.name(),
-                        pkg_id.version().clone(),
-                        &new_ver.to_string(),
-                    )
-                    .map_err(|e| e.to_string())?
-            });
-
-            if let Ok(unused_id) =
-                PackageIdSpec::query_str(&name_ver, prev_resolve.unused_patches().iter().cloned())
-            {
-                to_avoid.insert(unused_id);
-            }
-        }
-
-        // Mirror `--workspace` and never avoid workspace members.
-        // Filtering them out here so the above processes them normally
-        // so their dependencies can be updated as requested
-        to_avoid = to_avoid
-            .into_iter()
-            .filter(|id| {
-                for package in ws.members() {
-                    let member_id = package.package_id();
-                    // Skip checking the `version` because `previous_resolve` might have a stale
-                    // value.
-                    // When dealing with workspace members, the other fields should be a
-                    // sufficiently unique match.
-                    if id.name() == member_id.name() && id.source_id() == member_id.source_id() {
-                        return false;
-                    }
-                }
-                true
-            })
-            .collect();
-
-        registry.add_sources(sources).map_err(|e| e.to_string())?;
-
-        // Here we place an artificial limitation that all non-registry sources
-        // cannot be locked at more than one revision. This means that if a Git
-        // repository provides more than one package, they must all be updated in
-        // step when any of them are updated.
-        //
-        // OFFICAL TODO: this seems like a hokey reason to single out the registry as being
-        // different.
-        let to_avoid_sources: HashSet<_> = to_avoid
-            .iter()
-            .map(|p| p.source_id())
-            .filter(|s| !s.is_registry())
-            .collect();
-
-        let keep =
-            |p: &PackageId| !to_avoid_sources.contains(&p.source_id()) && !to_avoid.contains(p);
-
-        let mut resolve = ops::resolve_with_previous(
-            &mut registry,
+        let features = CliFeatures::new_all(true);
+        let resolve = resolve::update_resolve_once(
             &ws,
-            &CliFeatures::new_all(true),
-            HasDevUnits::No,
-            Some(&prev_resolve),
-            Some(&keep),
-            &[],
-            true,
-        )
-        .map_err(|e| e.to_string())?;
-
-        let lockfile = ops::resolve_to_string(&ws, &mut resolve).map_err(|e| e.to_string())?;
-        let lockfile = Lockfile::from_str(&lockfile).map_err(|e| e.to_string())?;
-        let tree = lockfile.dependency_tree().map_err(|e| e.to_string())?;
+            &features,
+            prev_resolve,
+            update,
+            self.meta.resolve_as_of(),
+        )?;
+        let tree = resolve::resolve_to_tree(&ws, &resolve, &self.meta.cfg)?;
 
         Ok((resolve, tree))
     }
@@ -513,108 +345,61 @@ impl DepOpsVirt {
         Ok((resolve, tree))
     }
 
-    fn format_virt_toml_file(&self, name: &str, ver: &str, features: &Vec<&str>) -> String {
+    fn format_virt_toml_file(&self, features: &[String]) -> String {
         let mut file = String::with_capacity(256);
         file.push_str(
             "[package]\nname = \"virt\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n",
         );
 
-        // Add all features
-        file.push_str(&format!(
-            "{} = {{ version = \"={}\", features = [{}] }}",
-            name,
-            ver,
-            features
+        // Add the selected features
+        let mut deps = Vec::new();
+        for (name, ver) in self.crates.iter() {
+            let features = features
                 .iter()
+                .filter_map(|f| match f.split_once('/') {
+                    Some((crate_name, feature)) if crate_name == name => Some(feature),
+                    _ if self.crates.len() == 1 => Some(f.as_str()),
+                    _ => None,
+                })
                 .map(|f| format!("\"{}\"", f))
                 .collect::<Vec<_>>()
-                .join(",")
-        ));
-
-        file
-    }
-
-    fn extract_rufs_from_resolve(
-        &self,
-        resolve: &Resolve,
-    ) -> Result<FxHashMap<String, Vec<String>>, String> {
-        let mut rufs = FxHashMap::default();
-
-        for pkg_id in resolve.iter() {
-            let pkg_features = resolve.features(pkg_id);
-            let pkg_rufs = self.extract_rufs_from_one_pkg(
-                &pkg_id.name().as_str(),
-                &pkg_id.version().to_string(),
-                pkg_features,
-            )?;
-
-            // If no ruf used, we just skip it.
-            if !pkg_rufs.is_empty() {
-                let name_ver = format!("{}@{}", pkg_id.name(), pkg_id.version());
-                let check_dup = rufs.insert(name_ver, pkg_rufs);
-                assert!(check_dup.is_none());
-            }
-        }
-
-        Ok(rufs)
-    }
-
-    fn extract_rufs_from_one_pkg(
-        &self,
-        name: &str,
-        ver: &str,
-        pkg_feature: &[InternedString],
-    ) -> Result<Vec<String>, String> {
-        let mut rufs = FxHashSet::default();
-        let ver = Version::parse(ver).unwrap();
-        let condrufs = self
-            .get_cads_with_crate_name(name)?
-            .get(&ver)
-            .ok_or(format!("{name}@{ver} cond rufs not found"))?
-            .clone();
-
-        for condruf in condrufs.inner() {
-            let cond = condruf.cond;
-            let feature = condruf.feature;
-
-            // Check the conditions and add the feature if enabled.
-            if let Some(cond) = cond {
-                assert!(!cond.is_empty());
-                if let Some(caps) = RE_CONDS.captures(&cond) {
-                    let cond_pf = caps.get(1).expect("Fatal, invalid regex capture").as_str();
-                    if pkg_feature.contains(&InternedString::new(cond_pf)) {
-                        rufs.insert(feature);
-                    }
-                } // Or it's not `feature = "xxx"` condition, we assume it not enabled.
-            } else {
-                rufs.insert(feature);
-            }
+                .join(",");
+
+            deps.push(format!(
+                "{} = {{ version = \"={}\",{} features = [{}] }}",
+                name,
+                ver,
+                if self.features.uses_default() {
+                    ""
+                } else {
+                    " default-features = false,"
+                },
+                features
+            ));
         }
+        file.push_str(&deps.join("\n"));
 
-        Ok(rufs.drain().collect())
+        file
     }
 }
 
-impl DepOps for DepOpsVirt {
+impl<S: MetaSource> DepOps for DepOpsVirt<S> {
     fn get_all_candidates(&self, name: &str) -> Result<FxHashMap<Version, CondRufs>, AuditError> {
         // Check locals first
         if self.locals.contains_key(name) {
             return Ok(FxHashMap::default());
         }
 
-        if let Some(cads) = self.cads_cache.borrow().get(name) {
-            return Ok(cads.clone());
-        }
-
-        let cads = self
-            .get_cads_with_crate_name(name)
-            .map_err(|e| AuditError::InnerError(e))?;
+        self.meta.get_all_candidates(name)
+    }
 
-        self.cads_cache
-            .borrow_mut()
-            .insert(name.to_string(), cads.clone());
+    fn get_yanked(&self, name: &str) -> Result<FxHashSet<Version>, AuditError> {
+        // Locals are never yanked.
+        if self.locals.contains_key(name) {
+            return Ok(FxHashSet::default());
+        }
 
-        Ok(cads)
+        self.meta.get_yanked(name)
     }
 
     fn get_pkg_versionreq(
@@ -627,30 +412,17 @@ impl DepOps for DepOpsVirt {
             return Ok(localreq.clone());
         }
 
-        let name_ver = format!("{}@{}", name, ver);
-        if let Some(reqs) = self.reqs_cache.borrow().get(&name_ver) {
-            return Ok(reqs.clone());
-        }
-
-        let version_id = self
-            .get_version_id_with_name_ver(name, ver)
-            .map_err(|e| AuditError::InnerError(e))?;
-
-        let reqs = self
-            .get_reqs_with_version_id(version_id)
-            .map_err(|e| AuditError::InnerError(e))?;
-
-        self.reqs_cache.borrow_mut().insert(name_ver, reqs.clone());
-
-        Ok(reqs)
+        self.meta.get_pkg_versionreq(name, ver)
     }
 
     fn extract_rufs(
         &self,
         resolve: &Resolve,
     ) -> Result<FxHashMap<String, Vec<String>>, AuditError> {
-        self.extract_rufs_from_resolve(resolve)
-            .map_err(|e| AuditError::InnerError(e))
+        // The virt package is not a real crate.
+        self.meta.extract_rufs(resolve, |pkg_id| {
+            self.locals.contains_key(pkg_id.name().as_str())
+        })
     }
 
     fn resolve_condrufs<'ctx>(
@@ -660,35 +432,53 @@ impl DepOps for DepOpsVirt {
         ver: &str,
         condrufs: &'ctx CondRufs,
     ) -> Result<Vec<&'ctx String>, AuditError> {
-        let mut rufs = FxHashSet::default();
+        self.meta.resolve_condrufs(resolve, name, ver, condrufs)
+    }
 
-        let pkg_id = resolve
-            .query(&format!("{}@{}", name, ver))
-            .map_err(|e| AuditError::InnerError(e.to_string()))?;
+    fn set_cfg(&mut self, cfg: CfgProfile) {
+        self.meta.set_cfg(cfg);
+    }
 
-        let pkg_features = resolve.features(pkg_id);
+    fn get_cfg(&self) -> &CfgProfile {
+        &self.meta.cfg
+    }
 
-        for condruf in condrufs.borrow() {
-            if let Some(cond) = &condruf.cond {
-                assert!(!cond.is_empty());
-                if let Some(caps) = RE_CONDS.captures(&cond) {
-                    let cond_pf = caps.get(1).expect("Fatal, invalid regex capture").as_str();
-                    if pkg_features.contains(&InternedString::new(cond_pf)) {
-                        rufs.insert(&condruf.feature);
-                    }
-                } // Or it's not `feature = "xxx"` condition, we assume it not enabled.
-            } else {
-                rufs.insert(&condruf.feature);
-            }
-        }
+    fn set_features(&mut self, features: FeatureSelection) {
+        self.features = features;
+    }
 
-        Ok(rufs.drain().collect())
+    fn get_features(&self) -> &FeatureSelection {
+        &self.features
     }
 
-    fn filter_rufs<'ctx>(&self, rustv: u32, rufs: Vec<&'ctx String>) -> Vec<&'ctx String> {
-        rufs.into_iter()
-            .filter(|ruf| !basic::get_ruf_status(ruf, rustv).is_usable())
-            .collect()
+    fn set_as_of(&mut self, as_of: Option<Timestamp>) {
+        self.meta.set_as_of(as_of);
+    }
+
+    fn get_as_of(&self) -> Option<&Timestamp> {
+        self.meta.as_of.as_ref()
+    }
+
+    fn set_policy(&mut self, policy: RufPolicy) {
+        self.meta.policy = policy;
+    }
+
+    fn get_policy(&self) -> &RufPolicy {
+        &self.meta.policy
+    }
+
+    fn get_root_features(&self, resolve: &Resolve) -> Result<Vec<String>, AuditError> {
+        self.get_declared_features(resolve)
+            .map_err(AuditError::InnerError)
+    }
+
+    fn filter_rufs<'ctx>(
+        &self,
+        rustv: u32,
+        name: &str,
+        rufs: Vec<&'ctx String>,
+    ) -> Vec<&'ctx String> {
+        self.meta.filter_rufs(rustv, name, rufs)
     }
 
     fn first_resolve(&self) -> Result<(Resolve, Tree), AuditError> {
@@ -705,6 +495,27 @@ impl DepOps for DepOpsVirt {
             .map_err(|e| AuditError::InnerError(e.to_string()))
     }
 
+    fn get_locals(&self) -> Vec<(String, Version)> {
+        let mut locals = vec![("virt".to_string(), Version::new(0, 1, 0))];
+        locals.extend(self.crates.iter().cloned());
+
+        locals
+    }
+
+    fn get_audited(&self) -> Vec<(String, Version)> {
+        self.crates.clone()
+    }
+
+    fn get_fixed_manifest(&self, pins: &[(String, Version)]) -> Result<Option<String>, AuditError> {
+        // The virt toml file already has the selected features on, and only pins the target crates,
+        // which are never fixed, the lockfile pins the rest.
+        let file = fs::read_to_string(&self.toml_path)
+            .map_err(|e| AuditError::InnerError(e.to_string()))?;
+        let manifest = manifest::pin_reqs(&file, pins).map_err(AuditError::InnerError)?;
+
+        Ok(Some(manifest))
+    }
+
     fn get_resolve_lockfile(&self, resolve: &Resolve) -> Result<String, AuditError> {
         let config = GlobalContext::new(
             Shell::new(),
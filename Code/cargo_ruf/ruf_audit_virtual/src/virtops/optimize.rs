@@ -0,0 +1,152 @@
+use std::cmp::Ordering;
+
+use fxhash::FxHashSet;
+use semver::Version;
+use serde::Serialize;
+
+use super::audit::fix_rustc;
+use super::options::AuditOptions;
+use super::report::{self, Summary};
+use crate::basic::{distance, is_major_downgrade, Objective};
+use crate::core::{AuditError, AuditObserver, DepOps, DepTreeManager};
+
+/// The cost of a fix, compared by the [`Objective`].
+#[derive(Debug, Clone, Serialize)]
+pub struct FixCost {
+    /// Packages changed in the lock, including the added and removed ones.
+    pub changed: usize,
+    /// Total semver distance of the changes, as major, minor and patch.
+    pub distance: [u64; 3],
+    /// Changes going to older incompatible versions.
+    pub major_downgrades: usize,
+    pub rustv: i32,
+}
+
+/// One alternative fix, along with the objective choosing its candidates.
+#[derive(Serialize)]
+pub struct RankedFix {
+    pub cost: FixCost,
+    pub strategy: Objective,
+    pub summary: Summary,
+}
+
+impl FixCost {
+    pub fn of(summary: &Summary) -> Self {
+        // Edges of the same change from different parents are counted once.
+        let changes = summary
+            .edges
+            .iter()
+            .map(|edge| (&edge.dep, &edge.from, &edge.to))
+            .collect::<FxHashSet<_>>();
+
+        let mut cost = Self {
+            changed: changes.len(),
+            distance: [0; 3],
+            major_downgrades: 0,
+            rustv: summary.fix_rustv,
+        };
+        for (_, from, to) in changes {
+            if let (Some(from), Some(to)) = (from, to) {
+                let d = distance(from, to);
+                cost.distance.iter_mut().zip(d).for_each(|(c, d)| *c += d);
+                if is_major_downgrade(from, to) {
+                    cost.major_downgrades += 1;
+                }
+            }
+        }
+
+        cost
+    }
+
+    /// The less one is better under the objective, others break the ties.
+    pub fn cmp_by(&self, other: &Self, objective: Objective) -> Ordering {
+        let newer = other.rustv.cmp(&self.rustv);
+        let changed = self.changed.cmp(&other.changed);
+        let distance = self.distance.cmp(&other.distance);
+        let downgrades = self.major_downgrades.cmp(&other.major_downgrades);
+
+        match objective {
+            Objective::NewestRustc => newer.then(changed).then(distance),
+            Objective::FewestChanges => changed.then(distance).then(newer),
+            Objective::SmallestDistance => distance.then(changed).then(newer),
+            Objective::NoMajorDowngrade => downgrades.then(newer).then(changed).then(distance),
+        }
+    }
+}
+
+/// Enumerate alternative fixes and rank them by the objective in options, the best first.
+/// Fixes are searched on each rustc in options, choosing candidates by each objective in turn,
+/// and the same fix found twice is kept once. For `NewestRustc`, the search stops at the first
+/// fixable rustc. The observer receives the events of the audit.
+pub fn optimize_audit(
+    mut ops: impl DepOps,
+    options: &AuditOptions,
+    observer: &mut impl AuditObserver,
+) -> Result<Vec<RankedFix>, AuditError> {
+    options.setup(&mut ops);
+    let rustcs = options.rustc_indexes()?;
+    // Init a tree first, locals are set by the operators.
+    let mut deptree = DepTreeManager::new(ops, rustcs[0])?;
+    deptree.set_yank_policy(options.yank_policy);
+
+    let strategies = Objective::ALL
+        .into_iter()
+        .filter(|strategy| *strategy != options.objective);
+    let strategies = [options.objective]
+        .into_iter()
+        .chain(strategies)
+        .collect::<Vec<_>>();
+
+    let mut ranked = Vec::new();
+    let mut seen: FxHashSet<(u32, Vec<(String, Version)>)> = FxHashSet::default();
+    let mut trail = Vec::new();
+    for rustc in rustcs {
+        let mut failed = None;
+        let mut no_issue = false;
+        for &strategy in strategies.iter() {
+            deptree.set_objective(strategy);
+            let (decision, fixed) = fix_rustc(&mut deptree, rustc, observer)?;
+            let Some(fix_deps) = fixed else {
+                failed.get_or_insert(decision);
+                continue;
+            };
+
+            // Nothing to choose without issues.
+            no_issue = fix_deps.is_empty();
+            if seen.insert((rustc, deptree.get_fixed_pkgs())) {
+                let summary =
+                    report::summarize(&deptree, options, rustc as i32, fix_deps, vec![decision])?;
+                ranked.push(RankedFix {
+                    cost: FixCost::of(&summary),
+                    strategy,
+                    summary,
+                });
+            }
+            if no_issue {
+                break;
+            }
+        }
+
+        let fixed = ranked.iter().any(|r| r.cost.rustv == rustc as i32);
+        if !fixed {
+            trail.extend(failed);
+        }
+
+        // Older rustcs can never beat one without issues.
+        if no_issue || (fixed && options.objective == Objective::NewestRustc) {
+            break;
+        }
+    }
+
+    if ranked.is_empty() {
+        return Err(AuditError::NoFix(
+            trail
+                .iter()
+                .filter_map(|decision| decision.failure())
+                .collect(),
+        ));
+    }
+
+    ranked.sort_by(|a, b| a.cost.cmp_by(&b.cost, options.objective));
+    Ok(ranked)
+}
@@ -0,0 +1,49 @@
+use crate::basic::{
+    CfgProfile, FeatureSelection, Objective, RufPolicy, RustcRange, Timestamp, YankPolicy,
+};
+use crate::core::{AuditError, DepOps};
+
+/// Configurations of the audits.
+#[derive(Debug, Clone, Default)]
+pub struct AuditOptions {
+    /// The rustc versions to search, newest first.
+    pub rustc: RustcRange,
+    /// The target and `--cfg` flags ruf conds are evaluated with, the host target by default.
+    pub cfg: CfgProfile,
+    /// Features of the audited packages, all features by default.
+    pub features: FeatureSelection,
+    /// What fixes shall minimize, deciding the candidates chosen when fixing,
+    /// and the ranking of `optimize_audit`.
+    pub objective: Objective,
+    /// Audit as of the instant, using only versions published before it, like resolving then.
+    pub as_of: Option<Timestamp>,
+    /// Whether yanked versions can be fix candidates, only the locked ones by default as cargo does.
+    pub yank_policy: YankPolicy,
+    /// Rufs accepted on top of the compiler, like the `ruf-policy.toml` of the workspace,
+    /// all usable ones by default.
+    pub policy: RufPolicy,
+}
+
+impl AuditOptions {
+    /// Pass the options to the operators, before the tree is built.
+    pub(super) fn setup(&self, ops: &mut impl DepOps) {
+        ops.set_cfg(self.cfg.clone());
+        ops.set_features(self.features.clone());
+        ops.set_as_of(self.as_of);
+        ops.set_policy(self.policy.clone());
+    }
+
+    /// Lifetime table indexes of the rustc versions to search, from newest to oldest.
+    pub(super) fn rustc_indexes(&self) -> Result<Vec<u32>, AuditError> {
+        ruf_lifetime::lifetime().map_err(AuditError::InnerError)?;
+        let indexes = self.rustc.indexes().map_err(AuditError::InnerError)?;
+        if indexes.is_empty() {
+            return Err(AuditError::InnerError(format!(
+                "no rustc versions from {} to {} in our ruf lifetime table",
+                self.rustc.oldest, self.rustc.newest
+            )));
+        }
+
+        Ok(indexes)
+    }
+}
@@ -0,0 +1,368 @@
+use std::fmt::Debug;
+use std::io;
+
+use cargo_lock::dependency::graph::{Graph, NodeIndex};
+use fxhash::FxHashMap;
+use semver::Version;
+use serde::Serialize;
+
+use super::options::AuditOptions;
+use crate::basic::{Channel, Date, RustcRange, Toolchain};
+use crate::core::{AuditError, DepOps, DepTreeManager, FixFailure, RustcFailure};
+
+/// One fix step, the package is updated and uses the rufs after the fix.
+#[derive(Debug, Clone, Serialize)]
+pub struct FixStep {
+    pub name: String,
+    pub from: Version,
+    pub to: Version,
+    pub rufs: Vec<String>,
+}
+
+/// A dependency edge rewritten by the fix, `None` means the edge not exists.
+#[derive(Debug, Clone, Serialize)]
+pub struct EdgeChange {
+    pub parent: String,
+    pub dep: String,
+    pub from: Option<Version>,
+    pub to: Option<Version>,
+}
+
+/// An issue package, along with the rufs blocking the rustc.
+#[derive(Debug, Clone, Serialize)]
+pub struct IssueRecord {
+    pub package: String,
+    pub rufs: Vec<String>,
+    /// Rufs among them only enabled by build scripts probing a nightly compiler,
+    /// which are skipped on stable compilers.
+    pub probed: Vec<String>,
+    /// The audited packages depending on it.
+    pub roots: Vec<String>,
+}
+
+/// Audit errors, with the package resolved from the dependency tree.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorRecord {
+    pub kind: String,
+    pub message: Option<String>,
+    pub package: Option<String>,
+    /// Why the issue cannot be fixed, if explained.
+    pub failure: Option<FixFailure>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    /// No ruf issues found.
+    NoIssue,
+    /// All issues fixed.
+    Fixed,
+    /// Some issue has no fix.
+    Unfixable,
+    /// Fixes found, but failed when applying them.
+    FixFailed,
+}
+
+/// What happens when checking one rustc version.
+#[derive(Debug, Clone, Serialize)]
+pub struct RustcDecision {
+    /// Index in the lifetime table, and the toolchain name.
+    pub rustv: u32,
+    pub toolchain: String,
+    pub decision: Decision,
+    pub issues: Vec<IssueRecord>,
+    pub error: Option<ErrorRecord>,
+}
+
+/// Nightlies from `from` to `to`, both inclusive.
+#[derive(Debug, Clone, Serialize)]
+pub struct NightlyWindow {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize)]
+pub struct Summary {
+    /// The audited packages, fixes hold for all of them.
+    pub audited: Vec<String>,
+    /// The target triple audited for.
+    pub target: String,
+    /// The compiler channel simulated.
+    pub channel: Channel,
+    pub fix_rustv: i32,
+    pub fix_toolchain: String,
+    /// The newest nightly building the fixed rustc with all used rufs usable,
+    /// `None` if no rufs are used or no such nightly.
+    pub fix_nightly: Option<String>,
+    /// All nightlies building the fixed rustc with all used rufs usable, in the rustc range.
+    pub nightly_windows: Vec<NightlyWindow>,
+    pub fix_deps: FxHashMap<String, Vec<FixStep>>,
+    /// Dependency edges rewritten by the fix.
+    pub edges: Vec<EdgeChange>,
+    /// Decisions on each checked rustc, from newest to oldest.
+    pub trail: Vec<RustcDecision>,
+}
+
+/// Flat trail rows for csv, one row per issue package.
+#[derive(Serialize)]
+struct TrailRow<'a> {
+    rustv: u32,
+    toolchain: &'a str,
+    decision: Decision,
+    package: Option<&'a str>,
+    rufs: String,
+    probed: String,
+    error: Option<&'a str>,
+}
+
+/// Flat fix rows for csv, one row per fix step.
+#[derive(Serialize)]
+struct FixRow<'a> {
+    issue: &'a str,
+    name: &'a str,
+    from: &'a Version,
+    to: &'a Version,
+    rufs: String,
+}
+
+impl Summary {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("Fatal, summary serialize failed")
+    }
+
+    /// Write the decision trail in csv.
+    pub fn write_trail_csv(&self, writer: impl io::Write) -> Result<(), AuditError> {
+        let mut wtr = csv::Writer::from_writer(writer);
+        for decision in self.trail.iter() {
+            let error = decision.error.as_ref().and_then(|e| e.message.as_deref());
+            if decision.issues.is_empty() {
+                wtr.serialize(TrailRow {
+                    rustv: decision.rustv,
+                    toolchain: &decision.toolchain,
+                    decision: decision.decision,
+                    package: None,
+                    rufs: String::new(),
+                    probed: String::new(),
+                    error,
+                })
+                .map_err(|e| AuditError::InnerError(e.to_string()))?;
+            }
+            for issue in decision.issues.iter() {
+                wtr.serialize(TrailRow {
+                    rustv: decision.rustv,
+                    toolchain: &decision.toolchain,
+                    decision: decision.decision,
+                    package: Some(&issue.package),
+                    rufs: issue.rufs.join(";"),
+                    probed: issue.probed.join(";"),
+                    error,
+                })
+                .map_err(|e| AuditError::InnerError(e.to_string()))?;
+            }
+        }
+
+        wtr.flush()
+            .map_err(|e| AuditError::InnerError(e.to_string()))
+    }
+
+    /// Write the fix steps in csv.
+    pub fn write_fixes_csv(&self, writer: impl io::Write) -> Result<(), AuditError> {
+        let mut wtr = csv::Writer::from_writer(writer);
+        for (issue, steps) in self.fix_deps.iter() {
+            for step in steps {
+                wtr.serialize(FixRow {
+                    issue,
+                    name: &step.name,
+                    from: &step.from,
+                    to: &step.to,
+                    rufs: step.rufs.join(";"),
+                })
+                .map_err(|e| AuditError::InnerError(e.to_string()))?;
+            }
+        }
+
+        wtr.flush()
+            .map_err(|e| AuditError::InnerError(e.to_string()))
+    }
+}
+
+/// Keep the old format, as our analysis scripts parse it.
+impl Debug for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Summary")
+            .field("fix_rustv", &self.fix_rustv)
+            .field(
+                "fix_deps",
+                &self
+                    .fix_deps
+                    .iter()
+                    .map(|(name_ver, deps)| {
+                        (
+                            name_ver,
+                            deps.iter()
+                                .map(|step| {
+                                    (
+                                        &step.name,
+                                        step.from.to_string(),
+                                        step.to.to_string(),
+                                        &step.rufs,
+                                    )
+                                })
+                                .collect::<Vec<_>>(),
+                        )
+                    })
+                    .collect::<FxHashMap<_, _>>(),
+            )
+            .finish()
+    }
+}
+
+impl RustcDecision {
+    /// Record the issues under current rustc of the deptree, taken as fixed by default.
+    pub(super) fn new(
+        deptree: &DepTreeManager<impl DepOps>,
+        rustv: u32,
+        issues: &[NodeIndex],
+    ) -> Result<Self, AuditError> {
+        let graph = deptree.get_graph();
+        let used_rufs = deptree.extract_rufs()?;
+
+        let issues = issues
+            .iter()
+            .map(|nx| {
+                let package = format!("{}@{}", graph[*nx].name, graph[*nx].version);
+                let rufs = used_rufs
+                    .get(&package)
+                    .map(|rufs| {
+                        deptree
+                            .filter_rufs(graph[*nx].name.as_str(), rufs.iter().collect())
+                            .into_iter()
+                            .cloned()
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                let probed = deptree
+                    .get_probed_rufs(graph[*nx].name.as_str(), &graph[*nx].version)?
+                    .into_iter()
+                    .filter(|ruf| rufs.contains(ruf))
+                    .collect();
+                Ok(IssueRecord {
+                    package,
+                    rufs,
+                    probed,
+                    roots: deptree.get_dependent_audited(*nx),
+                })
+            })
+            .collect::<Result<Vec<_>, AuditError>>()?;
+
+        let decision = if issues.is_empty() {
+            Decision::NoIssue
+        } else {
+            Decision::Fixed
+        };
+
+        Ok(Self {
+            rustv,
+            toolchain: Toolchain::from_index(rustv).to_string(),
+            decision,
+            issues,
+            error: None,
+        })
+    }
+
+    /// Record the failure, the graph shall be the one where the error happens.
+    pub(super) fn fail(&mut self, decision: Decision, error: &AuditError, graph: &Graph) {
+        self.decision = decision;
+        self.error = Some(ErrorRecord {
+            kind: error.kind().to_string(),
+            message: error.message().map(|m| m.to_string()),
+            package: error.package(graph),
+            failure: error.failure().cloned(),
+        });
+    }
+
+    /// The failure of the decision, `None` if it does not fail.
+    pub(super) fn failure(&self) -> Option<RustcFailure> {
+        let error = self.error.as_ref()?;
+        Some(RustcFailure {
+            rustv: self.rustv,
+            toolchain: self.toolchain.clone(),
+            message: error.message.clone(),
+            failure: error.failure.clone(),
+        })
+    }
+}
+
+/// Build the summary from a fixed deptree.
+pub(super) fn summarize(
+    deptree: &DepTreeManager<impl DepOps>,
+    options: &AuditOptions,
+    fix_rustv: i32,
+    fix_deps: FxHashMap<String, Vec<FixStep>>,
+    trail: Vec<RustcDecision>,
+) -> Result<Summary, AuditError> {
+    let edges = deptree
+        .get_changed_edges()
+        .into_iter()
+        .map(|(parent, dep, from, to)| EdgeChange {
+            parent,
+            dep,
+            from,
+            to,
+        })
+        .collect();
+
+    // No nightlies needed if simulating stable compilers.
+    let windows = if deptree.get_cfg().is_nightly() {
+        nightly_windows(deptree, &options.rustc, fix_rustv as u32)?
+    } else {
+        Vec::new()
+    };
+    let fix_nightly = windows
+        .last()
+        .map(|(_, to)| Toolchain::Nightly(*to).to_string());
+
+    Ok(Summary {
+        audited: deptree.get_audited(),
+        target: deptree.get_cfg().target().to_string(),
+        channel: deptree.get_cfg().channel(),
+        fix_rustv,
+        fix_toolchain: Toolchain::from_index(fix_rustv as u32).to_string(),
+        fix_nightly,
+        nightly_windows: windows
+            .into_iter()
+            .map(|(from, to)| NightlyWindow {
+                from: Toolchain::Nightly(from).to_string(),
+                to: Toolchain::Nightly(to).to_string(),
+            })
+            .collect(),
+        fix_deps,
+        edges,
+        trail,
+    })
+}
+
+/// Nightlies in the range building the rustc, where all rufs used by the deptree are usable.
+fn nightly_windows(
+    deptree: &DepTreeManager<impl DepOps>,
+    range: &RustcRange,
+    rustv: u32,
+) -> Result<Vec<(Date, Date)>, AuditError> {
+    let used_rufs = deptree.extract_rufs()?;
+    let mut rufs = used_rufs
+        .values()
+        .flatten()
+        .map(|ruf| ruf.as_str())
+        .collect::<Vec<_>>();
+    rufs.sort();
+    rufs.dedup();
+    if rufs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let Some((from, to)) = range.nightly_window(rustv) else {
+        return Ok(Vec::new());
+    };
+
+    Ok(ruf_lifetime::usable_nightlies(&rufs, from, to))
+}
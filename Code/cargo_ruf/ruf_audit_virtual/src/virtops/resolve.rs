@@ -0,0 +1,440 @@
+//! Cargo resolve helpers shared by the dependency operators.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::task::Poll;
+
+use cargo::core::dependency::DepKind;
+use cargo::core::registry::PackageRegistry;
+use cargo::core::resolver::{CliFeatures, EncodableResolve, HasDevUnits};
+use cargo::core::{
+    Dependency, Package, PackageId, PackageIdSpec, PackageIdSpecQuery, Resolve, Shell, SourceId,
+    Workspace,
+};
+use cargo::sources::source::{MaybePackage, QueryKind, Source};
+use cargo::sources::{IndexSummary, SourceConfigMap};
+use cargo::util::cache_lock::CacheLockMode;
+use cargo::util::interning::InternedString;
+use cargo::{ops, CargoResult, GlobalContext};
+
+use cargo_lock::dependency::Tree;
+use cargo_lock::Lockfile;
+use fxhash::{FxHashMap, FxHashSet};
+use semver::Version;
+
+use crate::basic::{CfgExpr, CfgProfile, CondRufs, FeatureSelection, Timestamp};
+
+/// Quiet cargo context working at `cwd`.
+pub(super) fn new_gctx(cwd: &Path, homedir: &Path) -> GlobalContext {
+    let config = GlobalContext::new(Shell::new(), cwd.to_path_buf(), homedir.to_path_buf());
+    config.shell().set_verbosity(cargo::core::Verbosity::Quiet);
+    config
+}
+
+/// The cli features of the workspace members, like `--features`.
+pub(super) fn cli_features(selection: &FeatureSelection) -> Result<CliFeatures, String> {
+    match selection {
+        FeatureSelection::All => Ok(CliFeatures::new_all(true)),
+        _ => CliFeatures::from_command_line(
+            &selection.features(&[]),
+            false,
+            selection.uses_default(),
+        )
+        .map_err(|e| e.to_string()),
+    }
+}
+
+/// Where publish times of the crates come from, for resolves as of an instant.
+pub(super) trait PublishTimes {
+    fn publish_times(&self, name: &str) -> Result<FxHashMap<Version, Timestamp>, String>;
+}
+
+/// Resolve as of the instant, as cargo would have resolved then.
+#[derive(Clone, Copy)]
+pub(super) struct AsOf<'a> {
+    pub time: &'a Timestamp,
+    pub times: &'a dyn PublishTimes,
+}
+
+impl AsOf<'_> {
+    /// Versions without publish times are kept, as our databases may lack them.
+    fn is_late(&self, times: &FxHashMap<Version, Timestamp>, ver: &Version) -> bool {
+        times.get(ver).is_some_and(|time| time > self.time)
+    }
+}
+
+/// A registry source hiding the versions published after the instant, so that resolves never
+/// meet them, instead of moving them back after resolving.
+struct AsOfSource<'a> {
+    inner: Box<dyn Source + 'a>,
+    as_of: AsOf<'a>,
+}
+
+impl Source for AsOfSource<'_> {
+    fn source_id(&self) -> SourceId {
+        self.inner.source_id()
+    }
+
+    fn replaced_source_id(&self) -> SourceId {
+        self.inner.replaced_source_id()
+    }
+
+    fn supports_checksums(&self) -> bool {
+        self.inner.supports_checksums()
+    }
+
+    fn requires_precise(&self) -> bool {
+        self.inner.requires_precise()
+    }
+
+    fn query(
+        &mut self,
+        dep: &Dependency,
+        kind: QueryKind,
+        f: &mut dyn FnMut(IndexSummary),
+    ) -> Poll<CargoResult<()>> {
+        let times = match self.as_of.times.publish_times(dep.package_name().as_str()) {
+            Ok(times) => times,
+            Err(e) => return Poll::Ready(Err(io::Error::other(e).into())),
+        };
+        let as_of = self.as_of;
+        self.inner.query(dep, kind, &mut |summary| {
+            if !as_of.is_late(&times, summary.as_summary().version()) {
+                f(summary);
+            }
+        })
+    }
+
+    fn invalidate_cache(&mut self) {
+        self.inner.invalidate_cache()
+    }
+
+    fn set_quiet(&mut self, quiet: bool) {
+        self.inner.set_quiet(quiet)
+    }
+
+    fn download(&mut self, package: PackageId) -> CargoResult<MaybePackage> {
+        self.inner.download(package)
+    }
+
+    fn finish_download(&mut self, pkg_id: PackageId, contents: Vec<u8>) -> CargoResult<Package> {
+        self.inner.finish_download(pkg_id, contents)
+    }
+
+    fn fingerprint(&self, pkg: &Package) -> CargoResult<String> {
+        self.inner.fingerprint(pkg)
+    }
+
+    fn verify(&self, pkg: PackageId) -> CargoResult<()> {
+        self.inner.verify(pkg)
+    }
+
+    fn describe(&self) -> String {
+        self.inner.describe()
+    }
+
+    fn is_replaced(&self) -> bool {
+        self.inner.is_replaced()
+    }
+
+    fn add_to_yanked_whitelist(&mut self, pkgs: &[PackageId]) {
+        self.inner.add_to_yanked_whitelist(pkgs)
+    }
+
+    fn is_yanked(&mut self, pkg: PackageId) -> Poll<CargoResult<bool>> {
+        self.inner.is_yanked(pkg)
+    }
+
+    fn block_until_ready(&mut self) -> CargoResult<()> {
+        self.inner.block_until_ready()
+    }
+}
+
+/// The registry to resolve with. Given an instant, the sources, and crates.io, are loaded hiding
+/// the versions published after it, with source replacements applied as cargo does.
+fn new_registry<'gctx>(
+    ws: &Workspace<'gctx>,
+    sources: Vec<SourceId>,
+    as_of: Option<AsOf<'gctx>>,
+) -> Result<PackageRegistry<'gctx>, String> {
+    let gctx = ws.gctx();
+    let mut registry = PackageRegistry::new(gctx).map_err(|e| e.to_string())?;
+    let Some(as_of) = as_of else {
+        registry.add_sources(sources).map_err(|e| e.to_string())?;
+        return Ok(registry);
+    };
+
+    let crates_io = SourceId::crates_io_maybe_sparse_http(gctx).map_err(|e| e.to_string())?;
+    let source_config = SourceConfigMap::new(gctx).map_err(|e| e.to_string())?;
+    let mut loaded = Vec::new();
+    for source_id in sources.into_iter().chain([crates_io]) {
+        // Precise ones come first, the same source is loaded only once.
+        if loaded
+            .iter()
+            .any(|id: &SourceId| id.canonical_url() == source_id.canonical_url())
+        {
+            continue;
+        }
+        loaded.push(source_id);
+
+        let inner = source_config
+            .load(source_id, &HashSet::new())
+            .map_err(|e| e.to_string())?;
+        registry.add_preloaded(Box::new(AsOfSource { inner, as_of }));
+    }
+
+    Ok(registry)
+}
+
+/// Resolve the workspace, reusing the previous resolve if given. As of an instant, locked
+/// packages published after it are resolved again, as cargo would have resolved then.
+pub(super) fn resolve_ws<'gctx>(
+    ws: &Workspace<'gctx>,
+    features: &CliFeatures,
+    previous: Option<&Resolve>,
+    as_of: Option<AsOf<'gctx>>,
+) -> Result<Resolve, String> {
+    let mut late = FxHashSet::default();
+    if let (Some(previous), Some(as_of)) = (previous, as_of) {
+        for pkg_id in previous.iter() {
+            if !pkg_id.source_id().is_registry() {
+                continue;
+            }
+            let times = as_of.times.publish_times(pkg_id.name().as_str())?;
+            if as_of.is_late(&times, pkg_id.version()) {
+                late.insert(pkg_id);
+            }
+        }
+    }
+    let keep = |pkg_id: &PackageId| !late.contains(pkg_id);
+
+    let mut registry = new_registry(ws, Vec::new(), as_of)?;
+    ops::resolve_with_previous(
+        &mut registry,
+        ws,
+        features,
+        HasDevUnits::No,
+        previous,
+        Some(&keep),
+        &[],
+        true,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Load a lockfile of the workspace from another path, as cargo loads its own `Cargo.lock`.
+pub(super) fn load_lockfile(ws: &Workspace, path: &Path) -> Result<Resolve, String> {
+    let lockfile = fs::read_to_string(path)
+        .map_err(|e| format!("cannot read lockfile {}: {e}", path.display()))?;
+    let encoded: EncodableResolve = toml::from_str(&lockfile)
+        .map_err(|e| format!("invalid lockfile {}: {e}", path.display()))?;
+    encoded
+        .into_resolve(&lockfile, ws)
+        .map_err(|e| format!("invalid lockfile {}: {e}", path.display()))
+}
+
+/// Updates one pkg in a time, just like `cargo update --precise`. As of an instant, the others
+/// updated along with it are not moved past it.
+pub(super) fn update_resolve_once<'gctx>(
+    ws: &Workspace<'gctx>,
+    features: &CliFeatures,
+    prev_resolve: &Resolve,
+    update: &(String, Version, Version),
+    as_of: Option<AsOf<'gctx>>,
+) -> Result<Resolve, String> {
+    let _lock = ws
+        .gctx()
+        .acquire_package_cache_lock(CacheLockMode::DownloadExclusive)
+        .map_err(|e| e.to_string())?;
+
+    let mut to_avoid = HashSet::new();
+
+    let mut sources = Vec::new();
+    let (name, prev_ver, new_ver) = update;
+    {
+        let name_ver = format!("{}@{}", name, prev_ver);
+        let pkg_id = prev_resolve.query(&name_ver).unwrap();
+
+        to_avoid.insert(pkg_id);
+        sources.push({
+            assert!(pkg_id.source_id().is_registry());
+            pkg_id
+                .source_id()
+                .with_precise_registry_version(
+                    pkg_id.name(),
+                    pkg_id.version().clone(),
+                    &new_ver.to_string(),
+                )
+                .map_err(|e| e.to_string())?
+        });
+
+        if let Ok(unused_id) =
+            PackageIdSpec::query_str(&name_ver, prev_resolve.unused_patches().iter().cloned())
+        {
+            to_avoid.insert(unused_id);
+        }
+    }
+
+    // Mirror `--workspace` and never avoid workspace members.
+    // Filtering them out here so the above processes them normally
+    // so their dependencies can be updated as requested
+    to_avoid.retain(|id| {
+        for package in ws.members() {
+            let member_id = package.package_id();
+            // Skip checking the `version` because `previous_resolve` might have a stale
+            // value.
+            // When dealing with workspace members, the other fields should be a
+            // sufficiently unique match.
+            if id.name() == member_id.name() && id.source_id() == member_id.source_id() {
+                return false;
+            }
+        }
+        true
+    });
+
+    let mut registry = new_registry(ws, sources, as_of)?;
+
+    // Here we place an artificial limitation that all non-registry sources
+    // cannot be locked at more than one revision. This means that if a Git
+    // repository provides more than one package, they must all be updated in
+    // step when any of them are updated.
+    //
+    // OFFICAL TODO: this seems like a hokey reason to single out the registry as being
+    // different.
+    let to_avoid_sources: HashSet<_> = to_avoid
+        .iter()
+        .map(|p| p.source_id())
+        .filter(|s| !s.is_registry())
+        .collect();
+
+    let keep = |p: &PackageId| !to_avoid_sources.contains(&p.source_id()) && !to_avoid.contains(p);
+
+    ops::resolve_with_previous(
+        &mut registry,
+        ws,
+        features,
+        HasDevUnits::No,
+        Some(prev_resolve),
+        Some(&keep),
+        &[],
+        true,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// The roots of the resolve, packages no one depends on, i.e. the workspace members.
+pub(super) fn root_pkgs(resolve: &Resolve) -> Vec<PackageId> {
+    let depended = resolve
+        .iter()
+        .flat_map(|pkg_id| resolve.deps(pkg_id).map(|(dep_id, _)| dep_id))
+        .collect::<FxHashSet<_>>();
+
+    resolve
+        .iter()
+        .filter(|pkg_id| !depended.contains(pkg_id))
+        .collect()
+}
+
+/// The packages built for the target of the cfg profile, along with their dependencies on it.
+///
+/// The resolve covers all targets, so we walk from the roots through the dependencies
+/// applying to the target only. Build scripts run on the host, so build-dependencies, and all
+/// dependencies below them, apply to the host instead.
+pub(super) fn target_pkgs(
+    resolve: &Resolve,
+    cfg: &CfgProfile,
+) -> FxHashMap<PackageId, FxHashSet<PackageId>> {
+    let host = cfg.for_host();
+
+    // Packages along with whether built for the host.
+    let mut stack = root_pkgs(resolve)
+        .into_iter()
+        .map(|pkg_id| (pkg_id, false))
+        .collect::<Vec<_>>();
+    let mut visited = FxHashSet::default();
+
+    let mut pkgs: FxHashMap<PackageId, FxHashSet<PackageId>> = FxHashMap::default();
+    while let Some((pkg_id, on_host)) = stack.pop() {
+        if !visited.insert((pkg_id, on_host)) {
+            continue;
+        }
+
+        let profile = if on_host { &host } else { cfg };
+        let pkg_deps = pkgs.entry(pkg_id).or_default();
+        for (dep_id, deps) in resolve.deps(pkg_id) {
+            for dep in deps {
+                let applies = dep.kind() != DepKind::Development
+                    && dep.platform().map_or(true, |platform| {
+                        profile.matches_platform(&platform.to_string())
+                    });
+                if applies {
+                    pkg_deps.insert(dep_id);
+                    stack.push((dep_id, on_host || dep.kind() == DepKind::Build));
+                }
+            }
+        }
+    }
+
+    pkgs
+}
+
+/// Build the dependency tree of the target through the lockfile of the resolve,
+/// packages and dependencies for other targets are dropped.
+pub(super) fn resolve_to_tree(
+    ws: &Workspace,
+    resolve: &Resolve,
+    cfg: &CfgProfile,
+) -> Result<Tree, String> {
+    let lockfile = ops::resolve_to_string(ws, resolve).map_err(|e| e.to_string())?;
+    let mut lockfile = Lockfile::from_str(&lockfile).map_err(|e| e.to_string())?;
+
+    let pkgs = target_pkgs(resolve, cfg)
+        .into_iter()
+        .map(|(pkg_id, deps)| {
+            let deps = deps
+                .into_iter()
+                .map(|dep_id| format!("{}@{}", dep_id.name(), dep_id.version()))
+                .collect::<FxHashSet<_>>();
+            (format!("{}@{}", pkg_id.name(), pkg_id.version()), deps)
+        })
+        .collect::<FxHashMap<_, _>>();
+    lockfile.packages.retain_mut(|pkg| {
+        let Some(deps) = pkgs.get(&format!("{}@{}", pkg.name, pkg.version)) else {
+            return false;
+        };
+        pkg.dependencies
+            .retain(|dep| deps.contains(&format!("{}@{}", dep.name, dep.version)));
+        true
+    });
+
+    lockfile.dependency_tree().map_err(|e| e.to_string())
+}
+
+/// Pick the rufs enabled by the cfg profile and the package features.
+pub(super) fn enabled_rufs<'ctx>(
+    condrufs: &'ctx CondRufs,
+    pkg_features: &[InternedString],
+    cfg: &CfgProfile,
+) -> Vec<&'ctx String> {
+    let features = pkg_features.iter().map(|f| f.as_str()).collect::<Vec<_>>();
+    let mut rufs = FxHashSet::default();
+
+    for condruf in condrufs.borrow() {
+        if let Some(cond) = &condruf.cond {
+            assert!(!cond.is_empty());
+            // Conditions we cannot parse are assumed not enabled.
+            if let Ok(expr) = cond.parse::<CfgExpr>()
+                && cfg.eval(&expr, &features)
+            {
+                rufs.insert(&condruf.feature);
+            }
+        } else {
+            rufs.insert(&condruf.feature);
+        }
+    }
+
+    rufs.drain().collect()
+}
@@ -1,71 +1,74 @@
-use std::io::Write;
+use super::options::AuditOptions;
+use crate::core::{AuditError, AuditEvent, AuditObserver, DepOps, DepTreeManager};
 
-use super::ops::DepOpsVirt;
-use crate::core::{AuditError, DepTreeManager};
-
-/// This is only for audit evaluations. We check whethe a crate can be fixed by rustc, and only take consider of its root.
+/// This is only for audit evaluations. We check whethe a crate can be fixed by rustc, and only take consider of its roots (the locals).
 pub fn root_audit(
-    name: &str,
-    ver: &str,
-    workspace: &str,
-    debugger: &mut impl Write,
+    mut ops: impl DepOps,
+    options: &AuditOptions,
+    observer: &mut impl AuditObserver,
 ) -> Result<u32, AuditError> {
+    options.setup(&mut ops);
+    let rustcs = options.rustc_indexes()?;
     // Init a tree first
-    let ops = DepOpsVirt::new(name, ver, workspace)?;
-    let mut deptree = DepTreeManager::new(ops, 63)?;
-    let logical_root = deptree
-        .get_graph()
-        .neighbors(deptree.get_root())
-        .next()
-        .unwrap();
-    deptree.set_local(&logical_root);
+    let mut deptree = DepTreeManager::new(ops, rustcs[0])?;
 
     let used_rufs = deptree.extract_rufs()?;
-    if let Some(root_used_rufs) = used_rufs.get(&format!("{name}@{ver}")) {
-        for rustv in (0..64).rev() {
+    let graph = deptree.get_graph();
+    let mut root_used_rufs = Vec::new();
+    let mut roots = Vec::new();
+    for nx in deptree.get_local_nodes() {
+        let name_ver = format!("{}@{}", graph[nx].name, graph[nx].version);
+        if let Some(rufs) = used_rufs.get(&name_ver) {
+            root_used_rufs.push((graph[nx].name.to_string(), rufs.clone()));
+            roots.push(name_ver);
+        }
+    }
+
+    if !root_used_rufs.is_empty() {
+        for &rustv in rustcs.iter() {
             deptree.switch_rustv(rustv);
-            let issue_rufs = deptree.filter_rufs(root_used_rufs.iter().collect());
+            let issue_rufs = root_used_rufs
+                .iter()
+                .flat_map(|(name, rufs)| deptree.filter_rufs(name, rufs.iter().collect()))
+                .collect::<Vec<_>>();
+            observer.on_event(AuditEvent::RootChecked {
+                rustv,
+                roots: roots.clone(),
+                rufs: issue_rufs.iter().map(|ruf| ruf.to_string()).collect(),
+            });
             if issue_rufs.is_empty() {
-                writeln!(
-                    debugger,
-                    "[Root Audit] Rustc {} fixed root crate {}@{}",
-                    rustv, name, ver
-                )
-                .unwrap();
-
                 return Ok(rustv);
-            } else {
-                writeln!(
-                    debugger,
-                    "[Root Audit] Rustc {} cannot fix root crate {}@{} due to {:?}",
-                    rustv, name, ver, issue_rufs
-                )
-                .unwrap();
             }
         }
 
-        return Err(AuditError::FunctionError(None, None));
+        Err(AuditError::FunctionError(None, None))
     } else {
-        writeln!(
-            debugger,
-            "[Root Audit] No rufs found for root crate: {}@{}",
-            name, ver
-        )
-        .unwrap();
+        observer.on_event(AuditEvent::RootChecked {
+            rustv: rustcs[0],
+            roots,
+            rufs: Vec::new(),
+        });
 
-        return Ok(63);
+        Ok(rustcs[0])
     }
 }
 
 #[test]
+#[ignore = "needs the ruf database in PostgreSQL and a local audit workspace"]
 fn test_audit() {
+    use super::ops::DepOpsVirt;
+    use crate::core::TextObserver;
     use std::sync::{Arc, Mutex};
 
     const WORKSPACE_PATH: &str = "/home/ubuntu/Workspaces/Cargo-Ecosystem-Monitor/Code/cargo_ruf/ruf_audit_virtual/virt_work";
     let stdout = Arc::new(Mutex::new(std::io::stdout()));
     let mut buffer = stdout.lock().unwrap();
+    let options = AuditOptions::default();
+    let mut observer = TextObserver(&mut *buffer);
 
-    let res = root_audit("capnp", "0.0.2", WORKSPACE_PATH, &mut *buffer);
-
-    println!("RESULTS: {:?}", res);
+    let _ = root_audit(
+        DepOpsVirt::new("capnp", "0.0.2", WORKSPACE_PATH).unwrap(),
+        &options,
+        &mut observer,
+    );
 }
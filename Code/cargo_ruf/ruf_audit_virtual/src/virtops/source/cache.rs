@@ -0,0 +1,247 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+
+use fxhash::{FxHashMap, FxHashSet};
+use semver::Version;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::{DepInfo, MetaSource};
+use crate::basic::{CondRufs, Timestamp};
+use crate::core::AuditError;
+
+/// Tells temp files written by different threads apart.
+static TEMP_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Our subdirectory in the cache directory, nothing outside it is touched.
+const NAMESPACE: &str = "ruf_audit_cache";
+/// Marks the snapshot directories created by us, only these are pruned.
+const MARKER: &str = ".ruf_audit_snapshot";
+/// Snapshots not opened for this long are pruned, so that workers still using them keep them.
+const PRUNE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Crate metas cached on disk, shared by audits and workers (threads or processes) using the
+/// same cache directory. Entries are kept under the snapshot of the source, so a new database
+/// dump invalidates them. Opening the cache marks its snapshot as used, and removes the other
+/// snapshots not used for a day.
+///
+/// Layout: `<dir>/ruf_audit_cache/<snapshot>/rufs/<name>.json`,
+/// `<dir>/ruf_audit_cache/<snapshot>/times/<name>.json`,
+/// `<dir>/ruf_audit_cache/<snapshot>/yanked/<name>.json`
+/// and `<dir>/ruf_audit_cache/<snapshot>/deps/<name>@<ver>.json`, along with the marker
+/// `<dir>/ruf_audit_cache/<snapshot>/.ruf_audit_snapshot`. Only marked snapshots in our
+/// subdirectory are ever removed.
+pub struct CachedSource<S: MetaSource> {
+    source: S,
+    snapshot: String,
+    /// The directory of the current snapshot.
+    snapshot_path: PathBuf,
+}
+
+impl<S: MetaSource> CachedSource<S> {
+    /// Cache under the snapshot reported by the source, see [`MetaSource::snapshot`].
+    pub fn new(source: S, dir: &str) -> Result<Self, AuditError> {
+        let snapshot = source.snapshot().map_err(AuditError::InnerError)?;
+        Self::with_snapshot(source, dir, &snapshot)
+    }
+
+    /// Cache under the given snapshot, e.g. the checkout date of an index.
+    pub fn with_snapshot(source: S, dir: &str, snapshot: &str) -> Result<Self, AuditError> {
+        let dir = PathBuf::from(dir).join(NAMESPACE);
+        let snapshot = sanitize(snapshot);
+        let snapshot_path = dir.join(&snapshot);
+
+        for sub in ["rufs", "times", "yanked", "deps"] {
+            fs::create_dir_all(snapshot_path.join(sub))
+                .map_err(|e| AuditError::InnerError(e.to_string()))?;
+        }
+        // Rewritten to tell the snapshot is used.
+        fs::write(snapshot_path.join(MARKER), &snapshot)
+            .map_err(|e| AuditError::InnerError(e.to_string()))?;
+
+        prune(&dir, &snapshot, SystemTime::now());
+
+        Ok(Self {
+            source,
+            snapshot,
+            snapshot_path,
+        })
+    }
+
+    fn load<T: DeserializeOwned>(path: &Path) -> Option<T> {
+        let content = fs::read_to_string(path).ok()?;
+        // Broken entries are queried again and overwritten.
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Write to a temp file first, and then rename it, so readers never see partial entries.
+    fn store<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+        let content = serde_json::to_string(value).map_err(|e| e.to_string())?;
+        let temp = path.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            TEMP_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        fs::write(&temp, content).map_err(|e| e.to_string())?;
+        fs::rename(&temp, path).map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            e.to_string()
+        })
+    }
+}
+
+impl<S: MetaSource> MetaSource for CachedSource<S> {
+    fn get_version_rufs(&self, name: &str) -> Result<FxHashMap<Version, CondRufs>, String> {
+        let path = self
+            .snapshot_path
+            .join("rufs")
+            .join(format!("{}.json", sanitize(name)));
+        if let Some(cads) = Self::load(&path) {
+            return Ok(cads);
+        }
+
+        let cads = self.source.get_version_rufs(name)?;
+        Self::store(&path, &cads)?;
+
+        Ok(cads)
+    }
+
+    fn get_version_deps(&self, name: &str, ver: &str) -> Result<Vec<DepInfo>, String> {
+        let path = self.snapshot_path.join("deps").join(format!(
+            "{}@{}.json",
+            sanitize(name),
+            sanitize(ver)
+        ));
+        if let Some(deps) = Self::load(&path) {
+            return Ok(deps);
+        }
+
+        let deps = self.source.get_version_deps(name, ver)?;
+        Self::store(&path, &deps)?;
+
+        Ok(deps)
+    }
+
+    fn get_publish_times(&self, name: &str) -> Result<FxHashMap<Version, Timestamp>, String> {
+        let path = self
+            .snapshot_path
+            .join("times")
+            .join(format!("{}.json", sanitize(name)));
+        if let Some(times) = Self::load(&path) {
+            return Ok(times);
+        }
+
+        let times = self.source.get_publish_times(name)?;
+        Self::store(&path, &times)?;
+
+        Ok(times)
+    }
+
+    fn get_yanked(&self, name: &str) -> Result<FxHashSet<Version>, String> {
+        let path = self
+            .snapshot_path
+            .join("yanked")
+            .join(format!("{}.json", sanitize(name)));
+        if let Some(yanked) = Self::load(&path) {
+            return Ok(yanked);
+        }
+
+        let yanked = self.source.get_yanked(name)?;
+        Self::store(&path, &yanked)?;
+
+        Ok(yanked)
+    }
+
+    fn snapshot(&self) -> Result<String, String> {
+        Ok(self.snapshot.clone())
+    }
+}
+
+/// Remove the snapshots not used for [`PRUNE_AFTER`] but the current one. Other workers may be
+/// removing them too, so failures are ignored.
+fn prune(dir: &Path, current: &str, now: SystemTime) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_name() == current {
+            continue;
+        }
+
+        // Unmarked entries are not ours.
+        let Ok(used) = fs::metadata(entry.path().join(MARKER)).and_then(|meta| meta.modified())
+        else {
+            continue;
+        };
+        if now
+            .duration_since(used)
+            .is_ok_and(|idle| idle > PRUNE_AFTER)
+        {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// Keep keys usable as file names.
+fn sanitize(key: &str) -> String {
+    key.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | '+' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[test]
+fn test_prune() {
+    use std::fs::File;
+
+    struct NoSource;
+    impl MetaSource for NoSource {
+        fn get_version_rufs(&self, name: &str) -> Result<FxHashMap<Version, CondRufs>, String> {
+            Err(format!("no {name}"))
+        }
+        fn get_version_deps(&self, name: &str, _ver: &str) -> Result<Vec<DepInfo>, String> {
+            Err(format!("no {name}"))
+        }
+    }
+
+    let dir = std::env::temp_dir().join(format!("ruf_audit_cache_test_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let ours = dir.join(NAMESPACE);
+    let used_long_ago = SystemTime::now() - 2 * PRUNE_AFTER;
+
+    // Others' data, in the cache directory and even in our subdirectory.
+    fs::create_dir_all(dir.join("other")).unwrap();
+    fs::write(dir.join("other").join(MARKER), "").unwrap();
+    File::options()
+        .write(true)
+        .open(dir.join("other").join(MARKER))
+        .unwrap()
+        .set_modified(used_long_ago)
+        .unwrap();
+    fs::create_dir_all(ours.join("unmarked")).unwrap();
+
+    // Snapshots of other workers, one still in use and one outdated.
+    let live = CachedSource::with_snapshot(NoSource, dir.to_str().unwrap(), "live").unwrap();
+    CachedSource::with_snapshot(NoSource, dir.to_str().unwrap(), "old").unwrap();
+    File::options()
+        .write(true)
+        .open(ours.join("old").join(MARKER))
+        .unwrap()
+        .set_modified(used_long_ago)
+        .unwrap();
+
+    CachedSource::with_snapshot(NoSource, dir.to_str().unwrap(), "new").unwrap();
+    assert!(dir.join("other").exists());
+    assert!(ours.join("unmarked").exists());
+    assert!(ours.join("live").join("rufs").exists());
+    assert!(!ours.join("old").exists());
+    assert!(ours.join("new").join("deps").exists());
+    assert_eq!(live.snapshot().unwrap(), "live");
+
+    let _ = fs::remove_dir_all(&dir);
+}
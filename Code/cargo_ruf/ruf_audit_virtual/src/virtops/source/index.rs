@@ -0,0 +1,416 @@
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use fxhash::{FxHashMap, FxHashSet, FxHasher};
+use semver::{Version, VersionReq};
+use serde::Deserialize;
+
+use super::{DepInfo, DepKind, MetaSource};
+use crate::basic::{CondRuf, CondRufs, Timestamp};
+use crate::core::AuditError;
+
+/*
+    -- The ruf dump can be exported from the `version_ruf` table --
+    \copy (SELECT name, num, conds, feature FROM version_ruf) TO 'version_ruf.csv' WITH CSV HEADER
+*/
+
+/// One line in the crates.io-index.
+#[derive(Deserialize)]
+struct IndexEntry {
+    vers: String,
+    deps: Vec<IndexDep>,
+    #[serde(default)]
+    yanked: bool,
+    /// Only recorded by newer crates.io-index checkouts.
+    #[serde(default)]
+    pubtime: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct IndexDep {
+    name: String,
+    req: String,
+    kind: Option<String>,
+    /// Set when the dependency is renamed, and `name` is the renamed one.
+    package: Option<String>,
+    target: Option<String>,
+}
+
+/// One row in the ruf dump.
+#[derive(Deserialize)]
+struct RufRecord {
+    name: String,
+    num: String,
+    conds: Option<String>,
+    feature: String,
+}
+
+/// One version in the crates.io-index, as we use it.
+struct IndexVersion {
+    deps: Vec<DepInfo>,
+    yanked: bool,
+    pubtime: Option<Timestamp>,
+}
+
+/// Crate metas from a local crates.io-index checkout and a ruf usage dump,
+/// so that audits can run without our PostgreSQL databases.
+///
+/// Candidates are the versions in both, as the rufs of versions missing from the dump are not
+/// known. Publish times come from the `pubtime` of the index entries, which older checkouts do not
+/// record, and the snapshot from the commit checked out along with the content of the dump.
+pub struct IndexSource {
+    index_path: PathBuf,
+
+    /// Ruf usages of all crates, loaded from the dump.
+    rufs: FxHashMap<String, FxHashMap<Version, CondRufs>>,
+    /// Hash of the dump content, for the snapshot.
+    dump_hash: u64,
+    /// Parsed index files, by crate and version.
+    /// Behind a lock, so that the source can be shared by threads.
+    deps_cache: Mutex<FxHashMap<String, FxHashMap<String, IndexVersion>>>,
+}
+
+impl IndexSource {
+    /// The index shall be checked out to the same date of the ruf dump.
+    pub fn new(index: &str, ruf_dump: &str) -> Result<Self, AuditError> {
+        let index_path = PathBuf::from(index);
+        if !index_path.join("config.json").exists() {
+            return Err(AuditError::InnerError(format!(
+                "{} is not a crates.io-index checkout",
+                index
+            )));
+        }
+
+        let dump = fs::read(ruf_dump)
+            .map_err(|e| AuditError::InnerError(format!("cannot read {ruf_dump}: {e}")))?;
+        let rufs = Self::load_ruf_dump(&dump).map_err(AuditError::InnerError)?;
+        let mut hasher = FxHasher::default();
+        hasher.write(&dump);
+
+        Ok(Self {
+            index_path,
+            rufs,
+            dump_hash: hasher.finish(),
+            deps_cache: Mutex::new(FxHashMap::default()),
+        })
+    }
+
+    fn load_ruf_dump(
+        dump: &[u8],
+    ) -> Result<FxHashMap<String, FxHashMap<Version, CondRufs>>, String> {
+        let mut reader = csv::Reader::from_reader(dump);
+
+        let mut rufs: FxHashMap<String, FxHashMap<Version, CondRufs>> = FxHashMap::default();
+        for record in reader.deserialize() {
+            let record: RufRecord = record.map_err(|e| e.to_string())?;
+            let ver = Version::parse(&record.num).map_err(|e| {
+                format!(
+                    "Version parse failure, invalid version: {} {}",
+                    record.num, e
+                )
+            })?;
+
+            let entry = rufs
+                .entry(record.name)
+                .or_default()
+                .entry(ver)
+                .or_insert_with(CondRufs::empty);
+
+            if record.feature != "no_feature_used" {
+                entry.push(CondRuf {
+                    // Empty conds are the same as NULL in our database.
+                    cond: record.conds.filter(|cond| !cond.is_empty()),
+                    feature: record.feature,
+                });
+            }
+        }
+
+        Ok(rufs)
+    }
+
+    /// Same layout as the crates.io-index.
+    fn index_file(&self, name: &str) -> PathBuf {
+        let name = name.to_lowercase();
+        let dir = match name.len() {
+            1 => self.index_path.join("1"),
+            2 => self.index_path.join("2"),
+            3 => self.index_path.join("3").join(&name[..1]),
+            _ => self.index_path.join(&name[..2]).join(&name[2..4]),
+        };
+
+        dir.join(name)
+    }
+
+    fn load_index_file(&self, name: &str) -> Result<FxHashMap<String, IndexVersion>, String> {
+        let file = File::open(self.index_file(name))
+            .map_err(|e| format!("No crate with name {} found in index: {}", name, e))?;
+
+        let mut versions = FxHashMap::default();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let entry: IndexEntry = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+
+            let mut deps = Vec::new();
+            for dep in entry.deps {
+                let req = VersionReq::parse(&dep.req).map_err(|e| {
+                    format!("VersionReq parse failure, invalid req: {} {}", dep.req, e)
+                })?;
+                let kind = dep.kind.as_deref().map_or(DepKind::Normal, DepKind::from);
+
+                deps.push(DepInfo {
+                    name: dep.package.unwrap_or(dep.name),
+                    req,
+                    kind,
+                    target: dep.target,
+                });
+            }
+
+            let pubtime = entry.pubtime.as_deref().map(str::parse).transpose()?;
+            versions.insert(
+                entry.vers,
+                IndexVersion {
+                    deps,
+                    yanked: entry.yanked,
+                    pubtime,
+                },
+            );
+        }
+
+        Ok(versions)
+    }
+
+    fn ensure_index_file(&self, name: &str) -> Result<(), String> {
+        if !self.deps_cache.lock().unwrap().contains_key(name) {
+            let versions = self.load_index_file(name)?;
+            self.deps_cache
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), versions);
+        }
+
+        Ok(())
+    }
+
+    /// The commit checked out in the index, read from its git directory.
+    fn index_head(&self) -> Result<String, String> {
+        let git_dir = self.index_path.join(".git");
+        let read = |path: &Path| {
+            fs::read_to_string(path)
+                .map_err(|e| format!("cannot read the index head {}: {e}", path.display()))
+        };
+
+        let head = read(&git_dir.join("HEAD"))?;
+        let Some(head_ref) = head.trim().strip_prefix("ref: ") else {
+            return Ok(head.trim().to_string());
+        };
+        if let Ok(commit) = fs::read_to_string(git_dir.join(head_ref)) {
+            return Ok(commit.trim().to_string());
+        }
+
+        // Refs may be packed.
+        read(&git_dir.join("packed-refs"))?
+            .lines()
+            .find_map(|line| {
+                line.split_once(' ')
+                    .filter(|(_, name)| *name == head_ref)
+                    .map(|(commit, _)| commit.to_string())
+            })
+            .ok_or(format!("cannot find the index head {head_ref}"))
+    }
+}
+
+impl MetaSource for IndexSource {
+    /// Versions missing from the index are dropped, crates missing there have none.
+    fn get_version_rufs(&self, name: &str) -> Result<FxHashMap<Version, CondRufs>, String> {
+        let Some(rufs) = self.rufs.get(name) else {
+            return Ok(FxHashMap::default());
+        };
+        if self.ensure_index_file(name).is_err() {
+            return Ok(FxHashMap::default());
+        }
+
+        let cache = self.deps_cache.lock().unwrap();
+        let versions = &cache[name];
+        Ok(rufs
+            .iter()
+            .filter(|(ver, _)| versions.contains_key(&ver.to_string()))
+            .map(|(ver, condrufs)| (ver.clone(), condrufs.clone()))
+            .collect())
+    }
+
+    /// Yanked ones are told by the index, crates missing there have none.
+    fn get_yanked(&self, name: &str) -> Result<FxHashSet<Version>, String> {
+        if self.ensure_index_file(name).is_err() {
+            return Ok(FxHashSet::default());
+        }
+
+        Ok(self.deps_cache.lock().unwrap()[name]
+            .iter()
+            .filter(|(_, version)| version.yanked)
+            .filter_map(|(ver, _)| Version::parse(ver).ok())
+            .collect())
+    }
+
+    /// Only checkouts recording `pubtime` have them, versions without it are left out.
+    fn get_publish_times(&self, name: &str) -> Result<FxHashMap<Version, Timestamp>, String> {
+        self.ensure_index_file(name)?;
+
+        let cache = self.deps_cache.lock().unwrap();
+        let versions = &cache[name];
+        let times = versions
+            .iter()
+            .filter_map(|(ver, version)| Some((Version::parse(ver).ok()?, version.pubtime?)))
+            .collect::<FxHashMap<_, _>>();
+        if times.is_empty() && !versions.is_empty() {
+            return Err(format!(
+                "no publish times of {name} in the index, check out a newer one"
+            ));
+        }
+
+        Ok(times)
+    }
+
+    /// The index commit and the dump content, so updating either invalidates caches.
+    fn snapshot(&self) -> Result<String, String> {
+        let head = self.index_head()?;
+        Ok(format!(
+            "index-{}-dump-{:016x}",
+            &head[..head.len().min(12)],
+            self.dump_hash
+        ))
+    }
+
+    fn get_version_deps(&self, name: &str, ver: &str) -> Result<Vec<DepInfo>, String> {
+        self.ensure_index_file(name)?;
+
+        self.deps_cache
+            .lock()
+            .unwrap()
+            .get(name)
+            .and_then(|versions| versions.get(ver))
+            .map(|version| version.deps.clone())
+            .ok_or(format!("No version with namever {}-{} found", name, ver))
+    }
+}
+
+#[test]
+fn test_index_source() {
+    use crate::basic::Date;
+
+    let dir = std::env::temp_dir().join(format!("ruf_audit_index_test_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let index = dir.join("index");
+    fs::create_dir_all(index.join("3").join("f")).unwrap();
+    fs::create_dir_all(index.join("se").join("rd")).unwrap();
+    fs::create_dir_all(index.join(".git").join("refs").join("heads")).unwrap();
+    fs::write(index.join("config.json"), "{}").unwrap();
+    fs::write(index.join(".git").join("HEAD"), "ref: refs/heads/master\n").unwrap();
+    fs::write(
+        index.join(".git").join("packed-refs"),
+        "# pack-refs with: peeled\n0123456789abcdef0123456789abcdef01234567 refs/heads/master\n",
+    )
+    .unwrap();
+
+    // Old entries without pubtime, a yanked one, and a renamed target specific dependency.
+    fs::write(
+        index.join("3").join("f").join("foo"),
+        r#"{"name":"foo","vers":"0.1.0","deps":[],"cksum":"","features":{}}
+{"name":"foo","vers":"0.2.0","deps":[{"name":"ser","package":"serde","req":"^1.0","features":[],"optional":false,"default_features":true,"target":"cfg(unix)","kind":"normal"}],"cksum":"","features":{},"yanked":true,"pubtime":"2021-06-01T12:00:00Z"}
+{"name":"foo","vers":"0.3.0","deps":[{"name":"cc","req":"^1","features":[],"optional":false,"default_features":true,"target":null,"kind":"build"}],"cksum":"","features":{},"pubtime":"2022-01-01T00:00:00Z"}
+"#,
+    )
+    .unwrap();
+    fs::write(
+        index.join("se").join("rd").join("serde"),
+        r#"{"name":"serde","vers":"1.0.0","deps":[],"cksum":"","features":{}}
+"#,
+    )
+    .unwrap();
+
+    // Foo 0.4.0 is newer than the index, and bar is not in it.
+    let dump = dir.join("version_ruf.csv");
+    fs::write(
+        &dump,
+        "name,num,conds,feature\n\
+         foo,0.1.0,,no_feature_used\n\
+         foo,0.2.0,,specialization\n\
+         foo,0.2.0,feature = \"nightly\",doc_cfg\n\
+         foo,0.3.0,,no_feature_used\n\
+         foo,0.4.0,,no_feature_used\n\
+         bar,1.0.0,,no_feature_used\n\
+         serde,1.0.0,,no_feature_used\n",
+    )
+    .unwrap();
+
+    assert!(IndexSource::new(dir.to_str().unwrap(), dump.to_str().unwrap()).is_err());
+    let source = IndexSource::new(index.to_str().unwrap(), dump.to_str().unwrap()).unwrap();
+    let v = |s: &str| Version::parse(s).unwrap();
+
+    let cads = source.get_version_rufs("foo").unwrap();
+    let mut versions = cads.keys().cloned().collect::<Vec<_>>();
+    versions.sort();
+    assert_eq!(versions, vec![v("0.1.0"), v("0.2.0"), v("0.3.0")]);
+    assert!(cads[&v("0.1.0")].borrow().is_empty());
+    let rufs = cads[&v("0.2.0")].borrow();
+    assert_eq!(rufs.len(), 2);
+    assert_eq!(rufs[1].cond.as_deref(), Some("feature = \"nightly\""));
+    assert!(source.get_version_rufs("bar").unwrap().is_empty());
+    assert!(source.get_version_rufs("baz").unwrap().is_empty());
+
+    let deps = source.get_version_deps("foo", "0.2.0").unwrap();
+    assert_eq!(deps.len(), 1);
+    assert_eq!(deps[0].name, "serde");
+    assert_eq!(deps[0].kind, DepKind::Normal);
+    assert_eq!(deps[0].target.as_deref(), Some("cfg(unix)"));
+    assert_eq!(
+        source.get_version_deps("foo", "0.3.0").unwrap()[0].kind,
+        DepKind::Build
+    );
+    assert!(source.get_version_deps("foo", "0.4.0").is_err());
+
+    assert_eq!(
+        source.get_yanked("foo").unwrap(),
+        [v("0.2.0")].into_iter().collect()
+    );
+    assert!(source.get_yanked("bar").unwrap().is_empty());
+
+    let times = source.get_publish_times("foo").unwrap();
+    assert_eq!(times.len(), 2);
+    assert_eq!(
+        times[&v("0.2.0")],
+        Timestamp::new(Date::new(2021, 6, 1), 12 * 3600)
+    );
+    assert!(source.get_publish_times("serde").is_err());
+
+    // Packed and loose refs, and a new dump changes the snapshot.
+    let snapshot = source.snapshot().unwrap();
+    assert!(
+        snapshot.starts_with("index-0123456789ab-dump-"),
+        "{snapshot}"
+    );
+    fs::write(
+        index.join(".git").join("refs").join("heads").join("master"),
+        "fedcba9876543210fedcba9876543210fedcba98\n",
+    )
+    .unwrap();
+    assert!(source
+        .snapshot()
+        .unwrap()
+        .starts_with("index-fedcba987654-dump-"));
+    fs::write(
+        &dump,
+        "name,num,conds,feature\nfoo,0.1.0,,no_feature_used\n",
+    )
+    .unwrap();
+    let updated = IndexSource::new(index.to_str().unwrap(), dump.to_str().unwrap()).unwrap();
+    assert_ne!(updated.snapshot().unwrap(), source.snapshot().unwrap());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
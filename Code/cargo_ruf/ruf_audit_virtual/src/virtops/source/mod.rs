@@ -0,0 +1,154 @@
+mod cache;
+mod index;
+mod pg;
+
+pub use cache::CachedSource;
+pub use index::IndexSource;
+pub use pg::{PgSource, DEFAULT_DB};
+
+use std::collections::hash_map::Entry;
+use std::sync::Arc;
+
+use fxhash::{FxHashMap, FxHashSet};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::basic::{CfgProfile, CondRufs, Timestamp};
+
+/// Dependency kinds, same as the `kind` column in crates.io `dependencies` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DepKind {
+    Normal,
+    Build,
+    Dev,
+}
+
+/// One dependency entry declared by a crate version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepInfo {
+    /// The real crate name, renames are already stripped.
+    pub name: String,
+    pub req: VersionReq,
+    pub kind: DepKind,
+    /// The platform of target specific dependencies, `cfg(..)` or a target triple.
+    pub target: Option<String>,
+}
+
+/// Where the crate metas and ruf usages come from.
+pub trait MetaSource {
+    /// Get all versions of a crate, along with their conditional ruf usages.
+    fn get_version_rufs(&self, name: &str) -> Result<FxHashMap<Version, CondRufs>, String>;
+    /// Get the declared dependencies of a crate version.
+    fn get_version_deps(&self, name: &str, ver: &str) -> Result<Vec<DepInfo>, String>;
+    /// Get the publish times of all versions of a crate, for audits as of an instant.
+    fn get_publish_times(&self, name: &str) -> Result<FxHashMap<Version, Timestamp>, String> {
+        Err(format!("no publish times of {name} in the source"))
+    }
+
+    /// Get the yanked versions of a crate, registry metadata kept apart from the ruf usages.
+    /// None are yanked if the source cannot tell.
+    fn get_yanked(&self, _name: &str) -> Result<FxHashSet<Version>, String> {
+        Ok(FxHashSet::default())
+    }
+
+    /// Identify the data snapshot, which changes when the data are updated.
+    /// Used to invalidate caches, see [`CachedSource`].
+    fn snapshot(&self) -> Result<String, String> {
+        Err("the source has no snapshot, give one to the cache".to_string())
+    }
+
+    /// Get the version requirements of a crate version on the target of the cfg profile,
+    /// dev dependencies excluded. The crate may be built for build scripts too, so those applying
+    /// to the host are kept as well.
+    fn get_version_reqs(
+        &self,
+        name: &str,
+        ver: &str,
+        cfg: &CfgProfile,
+    ) -> Result<FxHashMap<String, VersionReq>, String> {
+        let deps = self.get_version_deps(name, ver)?;
+        let host = cfg.for_host();
+
+        let mut dep_reqs: FxHashMap<String, VersionReq> = FxHashMap::default();
+        for dep in deps {
+            // We DONOT care the dev dependencies, and the ones for other targets.
+            if dep.kind == DepKind::Dev
+                || dep.target.as_ref().is_some_and(|target| {
+                    !cfg.matches_platform(target) && !host.matches_platform(target)
+                })
+            {
+                continue;
+            }
+
+            // NOTICE: Shall we ignore the optional ones ?
+            match dep_reqs.entry(dep.name) {
+                Entry::Occupied(mut entry) => {
+                    // Declared more than once for the target (normal and build, or several cfgs),
+                    // the version shall meet all of them.
+                    if *entry.get() != dep.req {
+                        entry.get_mut().comparators.extend(dep.req.comparators);
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(dep.req);
+                }
+            }
+        }
+
+        Ok(dep_reqs)
+    }
+}
+
+/// Shared by the operators of several threads, see [`audit_concurrent`](super::audit_concurrent).
+impl<S: MetaSource + ?Sized> MetaSource for Arc<S> {
+    fn get_version_rufs(&self, name: &str) -> Result<FxHashMap<Version, CondRufs>, String> {
+        (**self).get_version_rufs(name)
+    }
+
+    fn get_version_deps(&self, name: &str, ver: &str) -> Result<Vec<DepInfo>, String> {
+        (**self).get_version_deps(name, ver)
+    }
+
+    fn get_publish_times(&self, name: &str) -> Result<FxHashMap<Version, Timestamp>, String> {
+        (**self).get_publish_times(name)
+    }
+
+    fn get_yanked(&self, name: &str) -> Result<FxHashSet<Version>, String> {
+        (**self).get_yanked(name)
+    }
+
+    fn snapshot(&self) -> Result<String, String> {
+        (**self).snapshot()
+    }
+
+    fn get_version_reqs(
+        &self,
+        name: &str,
+        ver: &str,
+        cfg: &CfgProfile,
+    ) -> Result<FxHashMap<String, VersionReq>, String> {
+        (**self).get_version_reqs(name, ver, cfg)
+    }
+}
+
+impl From<i32> for DepKind {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => DepKind::Normal,
+            1 => DepKind::Build,
+            2 => DepKind::Dev,
+            _ => unreachable!("Fatal, unknown dependency kind: {}", value),
+        }
+    }
+}
+
+impl From<&str> for DepKind {
+    fn from(value: &str) -> Self {
+        match value {
+            "normal" => DepKind::Normal,
+            "build" => DepKind::Build,
+            "dev" => DepKind::Dev,
+            _ => unreachable!("Fatal, unknown dependency kind: {}", value),
+        }
+    }
+}
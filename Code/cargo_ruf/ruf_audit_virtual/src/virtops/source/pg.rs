@@ -0,0 +1,223 @@
+use std::sync::Mutex;
+
+use fxhash::{FxHashMap, FxHashSet};
+use postgres::{Client, NoTls};
+use semver::{Version, VersionReq};
+
+use super::{DepInfo, DepKind, MetaSource};
+use crate::basic::{CondRuf, CondRufs, Timestamp};
+use crate::core::AuditError;
+
+/*
+    -- Currently we HAVE NOT created this table --
+    CREATE TABLE version_ruf AS
+    SELECT versions_with_name.id, versions_with_name.name, versions_with_name.num, versions_with_name.crate_id, version_feature.conds, version_feature.feature
+    FROM versions_with_name
+    JOIN version_feature
+    ON versions_with_name.id = version_feature.id
+
+    -- We have to strip empty cond('') to NULL --
+    UPDATE version_ruf SET conds = NULL WHERE conds = ''
+
+    -- And also the dependencies table --
+    CREATE VIEW dependencies_with_name AS
+    SELECT dependencies.*, crates.name AS crate_name
+    FROM dependencies
+    JOIN crates
+    ON dependencies.crate_id = crates.id
+*/
+
+/// The default connection to our database.
+pub const DEFAULT_DB: &str = "host=localhost dbname=crates user=postgres password=postgres";
+
+/// Crate metas from our PostgreSQL databases.
+pub struct PgSource {
+    /// For our database connection.
+    conn: Mutex<Client>,
+}
+
+impl PgSource {
+    pub fn new(db: &str) -> Result<Self, AuditError> {
+        let client =
+            Client::connect(db, NoTls).map_err(|e| AuditError::InnerError(e.to_string()))?;
+
+        Ok(Self {
+            conn: Mutex::new(client),
+        })
+    }
+
+    #[allow(unused)]
+    fn get_crate_id_with_name(&self, crate_name: &str) -> Result<i32, String> {
+        let crate_id = self
+            .conn
+            .lock()
+            .unwrap()
+            .query(
+                "SELECT id FROM crates WHERE name = $1 LIMIT 1",
+                &[&crate_name],
+            )
+            .map_err(|e| e.to_string())?;
+
+        if crate_id.is_empty() {
+            return Err(format!("No crate with name {} found", crate_name));
+        }
+
+        Ok(crate_id[0].get::<usize, i32>(0))
+    }
+
+    fn get_version_id_with_name_ver(&self, crate_name: &str, version: &str) -> Result<i32, String> {
+        let version_id = self
+            .conn
+            .lock()
+            .unwrap()
+            .query(
+                "SELECT id FROM versions_with_name WHERE name = $1 AND num = $2 LIMIT 1",
+                &[&crate_name, &version],
+            )
+            .map_err(|e| e.to_string())?;
+
+        if version_id.is_empty() {
+            return Err(format!(
+                "No version with namever {}-{} found",
+                crate_name, version
+            ));
+        }
+
+        Ok(version_id[0].get::<usize, i32>(0))
+    }
+
+    fn get_cads_with_crate_name(&self, name: &str) -> Result<FxHashMap<Version, CondRufs>, String> {
+        let rows = self
+            .conn
+            .lock()
+            .unwrap()
+            .query(
+                "SELECT num, conds, feature FROM version_ruf WHERE name = $1",
+                &[&name],
+            )
+            .expect("Fatal, db query failed");
+
+        let mut dep_rufs = FxHashMap::default();
+        for row in rows {
+            let ver = row.get::<_, String>(0);
+            let ver = Version::parse(&ver)
+                .map_err(|e| format!("Version parse failure, invalid version: {} {}", ver, e))?;
+
+            let entry = dep_rufs.entry(ver).or_insert_with(CondRufs::empty);
+
+            let cond = row.get::<_, Option<String>>(1);
+            let ruf = row.get::<_, String>(2);
+
+            if ruf != "no_feature_used" {
+                entry.push(CondRuf { cond, feature: ruf });
+            }
+        }
+
+        Ok(dep_rufs)
+    }
+
+    fn get_deps_with_version_id(&self, version_id: i32) -> Result<Vec<DepInfo>, String> {
+        let rows = self
+            .conn
+            .lock()
+            .unwrap()
+            .query(
+                "SELECT crate_name, req, kind, target FROM dependencies_with_name WHERE version_id = $1",
+                &[&version_id],
+            )
+            .expect("Fatal, db query failed");
+
+        let mut deps = Vec::new();
+        for row in rows {
+            let name = row.get::<_, String>(0);
+            let req = row.get::<_, String>(1);
+            let req = VersionReq::parse(&req)
+                .map_err(|e| format!("VersionReq parse failure, invalid req: {} {}", req, e))?;
+            let kind = DepKind::from(row.get::<_, i32>(2));
+            let target = row.get::<_, Option<String>>(3);
+
+            deps.push(DepInfo {
+                name,
+                req,
+                kind,
+                target,
+            });
+        }
+
+        Ok(deps)
+    }
+
+    fn get_times_with_crate_name(
+        &self,
+        name: &str,
+    ) -> Result<FxHashMap<Version, Timestamp>, String> {
+        let rows = self
+            .conn
+            .lock()
+            .unwrap()
+            .query(
+                "SELECT num, created_at::TEXT FROM versions_with_name WHERE name = $1",
+                &[&name],
+            )
+            .map_err(|e| e.to_string())?;
+
+        let mut times = FxHashMap::default();
+        for row in rows {
+            let ver = row.get::<_, String>(0);
+            let ver = Version::parse(&ver)
+                .map_err(|e| format!("Version parse failure, invalid version: {} {}", ver, e))?;
+            let time = row.get::<_, String>(1).parse::<Timestamp>()?;
+
+            times.insert(ver, time);
+        }
+
+        Ok(times)
+    }
+}
+
+impl MetaSource for PgSource {
+    fn get_version_rufs(&self, name: &str) -> Result<FxHashMap<Version, CondRufs>, String> {
+        self.get_cads_with_crate_name(name)
+    }
+
+    fn get_version_deps(&self, name: &str, ver: &str) -> Result<Vec<DepInfo>, String> {
+        let version_id = self.get_version_id_with_name_ver(name, ver)?;
+        self.get_deps_with_version_id(version_id)
+    }
+
+    fn get_publish_times(&self, name: &str) -> Result<FxHashMap<Version, Timestamp>, String> {
+        self.get_times_with_crate_name(name)
+    }
+
+    /// Yanked ones are told by the versions table.
+    fn get_yanked(&self, name: &str) -> Result<FxHashSet<Version>, String> {
+        let rows = self
+            .conn
+            .lock()
+            .unwrap()
+            .query(
+                "SELECT num FROM versions_with_name WHERE name = $1 AND yanked",
+                &[&name],
+            )
+            .map_err(|e| e.to_string())?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| Version::parse(&row.get::<_, String>(0)).ok())
+            .collect())
+    }
+
+    /// The newest version in our databases, which tells the crates.io dump imported.
+    fn snapshot(&self) -> Result<String, String> {
+        let rows = self
+            .conn
+            .lock()
+            .unwrap()
+            .query("SELECT MAX(created_at)::TEXT FROM versions", &[])
+            .map_err(|e| e.to_string())?;
+
+        rows.first()
+            .and_then(|row| row.get::<_, Option<String>>(0))
+            .ok_or("No versions found".to_string())
+    }
+}
@@ -1,147 +1,116 @@
-use std::{fmt::Debug, io::Write};
-
 use cargo_lock::dependency::graph::NodeIndex;
 use fxhash::FxHashMap;
 use petgraph::visit;
 use semver::Version;
 
-use super::ops::DepOpsVirt;
-use crate::core::{AuditError, DepTreeManager};
-
-pub struct Summary {
-    pub fix_rustv: i32,
-    pub fix_deps: FxHashMap<String, Vec<(String, Version, Version, Vec<String>)>>,
-}
-
-impl Debug for Summary {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Summary")
-            .field("fix_rustv", &self.fix_rustv)
-            .field(
-                "fix_deps",
-                &self
-                    .fix_deps
-                    .iter()
-                    .map(|(name_ver, deps)| {
-                        (
-                            name_ver,
-                            deps.iter()
-                                .map(|(name, ver, fix_ver, feats)| {
-                                    (name, ver.to_string(), fix_ver.to_string(), feats)
-                                })
-                                .collect::<Vec<_>>(),
-                        )
-                    })
-                    .collect::<FxHashMap<_, _>>(),
-            )
-            .finish()
-    }
-}
+use super::options::AuditOptions;
+use super::report::{self, FixStep, RustcDecision, Summary};
+use crate::basic::Toolchain;
+use crate::core::{AuditError, AuditEvent, AuditObserver, DepOps, DepTreeManager, VersionChange};
 
 /// The main audit function.
-/// The debugger receives an output stream to write debug information.
+/// The observer receives the events of the audit.
+/// Only the newest rustc in options is checked.
 pub fn treeonly_audit(
-    name: &str,
-    ver: &str,
-    workspace: &str,
-    debugger: &mut impl Write,
+    mut ops: impl DepOps,
+    options: &AuditOptions,
+    observer: &mut impl AuditObserver,
 ) -> Result<Summary, AuditError> {
-    // Init a tree first
-    let ops = DepOpsVirt::new(name, ver, workspace)?;
-    let mut deptree = DepTreeManager::new(ops, 63)?;
-    let logical_root = deptree
-        .get_graph()
-        .neighbors(deptree.get_root())
-        .next()
-        .unwrap();
-    deptree.set_local(&logical_root);
-
-    check_fix(deptree, debugger)
+    options.setup(&mut ops);
+    let rustc = options.rustc_indexes()?[0];
+    // Init a tree first, locals are set by the operators.
+    let mut deptree = DepTreeManager::new(ops, rustc)?;
+    deptree.set_objective(options.objective);
+    deptree.set_yank_policy(options.yank_policy);
+
+    check_fix(deptree, options, rustc, observer)
 }
 
 fn check_fix(
-    mut deptree: DepTreeManager<DepOpsVirt>,
-    debugger: &mut impl Write,
+    mut deptree: DepTreeManager<impl DepOps>,
+    options: &AuditOptions,
+    rustc: u32,
+    observer: &mut impl AuditObserver,
 ) -> Result<Summary, AuditError> {
-    let rustc = 63;
-    writeln!(
-        debugger,
-        "[VirtAudit Debug] check_fix: Checking rustc version {}.",
-        rustc,
-    )
-    .unwrap();
+    observer.on_event(AuditEvent::CheckRustc {
+        rustv: rustc,
+        toolchain: Toolchain::from_index(rustc).to_string(),
+    });
 
-    let issue_deps = check_issue(&deptree, debugger)?;
+    let issue_deps = check_issue(&deptree, observer)?;
+    let decision = RustcDecision::new(&deptree, rustc, &issue_deps)?;
     if issue_deps.is_empty() {
-        writeln!(
-            debugger,
-            "[VirtAudit Debug] check_fix: Rustc version {} has no issues.",
-            rustc
-        )
-        .unwrap();
-        return Ok(Summary {
-            fix_rustv: rustc,
-            fix_deps: FxHashMap::default(),
-        });
+        observer.on_event(AuditEvent::RustcNoIssue { rustv: rustc });
+        return report::summarize(
+            &deptree,
+            options,
+            rustc as i32,
+            FxHashMap::default(),
+            vec![decision],
+        );
     }
 
     let first_issue = issue_deps.first().cloned().unwrap();
-    match check_fixable(&mut deptree, issue_deps, debugger) {
-        Ok(mut fixes) => match try_fix(&mut deptree, first_issue, fixes.remove(0), debugger) {
+    match check_fixable(&mut deptree, issue_deps, observer) {
+        Ok(mut fixes) => match try_fix(&mut deptree, first_issue, fixes.remove(0), observer) {
             Ok(fix_deps) => {
-                writeln!(
-                    debugger,
-                    "[VirtAudit Debug] check_fix: Rustc version {} issues fixed.",
-                    rustc,
-                )
-                .unwrap();
-                return Ok(Summary {
-                    fix_rustv: rustc,
+                observer.on_event(AuditEvent::RustcFixed { rustv: rustc });
+                report::summarize(
+                    &deptree,
+                    options,
+                    rustc as i32,
                     fix_deps,
-                });
+                    vec![decision],
+                )
             }
             Err(e) => {
-                writeln!(debugger,
-                        "[VirtAudit Debug] check_fix: Fix failure for rustc version {} with issue: {:?}", rustc, e).unwrap();
-                return Err(e);
+                observer.on_event(AuditEvent::RustcFailed {
+                    rustv: rustc,
+                    error: e.describe(),
+                });
+                Err(e)
             }
         },
         Err(e) => {
-            writeln!(debugger,
-                "[VirtAudit Debug] check_fix: Rustc version {} got issues cannot be fixed with error: {:?}.", rustc, e).unwrap();
-            return Err(e);
+            observer.on_event(AuditEvent::RustcFailed {
+                rustv: rustc,
+                error: e.describe(),
+            });
+            Err(e)
         }
     }
 }
 
 fn check_issue(
-    deptree: &DepTreeManager<DepOpsVirt>,
-    debugger: &mut impl Write,
+    deptree: &DepTreeManager<impl DepOps>,
+    observer: &mut impl AuditObserver,
 ) -> Result<Vec<NodeIndex>, AuditError> {
     // Extract current used rufs.
     let used_rufs = deptree.extract_rufs()?;
 
     // We do bfs and thus fix problems up to down.
     let graph = deptree.get_graph();
-    // In virt audit, real root is the child of `root`.
-    let root = graph.neighbors(deptree.get_root()).next().unwrap();
+    // Start from all roots: the virt package, or the workspace members.
+    let roots = deptree.get_roots();
 
     // Collect all ruf issues first.
     let mut issue_deps = Vec::new();
-    let mut bfs = visit::Bfs::new(&graph, root);
+    let mut bfs = visit::Bfs::new(&graph, roots[0]);
+    for &root in roots.iter().skip(1) {
+        bfs.discovered.insert(root.index());
+        bfs.stack.push_back(root);
+    }
     while let Some(nx) = bfs.next(&graph) {
         let node = &graph[nx];
         let name_ver = format!("{}@{}", node.name, node.version);
         if let Some(rufs) = used_rufs.get(&name_ver) {
-            let issue_rufs = deptree.filter_rufs(rufs.iter().collect());
+            let issue_rufs = deptree.filter_rufs(node.name.as_str(), rufs.iter().collect());
             if !issue_rufs.is_empty() {
                 // Ok here we got issues
-                writeln!(
-                    debugger,
-                    "[VirtAudit Debug] check_issue: Found issue package {}@{} rufs: {:?}",
-                    node.name, node.version, issue_rufs
-                )
-                .unwrap();
+                observer.on_event(AuditEvent::IssueFound {
+                    package: name_ver.clone(),
+                    rufs: issue_rufs.into_iter().cloned().collect(),
+                });
                 issue_deps.push(nx);
             }
         }
@@ -151,22 +120,16 @@ fn check_issue(
 }
 
 fn check_fixable(
-    deptree: &mut DepTreeManager<DepOpsVirt>,
+    deptree: &mut DepTreeManager<impl DepOps>,
     issue_deps: Vec<NodeIndex>,
-    debugger: &mut impl Write,
+    observer: &mut impl AuditObserver,
 ) -> Result<Vec<Vec<(String, Version, Version)>>, AuditError> {
     let graph = deptree.get_graph();
     let mut fixes = Vec::new();
 
     // Check possible fix for each issue.
     for nx in issue_deps {
-        writeln!(
-            debugger,
-            "[VirtAudit Debug] check_fixable: Check {}@{} fixibility",
-            graph[nx].name, graph[nx].version,
-        )
-        .unwrap();
-        match deptree.issue_fixable(nx, debugger) {
+        match deptree.issue_fixable(nx, observer) {
             Ok(fix) => {
                 let fix = fix
                     .into_iter()
@@ -179,16 +142,17 @@ fn check_fixable(
                     })
                     .collect::<Vec<_>>();
 
-                writeln!(
-                    debugger,
-                    "[VirtAudit Debug] check_fixable: Issue dep {}@{} is fixable with {:?}, adds to fix limits.",
-                    graph[nx].name,
-                    graph[nx].version,
-                    fix.iter()
-                        .map(|(name, ver, fix_ver)| format!("{}@{} -> {}", name, ver, fix_ver))
-                        .collect::<Vec<_>>()
-                )
-                .unwrap();
+                observer.on_event(AuditEvent::Fixable {
+                    package: format!("{}@{}", graph[nx].name, graph[nx].version),
+                    changes: fix
+                        .iter()
+                        .map(|(name, ver, fix_ver)| VersionChange {
+                            name: name.clone(),
+                            from: ver.clone(),
+                            to: fix_ver.clone(),
+                        })
+                        .collect(),
+                });
 
                 // Add limits for the fix.
                 deptree.set_fix_limit(&fix);
@@ -196,12 +160,10 @@ fn check_fixable(
                 fixes.push(fix);
             }
             Err(e) => {
-                writeln!(
-                    debugger,
-                    "[VirtAudit Debug] check_fixable: Issue dep {}@{} is not fixable with error {:?}.",
-                    graph[nx].name, graph[nx].version, e
-                )
-                .unwrap();
+                observer.on_event(AuditEvent::Unfixable {
+                    package: format!("{}@{}", graph[nx].name, graph[nx].version),
+                    error: e.describe(),
+                });
                 return Err(e);
             }
         }
@@ -214,11 +176,11 @@ fn check_fixable(
 }
 
 fn try_fix(
-    deptree: &mut DepTreeManager<DepOpsVirt>,
+    deptree: &mut DepTreeManager<impl DepOps>,
     first_issue: NodeIndex,
     first_fix: Vec<(String, Version, Version)>,
-    debugger: &mut impl Write,
-) -> Result<FxHashMap<String, Vec<(String, Version, Version, Vec<String>)>>, AuditError> {
+    observer: &mut impl AuditObserver,
+) -> Result<FxHashMap<String, Vec<FixStep>>, AuditError> {
     // For loop detect.
     let mut is_first = Some((first_issue, first_fix));
     let mut fix_deps = FxHashMap::default();
@@ -231,22 +193,20 @@ fn try_fix(
         let (issue_nx, fix) = if is_first.is_some() {
             is_first.take().unwrap()
         } else {
-            let issue_nx = check_issue(deptree, debugger)?.first().cloned();
+            let issue_nx = check_issue(deptree, observer)?.first().cloned();
             if issue_nx.is_none() {
                 return Ok(fix_deps);
             }
 
             let issue_nx = issue_nx.unwrap();
 
-            let fix = match deptree.issue_fixable(issue_nx, debugger) {
+            let fix = match deptree.issue_fixable(issue_nx, observer) {
                 Ok(fix) => fix,
                 Err(e) => {
-                    writeln!(
-                        debugger,
-                        "[VirtAudit Debug] check_fixable: Issue dep {}@{} is not fixable with error {:?}.",
-                        graph[issue_nx].name, graph[issue_nx].version, e
-                    )
-                    .unwrap();
+                    observer.on_event(AuditEvent::Unfixable {
+                        package: format!("{}@{}", graph[issue_nx].name, graph[issue_nx].version),
+                        error: e.describe(),
+                    });
                     return Err(e);
                 }
             };
@@ -265,15 +225,6 @@ fn try_fix(
         };
 
         let issue_name_ver = format!("{}@{}", graph[issue_nx].name, graph[issue_nx].version);
-        writeln!(
-            debugger,
-            "[VirtAudit Debug] try_fix: Try fix {} with {:?}",
-            graph[issue_nx].name,
-            fix.iter()
-                .map(|(name, ver, fix_ver)| format!("{}@{} -> {}", name, ver, fix_ver))
-                .collect::<Vec<_>>()
-        )
-        .unwrap();
 
         let entry = fix_deps
             .entry(issue_name_ver.clone())
@@ -281,22 +232,34 @@ fn try_fix(
 
         // Set the limit first.
         deptree.set_fix_limit(&fix);
-        let steps = deptree.issue_dofix(issue_nx, fix, debugger)?;
-
-        entry.extend(steps.into_iter());
+        let steps = deptree.issue_dofix(issue_nx, fix, observer)?;
+
+        entry.extend(steps.into_iter().map(|(name, from, to, rufs)| FixStep {
+            name,
+            from,
+            to,
+            rufs,
+        }));
     }
 }
 
 #[test]
+#[ignore = "needs the ruf database in PostgreSQL and a local audit workspace"]
 fn test_audit() {
+    use super::ops::DepOpsVirt;
+    use crate::core::TextObserver;
     use std::sync::{Arc, Mutex};
 
     const WORKSPACE_PATH: &str = "/home/ubuntu/Workspaces/Cargo-Ecosystem-Monitor/Code/cargo_ruf/ruf_audit_virtual/virt_work";
     let stdout = Arc::new(Mutex::new(std::io::stdout()));
     let mut buffer = stdout.lock().unwrap();
-
-    let res = treeonly_audit("leaf", "0.0.1", WORKSPACE_PATH, &mut *buffer);
-    // let res = treeonly_audit("ccm", "0.4.3", WORKSPACE_PATH, &mut *buffer);
-
-    println!("RESULTS: {:?}", res);
+    let options = AuditOptions::default();
+    let mut observer = TextObserver(&mut *buffer);
+
+    let _ = treeonly_audit(
+        DepOpsVirt::new("leaf", "0.0.1", WORKSPACE_PATH).unwrap(),
+        &options,
+        &mut observer,
+    );
+    // let res = treeonly_audit(DepOpsVirt::new("ccm", "0.4.3", WORKSPACE_PATH).unwrap(), &options, &mut observer);
 }
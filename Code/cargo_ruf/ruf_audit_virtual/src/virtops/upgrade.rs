@@ -0,0 +1,184 @@
+use std::fmt::Display;
+
+use fxhash::FxHashMap;
+use semver::Version;
+use serde::Serialize;
+
+use super::audit::{check_fixable, check_issue, try_fix};
+use super::options::AuditOptions;
+use super::report::{self, Decision, RustcDecision, Summary};
+use crate::basic::Toolchain;
+use crate::core::{AuditError, AuditEvent, AuditObserver, DepOps, DepTreeManager, VersionChange};
+
+/// A version requirement to edit in the manifest of a local package.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReqEdit {
+    /// The local package, `name@ver`.
+    pub package: String,
+    pub dep: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl Display for ReqEdit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} = \"{}\" -> \"{}\"",
+            self.package, self.dep, self.from, self.to
+        )
+    }
+}
+
+/// Upgrades fixing the ruf issues, along with the requirement edits they need.
+#[derive(Serialize)]
+pub struct UpgradePlan {
+    pub fix_rustv: i32,
+    pub fix_toolchain: String,
+    /// Packages to upgrade, sorted by name.
+    pub upgrades: Vec<VersionChange>,
+    /// Requirements of locals to edit, empty if the upgrades are within them.
+    pub edits: Vec<ReqEdit>,
+    /// The summary of the fixed tree, only when no edits are needed,
+    /// as the upgrades are then applied and checked with cargo.
+    pub summary: Option<Summary>,
+    /// Decisions on each checked rustc, from newest to oldest.
+    pub trail: Vec<RustcDecision>,
+}
+
+/// Search the newest rustc in options fixable by upgrades only, moving issue packages or
+/// their parents to newer releases that no longer use the broken rufs. Requirements of the
+/// locals (the audited packages, or workspace members) may be bumped, even across semver
+/// incompatible versions, and are reported as edits to their `Cargo.toml`.
+/// The observer receives the events of the audit.
+pub fn upgrade_audit(
+    mut ops: impl DepOps,
+    options: &AuditOptions,
+    observer: &mut impl AuditObserver,
+) -> Result<UpgradePlan, AuditError> {
+    options.setup(&mut ops);
+    let rustcs = options.rustc_indexes()?;
+    // Init a tree first, locals are set by the operators.
+    let mut deptree = DepTreeManager::new(ops, rustcs[0])?;
+    deptree.set_objective(options.objective);
+    deptree.set_yank_policy(options.yank_policy);
+    deptree.set_upgrade(true);
+
+    let mut trail = Vec::new();
+    for rustc in rustcs {
+        deptree.switch_rustv(rustc);
+        observer.on_event(AuditEvent::CheckRustc {
+            rustv: rustc,
+            toolchain: Toolchain::from_index(rustc).to_string(),
+        });
+        let issue_deps = check_issue(&deptree, observer)?;
+        let mut decision = RustcDecision::new(&deptree, rustc, &issue_deps)?;
+        if issue_deps.is_empty() {
+            observer.on_event(AuditEvent::RustcNoIssue { rustv: rustc });
+            trail.push(decision);
+            let summary = report::summarize(
+                &deptree,
+                options,
+                rustc as i32,
+                FxHashMap::default(),
+                trail.clone(),
+            )?;
+            return Ok(plan(rustc, Vec::new(), Vec::new(), Some(summary), trail));
+        }
+
+        let first_issue = issue_deps.first().cloned().unwrap();
+        let mut fixes = match check_fixable(&mut deptree, issue_deps, observer) {
+            Ok(fixes) => fixes,
+            Err(e) if !e.is_inner() => {
+                observer.on_event(AuditEvent::RustcFailed {
+                    rustv: rustc,
+                    error: e.describe(),
+                });
+                decision.fail(Decision::Unfixable, &e, deptree.get_graph());
+                trail.push(decision);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        // Fixes of later issues only go further up, keep the newest.
+        let mut upgrades: FxHashMap<(String, Version), Version> = FxHashMap::default();
+        for (name, ver, fix_ver) in fixes.iter().flatten() {
+            let to = upgrades
+                .entry((name.clone(), ver.clone()))
+                .or_insert(fix_ver.clone());
+            if *to < *fix_ver {
+                *to = fix_ver.clone();
+            }
+        }
+        let mut upgrades = upgrades
+            .into_iter()
+            .map(|((name, from), to)| (name, from, to))
+            .collect::<Vec<_>>();
+        upgrades.sort();
+
+        let edits = deptree
+            .get_req_edits(&upgrades)?
+            .into_iter()
+            .map(|(package, dep, req, ver)| ReqEdit {
+                package,
+                dep,
+                from: req.to_string(),
+                to: ver.to_string(),
+            })
+            .collect::<Vec<_>>();
+        let upgrades = upgrades
+            .into_iter()
+            .map(|(name, from, to)| VersionChange { name, from, to })
+            .collect();
+
+        if !edits.is_empty() {
+            // Cargo cannot resolve against the edited manifests, leave them to users.
+            observer.on_event(AuditEvent::RustcFixed { rustv: rustc });
+            trail.push(decision);
+            return Ok(plan(rustc, upgrades, edits, None, trail));
+        }
+
+        match try_fix(&mut deptree, first_issue, fixes.remove(0), observer) {
+            Ok(fix_deps) => {
+                observer.on_event(AuditEvent::RustcFixed { rustv: rustc });
+                trail.push(decision);
+                let summary =
+                    report::summarize(&deptree, options, rustc as i32, fix_deps, trail.clone())?;
+                return Ok(plan(rustc, upgrades, edits, Some(summary), trail));
+            }
+            Err(e) => {
+                observer.on_event(AuditEvent::RustcFailed {
+                    rustv: rustc,
+                    error: e.describe(),
+                });
+                decision.fail(Decision::FixFailed, &e, deptree.get_graph());
+                trail.push(decision);
+            }
+        }
+    }
+
+    Err(AuditError::NoFix(
+        trail
+            .iter()
+            .filter_map(|decision| decision.failure())
+            .collect(),
+    ))
+}
+
+fn plan(
+    rustc: u32,
+    upgrades: Vec<VersionChange>,
+    edits: Vec<ReqEdit>,
+    summary: Option<Summary>,
+    trail: Vec<RustcDecision>,
+) -> UpgradePlan {
+    UpgradePlan {
+        fix_rustv: rustc as i32,
+        fix_toolchain: Toolchain::from_index(rustc).to_string(),
+        upgrades,
+        edits,
+        summary,
+        trail,
+    }
+}
@@ -0,0 +1,71 @@
+import csv
+import json
+import sys
+import numpy as np
+import psycopg2 as pg
+import os
+
+# Usage: python3 build_ruf_lifetime.py [output] [nightly.csv], output defaults to data/ruf_lifetime.json.
+# The rustc releases are the columns of `feature_timeline`, from 1.0.0 on.
+# The csv records ruf status changes by nightly dates, with columns `name,date,status`,
+# where date is `YYYY-MM-DD` and each status holds until the next change of the ruf.
+# It defaults to data/ruf_nightly.csv, built by build_ruf_nightly.py.
+FORMAT_VERSION = 2
+STATUS_CODES = {"": "0", "active": "1", "incomplete": "2", "accepted": "3", "removed": "4"}
+
+
+def status_code(status):
+    # NULL statuses in the timeline are unknown, the same as empty ones.
+    if status is None:
+        status = ""
+    if status not in STATUS_CODES:
+        raise Exception("Unknown status: " + status)
+    return STATUS_CODES[status]
+
+
+conn = pg.connect(database="crates", user="postgres", password="postgres")
+cur = conn.cursor()
+cur.execute("SELECT * FROM feature_timeline")
+rows = np.array(cur.fetchall())
+
+lifetime = rows[:, 1:]
+ruf_name = rows[:, 0]
+RUSTC_VER_NUM = lifetime.shape[1]
+RUF_SIZE = lifetime.shape[0]
+lines = []
+for i in sorted(range(0, RUF_SIZE), key=lambda i: ruf_name[i]):
+    codes = [status_code(ruf_status) for ruf_status in lifetime[i]]
+    lines.append(json.dumps(ruf_name[i]) + ": [" + ",".join(codes) + "]")
+
+rustc = ", ".join('"1.%d.0"' % i for i in range(0, RUSTC_VER_NUM))
+
+current_dir_path = os.path.dirname(os.path.abspath(__file__))
+nightly_csv = sys.argv[2] if len(sys.argv) > 2 else current_dir_path + "/data/ruf_nightly.csv"
+nightly = {}
+with open(nightly_csv) as f:
+    for row in csv.DictReader(f):
+        nightly.setdefault(row["name"], []).append((row["date"], status_code(row["status"])))
+nightly_lines = []
+for name in sorted(nightly):
+    changes = sorted(nightly[name])
+    nightly_lines.append(
+        json.dumps(name) + ": [" + ",".join('["%s",%s]' % c for c in changes) + "]"
+    )
+
+# Same layout as `LifetimeStore::to_json`, one ruf per line.
+file = (
+    "{\n"
+    + '"format": %d,\n' % FORMAT_VERSION
+    + '"rustc": [' + rustc + "],\n"
+    + '"lifetime": {\n'
+    + ",\n".join(lines)
+    + "\n},\n"
+    + '"nightly": '
+    + ("{\n" + ",\n".join(nightly_lines) + "\n}" if nightly_lines else "{}")
+    + "\n}\n"
+)
+
+output = sys.argv[1] if len(sys.argv) > 1 else current_dir_path + "/data/ruf_lifetime.json"
+fo = open(output, "w")
+fo.write(file)
+fo.close()
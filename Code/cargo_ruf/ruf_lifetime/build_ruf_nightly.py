@@ -0,0 +1,163 @@
+import csv
+import io
+import json
+import os
+import re
+import sys
+import tarfile
+import urllib.error
+import urllib.request
+from datetime import date, timedelta
+
+# Usage: python3 build_ruf_nightly.py [output] [from] [to], output defaults to data/ruf_nightly.csv.
+# Records the status changes of library rufs by nightly dates, the nightly csv of build_ruf_lifetime.py.
+# The status is read from the `#[unstable]` and `#[stable]` attributes in the rust-src of the nightlies.
+# static.rust-lang.org keeps them since 2019-06 with gaps of months, and without gaps longer than two weeks
+# since 2020-12, so nightlies before `from` (2020-12-01 by default) fall back to the stable releases.
+# Nightlies are sampled weekly from `from` until the first nightly newer than the releases in
+# data/ruf_lifetime.json, or `to`, and each change is then dated by bisecting to the first nightly kept
+# with the new status. Downloads are cached in nightly_src/.
+DIST = "https://static.rust-lang.org/dist/%s/rust-src-nightly.tar.xz"
+CHANNEL = "https://static.rust-lang.org/dist/%s/channel-rust-nightly.toml"
+FIRST_NIGHTLY = "2020-12-01"
+STEP = 7
+
+ATTR = re.compile(r"#!?\[\s*(unstable|stable|rustc_const_unstable|rustc_const_stable)\s*\(([^\]]*?)\)\s*\]", re.S)
+FEATURE = re.compile(r'feature\s*=\s*"([^"]+)"')
+VERSION = re.compile(r'\[pkg\.rust\]\s*version = "1\.(\d+)\.')
+
+current_dir_path = os.path.dirname(os.path.abspath(__file__))
+cache_dir = current_dir_path + "/nightly_src"
+output = sys.argv[1] if len(sys.argv) > 1 else current_dir_path + "/data/ruf_nightly.csv"
+first = date.fromisoformat(sys.argv[2] if len(sys.argv) > 2 else FIRST_NIGHTLY)
+last = date.fromisoformat(sys.argv[3]) if len(sys.argv) > 3 else date.today()
+
+with open(current_dir_path + "/data/ruf_lifetime.json") as f:
+    lifetime_file = json.load(f)
+RUSTC_VER_NUM = len(lifetime_file["rustc"])
+lifetime = lifetime_file["lifetime"]
+
+# Incomplete and removed are statuses of language features, which are not told by the attributes.
+library_rufs = {
+    name for name, codes in lifetime.items() if not any(code in (2, 4) for code in codes)
+}
+
+
+def fetch(url, path):
+    # The file at the url, None if there is no such file.
+    if os.path.exists(path):
+        with open(path, "rb") as f:
+            return f.read()
+    if os.path.exists(path + ".missing"):
+        return None
+
+    os.makedirs(cache_dir, exist_ok=True)
+    try:
+        with urllib.request.urlopen(url) as resp:
+            data = resp.read()
+    except urllib.error.HTTPError as e:
+        if e.code not in (403, 404):
+            raise
+        open(path + ".missing", "w").close()
+        return None
+    with open(path, "wb") as f:
+        f.write(data)
+    return data
+
+
+samples = {}
+
+
+def sample(day):
+    # (minor, {ruf: status}) of the nightly, None if there is no such nightly.
+    if day in samples:
+        return samples[day]
+
+    data = fetch(DIST % day, "%s/%s.tar.xz" % (cache_dir, day))
+    if data is None:
+        samples[day] = None
+        return None
+
+    status = {}
+    with tarfile.open(fileobj=io.BytesIO(data), mode="r:xz") as tar:
+        for member in tar:
+            if member.isfile() and member.name.endswith(".rs"):
+                src = tar.extractfile(member).read().decode("utf-8", "ignore")
+                for attr in ATTR.finditer(src):
+                    feature = FEATURE.search(attr.group(2))
+                    if feature is None or feature.group(1) not in library_rufs:
+                        continue
+                    # Any unstable item keeps the ruf active.
+                    if attr.group(1).endswith("unstable"):
+                        status[feature.group(1)] = "active"
+                    else:
+                        status.setdefault(feature.group(1), "accepted")
+
+    # Older rust-src has no version file, the rustc version is read from the channel.
+    channel = fetch(CHANNEL % day, "%s/%s.toml" % (cache_dir, day))
+    minor = int(VERSION.search(channel.decode()).group(1))
+
+    samples[day] = (minor, status)
+    print("%s: 1.%d nightly, %d rufs" % (day, minor, len(status)), file=sys.stderr)
+    return samples[day]
+
+
+def changed(a, b):
+    # Rufs with different statuses, vanished rufs are unknown.
+    return {ruf for ruf in set(a) | set(b) if a.get(ruf, "") != b.get(ruf, "")}
+
+
+def bisect(lo, hi, rufs, changes):
+    # Date the changes of the rufs between the nightlies, the earliest nightly seeing the new status.
+    # Rufs first seen after the first nightly fall back to the stable releases before.
+    if not rufs:
+        return
+    days = (hi - lo).days
+    if days <= 1:
+        for ruf in rufs:
+            changes[ruf].append((hi, samples[hi][1].get(ruf, "")))
+        return
+
+    mid = lo + timedelta(days=days // 2)
+    probe = mid
+    while probe < hi and sample(probe) is None:
+        probe += timedelta(days=1)
+    if probe == hi:
+        probe = mid - timedelta(days=1)
+        while probe > lo and sample(probe) is None:
+            probe -= timedelta(days=1)
+        if probe == lo:
+            # No nightlies in between.
+            bisect(hi, hi, rufs, changes)
+            return
+
+    lo_status, probe_status = samples[lo][1], samples[probe][1]
+    bisect(lo, probe, {ruf for ruf in rufs if lo_status.get(ruf, "") != probe_status.get(ruf, "")}, changes)
+    bisect(probe, hi, {ruf for ruf in rufs if probe_status.get(ruf, "") != samples[hi][1].get(ruf, "")}, changes)
+
+
+# Weekly nightlies, skipping the missing ones, up to the last nightly in the lifetime table.
+days = []
+day = first
+while day <= last:
+    probe = day
+    while probe < day + timedelta(days=STEP) and sample(probe) is None:
+        probe += timedelta(days=1)
+    if probe < day + timedelta(days=STEP):
+        if samples[probe][0] >= RUSTC_VER_NUM:
+            break
+        days.append(probe)
+    day += timedelta(days=STEP)
+
+changes = {ruf: [] for ruf in library_rufs}
+for ruf, status in samples[days[0]][1].items():
+    changes[ruf].append((days[0], status))
+for lo, hi in zip(days, days[1:]):
+    bisect(lo, hi, changed(samples[lo][1], samples[hi][1]), changes)
+
+with open(output, "w", newline="") as f:
+    writer = csv.writer(f, lineterminator="\n")
+    writer.writerow(["name", "date", "status"])
+    for ruf in sorted(changes):
+        for day, status in sorted(changes[ruf]):
+            writer.writerow([ruf, day.isoformat(), status])
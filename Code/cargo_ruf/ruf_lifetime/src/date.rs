@@ -0,0 +1,148 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Rust 1.0 release date.
+const RUST_1_0: Date = Date::new(2015, 5, 15);
+/// Rust 1.1 release date, since then a new stable comes every 6 weeks.
+const RUST_1_1: Date = Date::new(2015, 6, 25);
+const RELEASE_CYCLE_DAYS: i64 = 42;
+
+/// Calendar date, for nightly toolchains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub const fn new(year: u32, month: u32, day: u32) -> Self {
+        Self { year, month, day }
+    }
+
+    /// Days in the month of the year, 0 for invalid months.
+    pub fn days_in_month(year: u32, month: u32) -> u32 {
+        match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 => (Date::new(year, 3, 1).days() - Date::new(year, 2, 1).days()) as u32,
+            _ => 0,
+        }
+    }
+
+    /// Days since 1970-01-01.
+    pub fn days(&self) -> i64 {
+        // Howard Hinnant's days_from_civil.
+        let y = self.year as i64 - if self.month <= 2 { 1 } else { 0 };
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let m = self.month as i64;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+
+    /// Inverse of [`Date::days`].
+    pub fn from_days(days: i64) -> Self {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        Self::new(year as u32, month as u32, day as u32)
+    }
+}
+
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s
+            .split('-')
+            .map(|p| p.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid date {s}: {e}"))?;
+        if parts.len() != 3 {
+            return Err(format!("invalid date {s}, expect YYYY-MM-DD"));
+        }
+        if parts[2] == 0 || parts[2] > Self::days_in_month(parts[0], parts[1]) {
+            return Err(format!("invalid date {s}, no such day"));
+        }
+
+        Ok(Self::new(parts[0], parts[1], parts[2]))
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// Release date of the stable `1.{minor}.0`.
+pub fn stable_release_date(minor: u32) -> Date {
+    if minor == 0 {
+        RUST_1_0
+    } else {
+        Date::from_days(RUST_1_1.days() + RELEASE_CYCLE_DAYS * (minor as i64 - 1))
+    }
+}
+
+/// The newest stable minor released at the date.
+pub fn stable_minor_at(date: &Date) -> Option<u32> {
+    let days = date.days();
+    if days < RUST_1_0.days() {
+        None
+    } else if days < RUST_1_1.days() {
+        Some(0)
+    } else {
+        Some(((days - RUST_1_1.days()) / RELEASE_CYCLE_DAYS) as u32 + 1)
+    }
+}
+
+/// The minor version the nightly of the date builds, two versions ahead of the stable at the time.
+pub fn nightly_minor_at(date: &Date) -> u32 {
+    stable_minor_at(date).map_or(0, |minor| minor + 2)
+}
+
+/// The first and the last nightly building `1.{minor}`, `None` for the ones before 1.0 released.
+pub fn nightly_window(minor: u32) -> Option<(Date, Date)> {
+    if minor < 2 {
+        return None;
+    }
+
+    let from = stable_release_date(minor - 2);
+    let to = Date::from_days(stable_release_date(minor - 1).days() - 1);
+    Some((from, to))
+}
+
+#[test]
+fn test_parse_date() {
+    assert_eq!("2023-05-01".parse(), Ok(Date::new(2023, 5, 1)));
+    assert_eq!("2020-02-29".parse(), Ok(Date::new(2020, 2, 29)));
+    assert_eq!("2000-02-29".parse(), Ok(Date::new(2000, 2, 29)));
+    assert_eq!("2023-12-31".parse(), Ok(Date::new(2023, 12, 31)));
+    for invalid in [
+        "2023-02-29",
+        "1900-02-29",
+        "2023-04-31",
+        "2023-06-31",
+        "2023-01-32",
+        "2023-13-01",
+        "2023-00-01",
+        "2023-01-00",
+        "2023-01",
+        "2023-01-01-01",
+        "2023-x-01",
+    ] {
+        assert!(invalid.parse::<Date>().is_err(), "{invalid}");
+    }
+
+    let date = Date::new(2024, 2, 29);
+    assert_eq!(Date::from_days(date.days()), date);
+    assert_eq!(date.to_string().parse(), Ok(date));
+}
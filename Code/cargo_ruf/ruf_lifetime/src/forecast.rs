@@ -0,0 +1,351 @@
+use std::fmt::Display;
+
+use fxhash::FxHashMap;
+use serde::Serialize;
+
+use super::{LifetimeStore, RufStatus};
+
+/// Upper bounds of the age buckets, in releases the ruf has held its status.
+const AGE_BUCKETS: [u32; 3] = [3, 11, 35];
+/// Unstable rufs holding their status this long are stale.
+const STALE_RELEASES: u32 = 36;
+/// Accepted rufs this young are flagged.
+const RECENT_RELEASES: u32 = 6;
+/// Status changes making a ruf churning.
+const CHURN_CHANGES: u32 = 2;
+/// Observations the broader rate counts as, so that rates of few observations stay close to it.
+const PRIOR_WEIGHT: f64 = 10.0;
+
+/// Patterns in the lifetime of a ruf, hinting what may happen next.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "signal", rename_all = "snake_case")]
+pub enum RiskSignal {
+    /// Unstable for many releases, without being accepted.
+    Stale { releases: u32 },
+    /// The status went back and forth, like active, incomplete and active again.
+    Churn { changes: u32 },
+    /// A newer ruf extends its name, like `const_generics_defaults` after `const_generics`,
+    /// which is often split from it or replacing it.
+    Superseded { by: String },
+    /// Accepted lately, the feature gate is only warned about and can be dropped.
+    RecentlyAccepted { releases: u32 },
+}
+
+impl Display for RiskSignal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stale { releases } => write!(f, "unstable for {releases} releases"),
+            Self::Churn { changes } => write!(f, "status changed {changes} times"),
+            Self::Superseded { by } => write!(f, "superseded by {by}"),
+            Self::RecentlyAccepted { releases } => {
+                write!(
+                    f,
+                    "accepted {releases} releases ago, the gate can be dropped"
+                )
+            }
+        }
+    }
+}
+
+/// Forecast of a ruf breaking in the next rustc release.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RufForecast {
+    pub ruf: String,
+    #[serde(serialize_with = "status_str")]
+    pub status: RufStatus,
+    /// Releases the status has held, up to the release forecast from.
+    pub age: u32,
+    pub signals: Vec<RiskSignal>,
+    /// Chance of becoming unusable in the next release, 1 if already unusable.
+    pub risk: f64,
+}
+
+fn status_str<S: serde::Serializer>(status: &RufStatus, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(status.as_str())
+}
+
+/// The lifetime of a ruf up to the release forecast from.
+struct History {
+    status: Vec<RufStatus>,
+    /// Releases the status has held, and the changes between known statuses so far,
+    /// at each release.
+    age: Vec<u32>,
+    changes: Vec<u32>,
+    /// The first release known.
+    first: Option<usize>,
+    /// The first newer ruf extending the name, and the release it comes.
+    superseded: Option<(usize, String)>,
+}
+
+impl History {
+    fn new(status: Vec<RufStatus>) -> Self {
+        let (mut age, mut changes) = (Vec::new(), Vec::new());
+        let mut last_known = None;
+        for (i, s) in status.iter().enumerate() {
+            let held = match i {
+                0 => 1,
+                _ if status[i - 1] == *s => age[i - 1] + 1,
+                _ => 1,
+            };
+            age.push(held);
+
+            let mut changed = changes.last().copied().unwrap_or(0);
+            if *s != RufStatus::Unknown {
+                if last_known.is_some_and(|last| last != *s) {
+                    changed += 1;
+                }
+                last_known = Some(*s);
+            }
+            changes.push(changed);
+        }
+        let first = status.iter().position(|s| *s != RufStatus::Unknown);
+
+        Self {
+            status,
+            age,
+            changes,
+            first,
+            superseded: None,
+        }
+    }
+
+    fn is_unstable(&self, i: usize) -> bool {
+        matches!(self.status[i], RufStatus::Active | RufStatus::Incomplete)
+    }
+
+    fn is_churning(&self, i: usize) -> bool {
+        self.changes[i] >= CHURN_CHANGES
+    }
+
+    fn is_superseded(&self, i: usize) -> bool {
+        self.superseded
+            .as_ref()
+            .is_some_and(|(since, _)| *since <= i)
+    }
+}
+
+fn age_bucket(age: u32) -> usize {
+    AGE_BUCKETS
+        .iter()
+        .position(|bound| age <= *bound)
+        .unwrap_or(AGE_BUCKETS.len())
+}
+
+/// How often the rufs observed break in the next release.
+#[derive(Default, Clone, Copy)]
+struct Rate {
+    broken: u32,
+    total: u32,
+}
+
+impl Rate {
+    fn observe(&mut self, broken: bool) {
+        self.broken += broken as u32;
+        self.total += 1;
+    }
+
+    fn get(&self) -> Option<f64> {
+        (self.total > 0).then(|| self.broken as f64 / self.total as f64)
+    }
+
+    /// The rate pulled towards the broader one.
+    fn smoothed(&self, prior: f64) -> f64 {
+        (self.broken as f64 + PRIOR_WEIGHT * prior) / (self.total as f64 + PRIOR_WEIGHT)
+    }
+}
+
+/// Break rates learnt from the history: by status and age, and how the signals raise them.
+#[derive(Default)]
+struct Model {
+    hazards: FxHashMap<(RufStatus, usize), Rate>,
+    statuses: FxHashMap<RufStatus, Rate>,
+    unstable: Rate,
+    churning: Rate,
+    superseded: Rate,
+}
+
+impl Model {
+    fn learn(histories: &FxHashMap<&str, History>, num: usize) -> Self {
+        let mut model = Self::default();
+        for history in histories.values() {
+            for i in 0..num - 1 {
+                let status = history.status[i];
+                if !status.is_usable() {
+                    continue;
+                }
+
+                let broken = !history.status[i + 1].is_usable();
+                model
+                    .hazards
+                    .entry((status, age_bucket(history.age[i])))
+                    .or_default()
+                    .observe(broken);
+                model.statuses.entry(status).or_default().observe(broken);
+                if history.is_unstable(i) {
+                    model.unstable.observe(broken);
+                    if history.is_churning(i) {
+                        model.churning.observe(broken);
+                    }
+                    if history.is_superseded(i) {
+                        model.superseded.observe(broken);
+                    }
+                }
+            }
+        }
+
+        model
+    }
+
+    fn hazard(&self, status: RufStatus, age: u32) -> f64 {
+        let prior = self
+            .statuses
+            .get(&status)
+            .and_then(Rate::get)
+            .unwrap_or(0.0);
+        self.hazards
+            .get(&(status, age_bucket(age)))
+            .map_or(prior, |rate| rate.smoothed(prior))
+    }
+
+    /// How much more unstable rufs with the signal break, 1 if unknown.
+    fn lift(&self, signal: &Rate) -> f64 {
+        match self.unstable.get() {
+            Some(base) if base > 0.0 => signal.smoothed(base) / base,
+            _ => 1.0,
+        }
+    }
+}
+
+impl LifetimeStore {
+    /// Forecast the rufs breaking in the release after `rustc_ver`. The risks are the break rates
+    /// of rufs with the same status and a similar age in the releases up to `rustc_ver`, raised
+    /// by how much more churning and superseded rufs broke, so newer releases are never peeked.
+    pub fn forecast(&self, rufs: &[&str], rustc_ver: u32) -> Vec<RufForecast> {
+        let num = rustc_ver as usize + 1;
+        assert!(num <= self.rustc_ver_num());
+
+        let mut histories = self
+            .rufs()
+            .map(|ruf| {
+                let mut status = self.get_all_ruf_status(ruf);
+                status.truncate(num);
+                (ruf, History::new(status))
+            })
+            .collect::<FxHashMap<_, _>>();
+
+        // Rufs extending the name are next to it in name order.
+        let mut names = histories.keys().copied().collect::<Vec<_>>();
+        names.sort();
+        let firsts = histories
+            .iter()
+            .map(|(ruf, history)| (*ruf, history.first))
+            .collect::<FxHashMap<_, _>>();
+        for (ruf, history) in histories.iter_mut() {
+            let Some(first) = history.first else {
+                continue;
+            };
+            let prefix = format!("{ruf}_");
+            let start = names.partition_point(|name| *name < prefix.as_str());
+            history.superseded = names[start..]
+                .iter()
+                .take_while(|name| name.starts_with(&prefix))
+                .filter_map(|name| Some((firsts[name]?, *name)))
+                .filter(|(since, _)| *since > first)
+                .min()
+                .map(|(since, name)| (since, name.to_string()));
+        }
+
+        let model = Model::learn(&histories, num);
+        let i = num - 1;
+        let unknown = History::new(vec![RufStatus::Unknown; num]);
+        rufs.iter()
+            .map(|ruf| {
+                let history = histories.get(ruf).unwrap_or(&unknown);
+                let (status, age) = (history.status[i], history.age[i]);
+                let mut forecast = RufForecast {
+                    ruf: ruf.to_string(),
+                    status,
+                    age,
+                    signals: Vec::new(),
+                    risk: 1.0,
+                };
+                if !status.is_usable() {
+                    return forecast;
+                }
+
+                let mut risk = model.hazard(status, age);
+                if history.is_unstable(i) {
+                    if history.is_churning(i) {
+                        forecast.signals.push(RiskSignal::Churn {
+                            changes: history.changes[i],
+                        });
+                        risk *= model.lift(&model.churning);
+                    }
+                    if history.is_superseded(i) {
+                        let (_, by) = history.superseded.as_ref().unwrap();
+                        forecast
+                            .signals
+                            .push(RiskSignal::Superseded { by: by.clone() });
+                        risk *= model.lift(&model.superseded);
+                    }
+                    // Already weighed by the age.
+                    if age >= STALE_RELEASES {
+                        forecast.signals.push(RiskSignal::Stale { releases: age });
+                    }
+                } else if age <= RECENT_RELEASES {
+                    forecast
+                        .signals
+                        .push(RiskSignal::RecentlyAccepted { releases: age });
+                }
+                forecast.risk = risk.min(1.0);
+
+                forecast
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_forecast() {
+    let store = LifetimeStore::from_json(
+        r#"{
+"format": 2,
+"rustc": ["1.0.0", "1.1.0", "1.2.0", "1.3.0", "1.4.0", "1.5.0"],
+"lifetime": {
+"gone": [1,1,4,4,4,4],
+"kept": [1,1,1,1,1,1],
+"flip": [1,2,1,2,2,2],
+"flip_extended": [0,0,0,1,1,1],
+"done": [0,0,0,0,3,3],
+"young": [0,0,0,0,1,1]
+}
+}"#,
+    )
+    .unwrap();
+
+    let forecasts = store.forecast(&["gone", "kept", "young", "flip", "done", "no_such_ruf"], 5);
+    let risk = |ruf: &str| forecasts.iter().find(|f| f.ruf == ruf).unwrap();
+    assert_eq!(risk("gone").risk, 1.0);
+    assert_eq!(risk("no_such_ruf").risk, 1.0);
+    assert_eq!(risk("kept").age, 6);
+    assert!(risk("kept").signals.is_empty());
+    // Young rufs broke, while older ones never did.
+    assert!(risk("young").risk > risk("kept").risk);
+    assert_eq!(
+        risk("flip").signals,
+        vec![
+            RiskSignal::Churn { changes: 3 },
+            RiskSignal::Superseded {
+                by: "flip_extended".to_string()
+            }
+        ]
+    );
+    assert_eq!(
+        risk("done").signals,
+        vec![RiskSignal::RecentlyAccepted { releases: 2 }]
+    );
+
+    // Only the releases up to the one forecast from are learnt.
+    let forecasts = store.forecast(&["kept"], 1);
+    assert_eq!(forecasts[0].risk, 0.0);
+}
@@ -0,0 +1,193 @@
+//! Ruf lifetime over rustc releases, shared by our audit and analysis crates.
+//!
+//! The default lifetime, `data/ruf_lifetime.json` (see `build_ruf_lifetime.py`), is embedded at build time.
+//! Another data file can be given by the `RUF_LIFETIME` environment variable, loaded at runtime,
+//! so new rustc releases can be appended to it without recompiling.
+//! Rufs can also be recorded in nightly granularity, see [`get_ruf_status_at`].
+//! Rufs breaking in the next release are forecast from the history, see [`forecast_rufs`].
+
+mod date;
+mod forecast;
+mod status;
+mod store;
+
+pub use date::{nightly_minor_at, nightly_window, stable_minor_at, stable_release_date, Date};
+pub use forecast::{RiskSignal, RufForecast};
+pub use status::RufStatus;
+pub use store::{LifetimeStore, FORMAT_VERSION};
+
+use std::env;
+
+use lazy_static::lazy_static;
+
+/// Environment variable overriding the default lifetime data file.
+pub const LIFETIME_ENV: &str = "RUF_LIFETIME";
+const DEFAULT_LIFETIME: &str = include_str!("../data/ruf_lifetime.json");
+
+lazy_static! {
+    static ref DEFAULT_STORE: LifetimeStore = LifetimeStore::from_json(DEFAULT_LIFETIME)
+        .expect("the embedded ruf lifetime is checked by test_lifetime");
+    static ref RUF_LIFETIME: Result<LifetimeStore, String> = load_lifetime();
+}
+
+/// The file given by [`LIFETIME_ENV`] if set, or the embedded default.
+fn load_lifetime() -> Result<LifetimeStore, String> {
+    match env::var_os(LIFETIME_ENV) {
+        Some(path) => {
+            LifetimeStore::load(path).map_err(|e| format!("{e}, given by {LIFETIME_ENV}"))
+        }
+        None => Ok(DEFAULT_STORE.clone()),
+    }
+}
+
+/// The lifetime in use, loaded once on first use, or why the file given by [`LIFETIME_ENV`]
+/// cannot be loaded.
+///
+/// The queries below use the embedded default if that file cannot be loaded,
+/// so callers should check this before running them.
+pub fn lifetime() -> Result<&'static LifetimeStore, String> {
+    RUF_LIFETIME.as_ref().map_err(|e| e.clone())
+}
+
+fn store() -> &'static LifetimeStore {
+    RUF_LIFETIME.as_ref().unwrap_or(&DEFAULT_STORE)
+}
+
+/// Number of rustc releases covered, rustc `1.i.0` for `i` below it.
+pub fn rustc_ver_num() -> usize {
+    store().rustc_ver_num()
+}
+
+pub fn get_ruf_status(ruf_name: &str, rustc_ver: u32) -> RufStatus {
+    store().get_ruf_status(ruf_name, rustc_ver)
+}
+
+pub fn get_all_ruf_status(ruf_name: &str) -> Vec<RufStatus> {
+    store().get_all_ruf_status(ruf_name)
+}
+
+/// Status of the ruf in the nightly of the date.
+pub fn get_ruf_status_at(ruf_name: &str, date: &Date) -> RufStatus {
+    store().get_ruf_status_at(ruf_name, date)
+}
+
+/// The nightly windows from `from` to `to` (both inclusive) where all the rufs are usable.
+pub fn usable_nightlies(rufs: &[&str], from: Date, to: Date) -> Vec<(Date, Date)> {
+    store().usable_nightlies(rufs, from, to)
+}
+
+/// Forecast the rufs breaking in the release after `rustc_ver`, see [`LifetimeStore::forecast`].
+pub fn forecast_rufs(rufs: &[&str], rustc_ver: u32) -> Vec<RufForecast> {
+    store().forecast(rufs, rustc_ver)
+}
+
+#[test]
+fn test_lifetime() {
+    use fxhash::FxHashMap;
+
+    let mut store = LifetimeStore::from_json(DEFAULT_LIFETIME).unwrap();
+    let num = store.rustc_ver_num();
+    assert_eq!(store.rustc_versions()[num - 1], format!("1.{}.0", num - 1));
+    assert_eq!(
+        store.get_ruf_status("proc_macro_mod", 0),
+        RufStatus::Unknown
+    );
+    assert_eq!(
+        store.get_ruf_status("proc_macro_mod", 27),
+        RufStatus::Active
+    );
+    assert_eq!(
+        store.get_ruf_status("proc_macro_mod", 31),
+        RufStatus::Removed
+    );
+    assert_eq!(store.get_ruf_status("no_such_ruf", 31), RufStatus::Unknown);
+    assert_eq!(store.get_all_ruf_status("proc_macro_mod").len(), num);
+
+    // Round trip.
+    let reload = LifetimeStore::from_json(&store.to_json()).unwrap();
+    assert_eq!(reload.to_json(), store.to_json());
+
+    // Append a release.
+    let mut status = FxHashMap::default();
+    status.insert("proc_macro_def_site".to_string(), RufStatus::Accepted);
+    status.insert("brand_new_ruf".to_string(), RufStatus::Active);
+    assert!(store.append_release("1.0.0", &status).is_err());
+    store.append_release(&format!("1.{num}"), &status).unwrap();
+
+    let reload = LifetimeStore::from_json(&store.to_json()).unwrap();
+    assert_eq!(reload.rustc_ver_num(), num + 1);
+    let status = reload.get_all_ruf_status("proc_macro_def_site");
+    assert_eq!(status[num], RufStatus::Accepted);
+    assert_eq!(
+        reload.get_ruf_status("proc_macro_mod", num as u32),
+        RufStatus::Unknown
+    );
+    assert_eq!(
+        reload.get_ruf_status("brand_new_ruf", 0),
+        RufStatus::Unknown
+    );
+    assert_eq!(
+        reload.get_ruf_status("brand_new_ruf", num as u32),
+        RufStatus::Active
+    );
+
+    // Nightly lookups, falling back to the stable release the nightly builds.
+    let date = |s: &str| s.parse::<Date>().unwrap();
+    let mut store = reload;
+    assert_eq!(nightly_minor_at(&date("2018-07-01")), 29);
+    assert_eq!(
+        store.get_ruf_status_at("proc_macro_mod", &date("2018-07-01")),
+        RufStatus::Active
+    );
+    let windows =
+        store.usable_nightlies(&["proc_macro_mod"], date("2018-01-01"), date("2019-12-31"));
+    assert_eq!(
+        windows,
+        vec![(nightly_window(27).unwrap().0, nightly_window(30).unwrap().1)]
+    );
+
+    // Nightly records override the stable releases.
+    store
+        .set_nightly(
+            "proc_macro_mod",
+            vec![
+                (date("2018-05-01"), RufStatus::Active),
+                (date("2018-06-01"), RufStatus::Removed),
+            ],
+        )
+        .unwrap();
+    let store = LifetimeStore::from_json(&store.to_json()).unwrap();
+    assert_eq!(
+        store.get_ruf_status_at("proc_macro_mod", &date("2018-06-01")),
+        RufStatus::Removed
+    );
+    let windows =
+        store.usable_nightlies(&["proc_macro_mod"], date("2018-01-01"), date("2019-12-31"));
+    assert_eq!(
+        windows,
+        vec![(nightly_window(27).unwrap().0, date("2018-05-31"))]
+    );
+
+    // The shipped nightly records, stabilized in the 1.62 nightlies.
+    let store = LifetimeStore::from_json(DEFAULT_LIFETIME).unwrap();
+    assert_eq!(nightly_minor_at(&date("2022-05-08")), 62);
+    assert_eq!(
+        store.get_ruf_status("bool_to_option", 62),
+        RufStatus::Accepted
+    );
+    assert_eq!(
+        store.get_ruf_status_at("bool_to_option", &date("2022-05-08")),
+        RufStatus::Active
+    );
+    assert_eq!(
+        store.get_ruf_status_at("bool_to_option", &date("2022-05-09")),
+        RufStatus::Accepted
+    );
+
+    // Broken overrides are errors.
+    env::set_var(LIFETIME_ENV, "/no/such/ruf_lifetime.json");
+    let res = load_lifetime();
+    env::remove_var(LIFETIME_ENV);
+    assert!(res.unwrap_err().contains(LIFETIME_ENV));
+    assert_eq!(load_lifetime().unwrap().rustc_ver_num(), num);
+}
@@ -0,0 +1,65 @@
+/// Ruf status in a rustc release, the discriminants are the codes in the lifetime data file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RufStatus {
+    Unknown = 0,
+    Active = 1,
+    Incomplete = 2,
+    Accepted = 3,
+    Removed = 4,
+}
+
+impl From<&str> for RufStatus {
+    fn from(value: &str) -> Self {
+        match value {
+            "active" => RufStatus::Active,
+            "incomplete" => RufStatus::Incomplete,
+            "accepted" => RufStatus::Accepted,
+            "removed" => RufStatus::Removed,
+            "" => RufStatus::Unknown,
+            _ => unreachable!("Fatal, unknown ruf status: {}", value),
+        }
+    }
+}
+
+impl From<u32> for RufStatus {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => RufStatus::Unknown,
+            1 => RufStatus::Active,
+            2 => RufStatus::Incomplete,
+            3 => RufStatus::Accepted,
+            4 => RufStatus::Removed,
+            _ => unreachable!("Fatal, unknown ruf status: {}", value),
+        }
+    }
+}
+
+impl RufStatus {
+    pub fn is_usable(&self) -> bool {
+        !matches!(self, Self::Removed | Self::Unknown)
+    }
+
+    /// The code in the lifetime data file.
+    pub fn code(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Parse the code in the lifetime data file.
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0..=4 => Some(Self::from(code as u32)),
+            _ => None,
+        }
+    }
+
+    /// Same as the `feature_timeline` table, empty for unknown.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unknown => "",
+            Self::Active => "active",
+            Self::Incomplete => "incomplete",
+            Self::Accepted => "accepted",
+            Self::Removed => "removed",
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use std::fs;
+use std::path::Path;
+
+use fxhash::FxHashMap;
+use serde::Deserialize;
+
+use super::{date, Date, RufStatus};
+
+/// Version of the lifetime data file format, bumped on incompatible changes.
+/// Version 2 adds the nightly status, files of version 1 are still accepted.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// The lifetime data file.
+///
+/// `rustc` lists the stable releases, the `i`th one is always `1.i.0`, and
+/// `lifetime` maps each ruf to its status codes (see [`RufStatus`]) in these releases.
+/// `nightly` optionally records the status changes of a ruf by nightly dates, as `[date, code]`
+/// pairs in date order, each status holds until the next change. Nightlies before the first change
+/// fall back to the stable releases.
+#[derive(Deserialize)]
+struct LifetimeFile {
+    format: u32,
+    rustc: Vec<String>,
+    lifetime: FxHashMap<String, Vec<u8>>,
+    #[serde(default)]
+    nightly: FxHashMap<String, Vec<(String, u8)>>,
+}
+
+/// Ruf lifetime over rustc releases, loaded from the data file at runtime.
+#[derive(Debug, Clone)]
+pub struct LifetimeStore {
+    rustc: Vec<String>,
+    lifetime: FxHashMap<String, Vec<RufStatus>>,
+    /// Status changes by nightly dates, for the rufs we know in nightly granularity.
+    nightly: FxHashMap<String, Vec<(Date, RufStatus)>>,
+}
+
+impl LifetimeStore {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("read ruf lifetime {} failed: {e}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let file: LifetimeFile =
+            serde_json::from_str(json).map_err(|e| format!("invalid ruf lifetime: {e}"))?;
+        if file.format == 0 || file.format > FORMAT_VERSION {
+            return Err(format!(
+                "unsupported ruf lifetime format {}, expect {FORMAT_VERSION}",
+                file.format
+            ));
+        }
+
+        for (i, rustc) in file.rustc.iter().enumerate() {
+            if *rustc != release_name(i) {
+                return Err(format!(
+                    "invalid ruf lifetime, expect rustc {} but found {rustc}",
+                    release_name(i)
+                ));
+            }
+        }
+
+        let mut lifetime = FxHashMap::default();
+        for (ruf, codes) in file.lifetime {
+            if codes.len() != file.rustc.len() {
+                return Err(format!(
+                    "invalid ruf lifetime of {ruf}, expect {} status but found {}",
+                    file.rustc.len(),
+                    codes.len()
+                ));
+            }
+            let status = codes
+                .iter()
+                .map(|code| {
+                    RufStatus::from_code(*code).ok_or(format!(
+                        "invalid ruf lifetime of {ruf}, unknown status {code}"
+                    ))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            lifetime.insert(ruf, status);
+        }
+
+        let mut store = Self {
+            rustc: file.rustc,
+            lifetime,
+            nightly: FxHashMap::default(),
+        };
+        for (ruf, changes) in file.nightly {
+            let changes = changes
+                .iter()
+                .map(|(date, code)| {
+                    let date = date
+                        .parse::<Date>()
+                        .map_err(|e| format!("invalid nightly lifetime of {ruf}: {e}"))?;
+                    let status = RufStatus::from_code(*code).ok_or(format!(
+                        "invalid nightly lifetime of {ruf}, unknown status {code}"
+                    ))?;
+                    Ok((date, status))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            store.set_nightly(&ruf, changes)?;
+        }
+
+        Ok(store)
+    }
+
+    /// One ruf per line, sorted, so that the data file diffs well.
+    pub fn to_json(&self) -> String {
+        let mut rufs = self.lifetime.iter().collect::<Vec<_>>();
+        rufs.sort_by(|a, b| a.0.cmp(b.0));
+
+        let rustc = self
+            .rustc
+            .iter()
+            .map(|rustc| format!("\"{rustc}\""))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let lifetime = rufs
+            .into_iter()
+            .map(|(ruf, status)| {
+                let codes = status
+                    .iter()
+                    .map(|s| s.code().to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                format!("{}: [{codes}]", serde_json::Value::from(ruf.as_str()))
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+
+        let mut rufs = self.nightly.iter().collect::<Vec<_>>();
+        rufs.sort_by(|a, b| a.0.cmp(b.0));
+        let nightly = rufs
+            .into_iter()
+            .map(|(ruf, changes)| {
+                let changes = changes
+                    .iter()
+                    .map(|(date, status)| format!("[\"{date}\",{}]", status.code()))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                format!("{}: [{changes}]", serde_json::Value::from(ruf.as_str()))
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let nightly = if nightly.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{\n{nightly}\n}}")
+        };
+
+        format!(
+            "{{\n\"format\": {FORMAT_VERSION},\n\"rustc\": [{rustc}],\n\"lifetime\": {{\n{lifetime}\n}},\n\"nightly\": {nightly}\n}}\n"
+        )
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_json())
+            .map_err(|e| format!("write ruf lifetime {} failed: {e}", path.display()))
+    }
+
+    /// Number of rustc releases covered, rustc `1.i.0` for `i` below it.
+    pub fn rustc_ver_num(&self) -> usize {
+        self.rustc.len()
+    }
+
+    pub fn rustc_versions(&self) -> &[String] {
+        &self.rustc
+    }
+
+    pub fn rufs(&self) -> impl Iterator<Item = &str> {
+        self.lifetime.keys().map(|ruf| ruf.as_str())
+    }
+
+    pub fn get_ruf_status(&self, ruf_name: &str, rustc_ver: u32) -> RufStatus {
+        if let Some(ruf_lifetime) = self.lifetime.get(ruf_name) {
+            assert!((rustc_ver as usize) < self.rustc_ver_num());
+            return ruf_lifetime[rustc_ver as usize];
+        }
+
+        RufStatus::Unknown
+    }
+
+    pub fn get_all_ruf_status(&self, ruf_name: &str) -> Vec<RufStatus> {
+        if let Some(ruf_lifetime) = self.lifetime.get(ruf_name) {
+            return ruf_lifetime.clone();
+        }
+
+        vec![RufStatus::Unknown; self.rustc_ver_num()]
+    }
+
+    /// Status of the ruf in the nightly of the date.
+    ///
+    /// Nightlies before the first nightly record of the ruf, or of rufs without them, fall back to
+    /// the stable release the nightly builds, and nightlies newer than the lifetime table take the
+    /// newest release.
+    pub fn get_ruf_status_at(&self, ruf_name: &str, date: &Date) -> RufStatus {
+        let recorded = self
+            .nightly
+            .get(ruf_name)
+            .and_then(|changes| changes.iter().rev().find(|(since, _)| since <= date));
+        if let Some((_, status)) = recorded {
+            return *status;
+        }
+
+        let minor = date::nightly_minor_at(date).min(self.rustc_ver_num() as u32 - 1);
+        self.get_ruf_status(ruf_name, minor)
+    }
+
+    /// Nightly status changes of the ruf, `None` if not recorded.
+    pub fn nightly_changes(&self, ruf_name: &str) -> Option<&[(Date, RufStatus)]> {
+        self.nightly.get(ruf_name).map(|changes| changes.as_slice())
+    }
+
+    /// Record the nightly status changes of the ruf, replacing the previous ones.
+    pub fn set_nightly(
+        &mut self,
+        ruf_name: &str,
+        mut changes: Vec<(Date, RufStatus)>,
+    ) -> Result<(), String> {
+        changes.sort_by_key(|(date, _)| *date);
+        if changes.windows(2).any(|w| w[0].0 == w[1].0) {
+            return Err(format!(
+                "invalid nightly lifetime of {ruf_name}, duplicated dates"
+            ));
+        }
+
+        self.nightly.insert(ruf_name.to_string(), changes);
+        Ok(())
+    }
+
+    /// The nightly windows from `from` to `to` (both inclusive) where all the rufs are usable.
+    pub fn usable_nightlies(&self, rufs: &[&str], from: Date, to: Date) -> Vec<(Date, Date)> {
+        if from > to {
+            return Vec::new();
+        }
+
+        // The status only changes at the nightly records or when a new stable is released.
+        let mut breaks = vec![from];
+        for ruf in rufs {
+            if let Some(changes) = self.nightly.get(*ruf) {
+                breaks.extend(changes.iter().map(|(date, _)| *date));
+            }
+        }
+        let (first, last) = (
+            date::stable_minor_at(&from).unwrap_or(0),
+            date::stable_minor_at(&to).unwrap_or(0),
+        );
+        breaks.extend((first..=last + 1).map(date::stable_release_date));
+        breaks.retain(|date| from <= *date && *date <= to);
+        breaks.sort();
+        breaks.dedup();
+
+        let mut windows: Vec<(Date, Date)> = Vec::new();
+        for (i, start) in breaks.iter().enumerate() {
+            let usable = rufs
+                .iter()
+                .all(|ruf| self.get_ruf_status_at(ruf, start).is_usable());
+            if !usable {
+                continue;
+            }
+
+            let end = breaks
+                .get(i + 1)
+                .map_or(to, |next| Date::from_days(next.days() - 1));
+            match windows.last_mut() {
+                Some(last) if last.1.days() + 1 == start.days() => last.1 = end,
+                _ => windows.push((*start, end)),
+            }
+        }
+
+        windows
+    }
+
+    /// Append the next rustc release, e.g. `1.64.0` after `1.63.0`.
+    /// Rufs not given are unknown in the release, and new rufs are unknown in all previous releases.
+    pub fn append_release(
+        &mut self,
+        rustc: &str,
+        status: &FxHashMap<String, RufStatus>,
+    ) -> Result<(), String> {
+        let expect = release_name(self.rustc_ver_num());
+        if rustc != expect && format!("{rustc}.0") != expect {
+            return Err(format!("cannot append rustc {rustc}, expect {expect}"));
+        }
+
+        for ruf in status.keys() {
+            if !self.lifetime.contains_key(ruf) {
+                self.lifetime
+                    .insert(ruf.clone(), vec![RufStatus::Unknown; self.rustc_ver_num()]);
+            }
+        }
+        for (ruf, ruf_lifetime) in self.lifetime.iter_mut() {
+            ruf_lifetime.push(status.get(ruf).copied().unwrap_or(RufStatus::Unknown));
+        }
+        self.rustc.push(expect);
+
+        Ok(())
+    }
+}
+
+fn release_name(minor: usize) -> String {
+    format!("1.{minor}.0")
+}
@@ -14,6 +14,9 @@
     ) sampled
     WHERE status = 'removed' OR status = 'unknown'
     LIMIT 700)
+
+    Summaries and errors are stored in JSONB, prebuild migrates virt_audit_results of old runs (TEXT),
+    keeping the old texts as JSON strings.
 */
 
 use std::{
@@ -30,8 +33,11 @@ use async_std::future::timeout;
 use crossbeam::channel;
 use log::{error, info};
 use postgres::{Client, NoTls};
+use serde_json::{json, Value};
 
-use ruf_audit_virtual::{treeonly_audit, AuditError, Summary};
+use ruf_audit_virtual::{
+    treeonly_audit, AuditError, AuditOptions, DepOpsVirt, Summary, TextObserver,
+};
 
 pub struct VersionInfo {
     pub version_id: i32,
@@ -63,8 +69,15 @@ pub fn run_audit_virt(workers: usize, status: &str) {
     println!("Creating Channel");
     let (tx, rx) = channel::bounded(workers);
 
+    // Crate metas are cached here, shared by all workers.
+    let cache = current_dir()
+        .expect("Failed to get current directory")
+        .join("virt_audit_cache");
+    let cache = Arc::new(cache.to_str().unwrap().to_string());
+
     let mut handles = Vec::new();
     for i in 0..workers {
+        let cache = Arc::clone(&cache);
         let conn = Arc::clone(&conn);
         let rx = rx.clone();
 
@@ -95,6 +108,7 @@ pub fn run_audit_virt(workers: usize, status: &str) {
                         &version.name,
                         &version.num,
                         workspace_str,
+                        &cache,
                         Arc::clone(&output),
                     )) {
                         Ok(Ok(Ok(summary))) => {
@@ -106,7 +120,7 @@ pub fn run_audit_virt(workers: usize, status: &str) {
                                 version.version_id,
                                 "success",
                                 Some(summary.fix_rustv),
-                                Some(&format!("{:?}", summary)),
+                                Some(summary.to_json()),
                                 None,
                                 Some(&output),
                                 duration,
@@ -117,12 +131,12 @@ pub fn run_audit_virt(workers: usize, status: &str) {
                         }
                         Ok(Ok(Err(e))) => {
                             let duration = start_time.elapsed();
-                            let (status, error) = match e {
-                                AuditError::InnerError(error) => ("inner fail", error),
-                                AuditError::FunctionError(_, _) => {
-                                    ("fix fail", "all methods failed".to_string())
-                                }
+                            let status = if e.is_inner() {
+                                "inner fail"
+                            } else {
+                                "fix fail"
                             };
+                            let error = serde_json::to_value(&e).unwrap();
                             let output = String::from_utf8(output.lock().unwrap().to_vec())
                                 .expect("cannot convert output to string");
                             store_audit_results(
@@ -131,7 +145,7 @@ pub fn run_audit_virt(workers: usize, status: &str) {
                                 status,
                                 None,
                                 None,
-                                Some(&error),
+                                Some(error),
                                 Some(&output),
                                 duration,
                             );
@@ -151,7 +165,7 @@ pub fn run_audit_virt(workers: usize, status: &str) {
                                 "panic",
                                 None,
                                 None,
-                                Some(e),
+                                Some(json!({ "kind": "panic", "message": e })),
                                 None,
                                 duration,
                             );
@@ -238,8 +252,8 @@ fn prebuild(conn: Arc<Mutex<Client>>) {
                     version_id INT PRIMARY KEY,
                     result VARCHAR,
                     fix_rustv INT,
-                    summary TEXT,
-                    error  TEXT,
+                    summary JSONB,
+                    error  JSONB,
                     output TEXT,
                     time_duration BIGINT
                 )"#
@@ -248,6 +262,29 @@ fn prebuild(conn: Arc<Mutex<Client>>) {
         )
         .unwrap();
 
+    // Old runs stored the summary and error in TEXT.
+    let old_columns = conn
+        .lock()
+        .unwrap()
+        .query(
+            r#"SELECT 1 FROM information_schema.columns
+                WHERE table_name = 'virt_audit_results' AND column_name IN ('summary', 'error') AND data_type = 'text'"#,
+            &[],
+        )
+        .unwrap();
+    if !old_columns.is_empty() {
+        info!("Migrating summary and error of virt_audit_results to JSONB");
+        conn.lock()
+            .unwrap()
+            .query(
+                r#"ALTER TABLE virt_audit_results
+                    ALTER COLUMN summary TYPE JSONB USING to_jsonb(summary),
+                    ALTER COLUMN error TYPE JSONB USING to_jsonb(error)"#,
+                &[],
+            )
+            .unwrap();
+    }
+
     conn.lock()
         .unwrap()
         .query(
@@ -262,8 +299,8 @@ fn store_audit_results(
     version_id: i32,
     result: &str,
     fix_rustv: Option<i32>,
-    summary: Option<&str>,
-    error: Option<&str>,
+    summary: Option<Value>,
+    error: Option<Value>,
     output: Option<&str>,
     time_duration: std::time::Duration,
 ) {
@@ -291,10 +328,19 @@ async fn limited_audit(
     name: &str,
     ver: &str,
     workspace: &str,
+    cache: &str,
     output: Arc<Mutex<Vec<u8>>>,
 ) -> Result<Result<Result<Summary, AuditError>, Box<dyn Any + Send>>, ()> {
     let result = timeout(Duration::from_secs(10 * 60), async {
-        panic::catch_unwind(|| treeonly_audit(name, ver, workspace, &mut *output.lock().unwrap()))
+        panic::catch_unwind(|| {
+            DepOpsVirt::new_cached(name, ver, workspace, cache).and_then(|ops| {
+                treeonly_audit(
+                    ops,
+                    &AuditOptions::default(),
+                    &mut TextObserver(&mut *output.lock().unwrap()),
+                )
+            })
+        })
     })
     .await;
 
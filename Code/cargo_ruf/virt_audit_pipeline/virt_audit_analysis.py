@@ -160,6 +160,18 @@ def parse_summary(summary_str: str) -> Dict:
     解析 Summary 字符串，提取 fix_rustv 和 fix_deps 信息。
     使用 pyparsing 进行可靠的解析。
     """
+    # JSONB 格式的 summary 已是 dict，直接读取
+    if isinstance(summary_str, dict):
+        fix_deps_dict = {
+            dep_name: [
+                (step["name"], step["from"], step["to"], step["rufs"]) for step in steps
+            ]
+            for dep_name, steps in summary_str["fix_deps"].items()
+        }
+        if not fix_deps_dict:
+            raise ValueError(f"fix_deps 不能为空: {summary_str}")
+        return {"fix_rustv": summary_str["fix_rustv"], "fix_deps": fix_deps_dict}
+
     # 定义基本元素
     LBRACE, RBRACE, LBRACKET, RBRACKET, LPAREN, RPAREN, COLON, COMMA = map(
         Suppress, "{}[]():,"
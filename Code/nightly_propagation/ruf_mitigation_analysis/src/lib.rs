@@ -1,36 +1,18 @@
-pub mod lifetime;
-use lifetime::{RUSTC_VER_NUM, get_lifetime_raw};
-use std::collections::HashMap;
+pub use ruf_lifetime::{lifetime, RufStatus};
 
 
-/// Get RUF status given the lifetime table:
-///     Input: lifetime:Rust Lifetime Table, ruf: query ruf name, rustc_version (0 represents rustc-1.0.0, 67 for rustc-1.67.0)
+/// Get RUF status from the shared lifetime table of the `ruf_lifetime` crate:
+///     Input: ruf: query ruf name, rustc_version (0 represents rustc-1.0.0, 67 for rustc-1.67.0)
 ///     Return: Some(&str) for existent RUF status, None for nonexistent rustc_version/ruf/status.
-pub fn get_ruf_status(lifetime: &HashMap<&'static str, [&'static str; RUSTC_VER_NUM]>, ruf: &str, rustc_version:usize) -> Option<&'static str> {
+pub fn get_ruf_status(ruf: &str, rustc_version:usize) -> Option<&'static str> {
     // No such rustc_version
-    if rustc_version >= RUSTC_VER_NUM {
+    if rustc_version >= ruf_lifetime::rustc_ver_num() {
         return None;
     }
-    if let Some(status_vec) = lifetime.get(ruf){
-        let status = status_vec[rustc_version];
-        // RUF not defined in the version
-        if status == "None" {
-            return None;
-        }
+    match ruf_lifetime::get_ruf_status(ruf, rustc_version as u32) {
+        // RUF not defined in the version, or no RUF found
+        RufStatus::Unknown => None,
         // RUF defined
-        else{
-            return Some(status);
-        }
+        status => Some(status.as_str()),
     }
-    // No RUF found
-    None
 }
-
-/// Get RUF Lifetime table:
-///     Data structure: HashMap< RUF Name, Status[rustc_version]>
-///     Access: status = HashMap[RUF].[rustc_version]
-///     Return: The RUF status of given RUF in the specific rustc_version (0 represents rustc-1.0.0, 67 for rustc-1.67.0). 
-pub fn get_lifetime () -> HashMap<&'static str, [&'static str; RUSTC_VER_NUM]> {
-    get_lifetime_raw()
-}
-
@@ -4,10 +4,8 @@ use std::path::Path;
 use std::fs::{create_dir, remove_dir_all, File, OpenOptions};
 use std::io::prelude::*;
 
-use RUF_mitigation::{get_ruf_status, get_lifetime};
-use lifetime::RUSTC_VER_NUM;
+use RUF_mitigation::{get_ruf_status, lifetime};
 use postgres::{Client, NoTls};
-mod lifetime;
 
 const MAX_RUSTC_VERSION:usize = 63; // 1.0.0 -> 1.63.0
 const RESULTSFILE:&str = "./mitigation_results.csv";
@@ -28,9 +26,10 @@ fn main() {
         )
         .unwrap(),
     ));
+    // Fail early if the lifetime given by `RUF_LIFETIME` cannot be loaded.
+    lifetime().unwrap();
     println!("Build RUF Impact Table (May take minutes)...");
     let ruf_impacts = get_ruf_impact(Arc::clone(&conn));
-    let ruf_lifetime = get_lifetime();
     init_mitigation_results_db(Arc::clone(&conn));
     println!("Simulate Mitigation Process...");
     // println!("count {:#?}", ruf_impacts);
@@ -49,7 +48,7 @@ fn main() {
             println!("Processing {count}th package version...");
         }  
         // Before Mitigation
-        let before_status = get_version_ruf_status(ruf_impact, MAX_RUSTC_VERSION , &ruf_lifetime);
+        let before_status = get_version_ruf_status(ruf_impact, MAX_RUSTC_VERSION);
         match before_status {
             "failure"   => before_count_failure += 1,
             "unstable"  => before_count_unstable += 1,
@@ -57,7 +56,7 @@ fn main() {
             _ => (),
         };
         // After Mitigation
-        let (after_status, recovery_point) = get_version_ruf_status_all(ruf_impact, &ruf_lifetime);
+        let (after_status, recovery_point) = get_version_ruf_status_all(ruf_impact);
         match after_status {
             "failure"   => after_count_failure += 1,
             "unstable"  => after_count_unstable += 1,
@@ -119,14 +118,14 @@ fn get_ruf_impact(conn: Arc<Mutex<Client>>) -> HashMap<i32, Vec<String>> {
 
 
 /// Return the best ruf status through all rustc version where specific package version is using. This represents that the version can be safety recovered.
-///     Arg: ruf_impact: Vec<RUF>, rustc_version: Specify rustc_version where RUF is running, lifetime_table: RUF lifetime
+///     Arg: ruf_impact: Vec<RUF>, rustc_version: Specify rustc_version where RUF is running
 ///     Return: Worst ruf status. Can be "stable", "unstable" (including "active" and "imcomplete"), "failure" (including "removed" and "unknown").
 ///             Also, return the recovery point.
-fn get_version_ruf_status_all(ruf_impact: &Vec<String>, lifetime_table: &HashMap<&'static str, [&'static str; RUSTC_VER_NUM]> ) -> (&'static str, usize){
+fn get_version_ruf_status_all(ruf_impact: &Vec<String>) -> (&'static str, usize){
     let mut final_status = "failure";
     let mut recovery_point = MAX_RUSTC_VERSION;
     for i in (0..(MAX_RUSTC_VERSION + 1)).rev(){
-        let status = get_version_ruf_status(ruf_impact, i, lifetime_table);
+        let status = get_version_ruf_status(ruf_impact, i);
         match status {
             "stable" => return ("stable", i),
             "unstable" => {
@@ -142,12 +141,12 @@ fn get_version_ruf_status_all(ruf_impact: &Vec<String>, lifetime_table: &HashMap
 }
 
 /// Return the worst ruf status (given rustc version) where specific package version is using. This represents that whether the version can be safety used.
-///     Arg: ruf_impact: Vec<RUF>, rustc_version: Specify rustc_version where RUF is running, lifetime_table: RUF lifetime
+///     Arg: ruf_impact: Vec<RUF>, rustc_version: Specify rustc_version where RUF is running
 ///     Return: Worst ruf status. Can be "stable", "unstable" (including "active" and "imcomplete"), "failure" (including "removed" and "unknown").
-fn get_version_ruf_status(ruf_impact: &Vec<String>, rustc_version:usize, lifetime_table: &HashMap<&'static str, [&'static str; RUSTC_VER_NUM]> ) -> &'static str{
+fn get_version_ruf_status(ruf_impact: &Vec<String>, rustc_version:usize) -> &'static str{
     let mut status = "stable";
     for ruf in ruf_impact{
-        if let Some(ruf_status) = get_ruf_status(lifetime_table, ruf.as_str(), rustc_version){
+        if let Some(ruf_status) = get_ruf_status(ruf.as_str(), rustc_version){
             if ruf_status == "active" || ruf_status == "imcomplete" {
                 status = "unstable";
             }